@@ -0,0 +1,87 @@
+//! The ELF class and machine a binary is built for.
+//!
+//! The dynamic loader expands a few tokens in `DT_RPATH`/`DT_RUNPATH` from the
+//! process ABI rather than from anything the binary says: `$LIB` is the
+//! loader's library directory name and `$PLATFORM` the kernel's `AT_PLATFORM`.
+//! The firmware runs a binary under the ABI its header names, so that header is
+//! enough to know what those tokens become on the TV.
+//!
+//! A firmware with a mixed 32/64-bit userland has a library of the same name
+//! for each class, so the firmware index keys libraries by [`ElfArch::tag`].
+
+use elf::endian::{AnyEndian, EndianParse};
+use elf::file::{Class, FileHeader};
+use elf::{ElfStream, ParseError, abi};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ElfClass {
+    Elf32,
+    Elf64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ElfArch {
+    pub class: ElfClass,
+    /// The `e_machine` value, e.g. `EM_ARM` or `EM_AARCH64`.
+    pub machine: u16,
+}
+
+impl ElfArch {
+    pub(crate) fn of<E: EndianParse>(ehdr: &FileHeader<E>) -> Self {
+        return Self {
+            class: match ehdr.class {
+                Class::ELF32 => ElfClass::Elf32,
+                Class::ELF64 => ElfClass::Elf64,
+            },
+            machine: ehdr.e_machine,
+        };
+    }
+
+    /// Read the class and machine from the header of an ELF file.
+    pub fn read<S>(source: S) -> Result<Self, ParseError>
+    where
+        S: std::io::Read + std::io::Seek,
+    {
+        let elf = ElfStream::<AnyEndian, S>::open_stream(source)?;
+        return Ok(Self::of(&elf.ehdr));
+    }
+
+    /// A short name for the class and machine, as in `elf32-arm` or
+    /// `elf64-aarch64`. A machine we have no name for is given by number.
+    pub fn tag(self) -> String {
+        let class = match self.class {
+            ElfClass::Elf32 => "elf32",
+            ElfClass::Elf64 => "elf64",
+        };
+        return match self.machine {
+            abi::EM_ARM => format!("{class}-arm"),
+            abi::EM_AARCH64 => format!("{class}-aarch64"),
+            abi::EM_386 => format!("{class}-i386"),
+            abi::EM_X86_64 => format!("{class}-x86_64"),
+            machine => format!("{class}-{machine}"),
+        };
+    }
+
+    /// What the loader substitutes for `$LIB`. glibc uses its own `slibdir`
+    /// name, which is `lib64` for a 64-bit loader and `lib` otherwise.
+    pub fn lib_dir(self) -> &'static str {
+        return match self.class {
+            ElfClass::Elf32 => "lib",
+            ElfClass::Elf64 => "lib64",
+        };
+    }
+
+    /// What the loader substitutes for `$PLATFORM`: the `AT_PLATFORM` string
+    /// the kernel passes in. Every webOS TV with a 32-bit userland is `ARMv7`, so
+    /// an ARM binary sees `v7l`. `None` for a machine we have no value for.
+    pub fn platform(self) -> Option<&'static str> {
+        return match self.machine {
+            abi::EM_ARM => Some("v7l"),
+            abi::EM_AARCH64 => Some("aarch64"),
+            abi::EM_386 => Some("i686"),
+            abi::EM_X86_64 => Some("x86_64"),
+            _ => None,
+        };
+    }
+}
@@ -0,0 +1,396 @@
+//! The CPU an ARM binary is built for, from its `.ARM.attributes` section.
+//!
+//! The compiler records the architecture and FPU it targeted (`-march`,
+//! `-mfpu`) and the float calling convention (`-mfloat-abi`). A binary built
+//! for more than the CPU has dies on its first unknown instruction with
+//! `SIGILL`, even when every symbol resolves. One built for the other float ABI
+//! passes floating-point arguments where the firmware's libraries do not look.
+
+use std::fmt::{Display, Formatter};
+
+use elf::endian::{AnyEndian, EndianParse};
+use elf::{ElfStream, ParseError, abi};
+use serde::{Deserialize, Serialize};
+
+const TAG_FILE: u8 = 1;
+const TAG_CPU_RAW_NAME: u64 = 4;
+const TAG_CPU_NAME: u64 = 5;
+const TAG_CPU_ARCH: u64 = 6;
+const TAG_FP_ARCH: u64 = 10;
+const TAG_ADVANCED_SIMD_ARCH: u64 = 12;
+const TAG_ABI_VFP_ARGS: u64 = 28;
+const TAG_COMPATIBILITY: u64 = 32;
+const TAG_ALSO_COMPATIBLE_WITH: u64 = 65;
+const TAG_CONFORMANCE: u64 = 67;
+
+/// The attributes that say what CPU and float ABI a binary needs. Each is the
+/// raw tag value, or `None` when the binary does not set it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ArmAttributes {
+    /// `Tag_CPU_arch`: 10 for `ARMv7`, 14 for `ARMv8-A`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub cpu_arch: Option<u64>,
+    /// `Tag_FP_arch`: 3 for `VFPv3`, 5 for `VFPv4`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub fp_arch: Option<u64>,
+    /// `Tag_Advanced_SIMD_arch`: 1 for NEON, 2 for NEON with fused
+    /// multiply-add.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub simd_arch: Option<u64>,
+    /// `Tag_ABI_VFP_args`: 0 passes floats in core registers (soft-float or
+    /// `softfp`), 1 in VFP registers (hard-float), 3 uses neither.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub vfp_args: Option<u64>,
+}
+
+/// The float calling convention a binary uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatAbi {
+    /// Floating-point arguments in core registers: `soft` or `softfp`.
+    Soft,
+    /// Floating-point arguments in VFP registers: `hard`.
+    Hard,
+}
+
+impl Display for FloatAbi {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return f.write_str(match self {
+            FloatAbi::Soft => "soft-float",
+            FloatAbi::Hard => "hard-float",
+        });
+    }
+}
+
+impl ArmAttributes {
+    /// Read the attributes of an ARM binary. `None` for another machine, or
+    /// an ARM binary without the section.
+    pub fn read<S>(source: S) -> Result<Option<ArmAttributes>, ParseError>
+    where
+        S: std::io::Read + std::io::Seek,
+    {
+        let mut elf = ElfStream::<AnyEndian, S>::open_stream(source)?;
+        return arm_attributes(&mut elf);
+    }
+
+    /// The float calling convention. A binary without the tag uses the base
+    /// one. `None` when it passes no floats, or uses a custom convention.
+    pub fn float_abi(&self) -> Option<FloatAbi> {
+        return match self.vfp_args.unwrap_or(0) {
+            0 => Some(FloatAbi::Soft),
+            1 => Some(FloatAbi::Hard),
+            _ => None,
+        };
+    }
+
+    /// The attributes of a CPU that runs code built for both. The float ABI
+    /// is taken from `self`, unless it has none.
+    #[must_use]
+    pub fn max(self, other: ArmAttributes) -> ArmAttributes {
+        let fp_arch = match (self.fp_arch, other.fp_arch) {
+            (Some(a), Some(b)) if fp_covers(b, a) => Some(b),
+            (Some(a), _) => Some(a),
+            (None, b) => b,
+        };
+        return ArmAttributes {
+            cpu_arch: self.cpu_arch.max(other.cpu_arch),
+            fp_arch,
+            simd_arch: self.simd_arch.max(other.simd_arch),
+            vfp_args: self.vfp_args.or(other.vfp_args),
+        };
+    }
+
+    /// What the binary needs beyond a CPU described by `baseline`, one
+    /// sentence each. Empty when it runs there.
+    pub fn exceeds(&self, baseline: &ArmAttributes) -> Vec<String> {
+        let mut reasons = Vec::new();
+        if let (Some(needed), Some(has)) = (self.cpu_arch, baseline.cpu_arch) {
+            if needed > has {
+                reasons.push(format!(
+                    "needs {}, but the firmware is built for {}",
+                    cpu_arch_name(needed),
+                    cpu_arch_name(has)
+                ));
+            }
+        }
+        if let (Some(needed), Some(has)) = (self.fp_arch, baseline.fp_arch) {
+            if !fp_covers(has, needed) {
+                reasons.push(format!(
+                    "needs {}, but the firmware is built for {}",
+                    fp_arch_name(needed),
+                    fp_arch_name(has)
+                ));
+            }
+        }
+        if let Some(needed) = self.simd_arch.filter(|simd| *simd > 0) {
+            let has = baseline.simd_arch.unwrap_or(0);
+            if needed > has {
+                reasons.push(format!(
+                    "needs {}, but the firmware is built for {}",
+                    simd_arch_name(needed),
+                    simd_arch_name(has)
+                ));
+            }
+        }
+        if let (Some(needed), Some(has)) = (self.float_abi(), baseline.float_abi()) {
+            if needed != has {
+                reasons.push(format!("is {needed}, but the firmware is {has}"));
+            }
+        }
+        return reasons;
+    }
+}
+
+pub(crate) fn arm_attributes<E, S>(
+    elf: &mut ElfStream<E, S>,
+) -> Result<Option<ArmAttributes>, ParseError>
+where
+    E: EndianParse,
+    S: std::io::Read + std::io::Seek,
+{
+    if elf.ehdr.e_machine != abi::EM_ARM {
+        return Ok(None);
+    }
+    let big_endian = !elf.ehdr.endianness.is_little();
+    let Some(section) = elf.section_header_by_name(".ARM.attributes")?.copied() else {
+        return Ok(None);
+    };
+    let (data, _) = elf.section_data(&section)?;
+    return Ok(parse_attributes(data, big_endian));
+}
+
+/// Read the `aeabi` file attributes. `None` when the data is malformed.
+fn parse_attributes(data: &[u8], big_endian: bool) -> Option<ArmAttributes> {
+    let read_u32 = |bytes: &[u8]| -> Option<usize> {
+        let bytes: [u8; 4] = bytes.get(..4)?.try_into().ok()?;
+        let value = if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        };
+        return usize::try_from(value).ok();
+    };
+    let (&version, mut rest) = data.split_first()?;
+    if version != b'A' {
+        return None;
+    }
+    let mut attributes = ArmAttributes::default();
+    while !rest.is_empty() {
+        let length = read_u32(rest)?;
+        let section = rest.get(4..length)?;
+        rest = &rest[length..];
+        let vendor_end = section.iter().position(|b| *b == 0)?;
+        if &section[..vendor_end] != b"aeabi" {
+            continue;
+        }
+        let mut subsections = &section[vendor_end + 1..];
+        while !subsections.is_empty() {
+            let tag = subsections[0];
+            let size = read_u32(subsections.get(1..)?)?;
+            let body = subsections.get(5..size)?;
+            subsections = &subsections[size..];
+            if tag == TAG_FILE {
+                read_file_attributes(body, &mut attributes)?;
+            }
+        }
+    }
+    return Some(attributes);
+}
+
+fn read_file_attributes(mut body: &[u8], attributes: &mut ArmAttributes) -> Option<()> {
+    while !body.is_empty() {
+        let tag = read_uleb128(&mut body)?;
+        match tag {
+            TAG_CPU_RAW_NAME | TAG_CPU_NAME | TAG_ALSO_COMPATIBLE_WITH | TAG_CONFORMANCE => {
+                skip_string(&mut body)?;
+            }
+            TAG_COMPATIBILITY => {
+                read_uleb128(&mut body)?;
+                skip_string(&mut body)?;
+            }
+            // Above 32, odd tags are strings and even ones numbers.
+            tag if tag > TAG_COMPATIBILITY && tag % 2 == 1 => skip_string(&mut body)?,
+            tag => {
+                let value = read_uleb128(&mut body)?;
+                match tag {
+                    TAG_CPU_ARCH => attributes.cpu_arch = Some(value),
+                    TAG_FP_ARCH => attributes.fp_arch = Some(value),
+                    TAG_ADVANCED_SIMD_ARCH => attributes.simd_arch = Some(value),
+                    TAG_ABI_VFP_ARGS => attributes.vfp_args = Some(value),
+                    _ => {}
+                }
+            }
+        }
+    }
+    return Some(());
+}
+
+fn read_uleb128(data: &mut &[u8]) -> Option<u64> {
+    let mut value: u64 = 0;
+    for (i, byte) in data.iter().enumerate().take(10) {
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            *data = &data[i + 1..];
+            return Some(value);
+        }
+    }
+    return None;
+}
+
+fn skip_string(data: &mut &[u8]) -> Option<()> {
+    let end = data.iter().position(|b| *b == 0)?;
+    *data = &data[end + 1..];
+    return Some(());
+}
+
+/// An FPU's version and whether it has all 32 double registers, from
+/// `Tag_FP_arch`.
+fn fp_rank(fp_arch: u64) -> (u64, bool) {
+    return match fp_arch {
+        3 => (3, true),
+        4 => (3, false),
+        5 => (4, true),
+        6 => (4, false),
+        7 => (5, true),
+        8 => (5, false),
+        version => (version, false),
+    };
+}
+
+/// Whether an FPU `has` runs code built for `needed`.
+fn fp_covers(has: u64, needed: u64) -> bool {
+    let (has_version, has_d32) = fp_rank(has);
+    let (needed_version, needed_d32) = fp_rank(needed);
+    return needed_version <= has_version && (has_d32 || !needed_d32);
+}
+
+/// The architecture a `Tag_CPU_arch` value names.
+pub fn cpu_arch_name(cpu_arch: u64) -> String {
+    let name = match cpu_arch {
+        0 => "pre-ARMv4",
+        1 => "ARMv4",
+        2 => "ARMv4T",
+        3 => "ARMv5T",
+        4 => "ARMv5TE",
+        5 => "ARMv5TEJ",
+        6 => "ARMv6",
+        7 => "ARMv6KZ",
+        8 => "ARMv6T2",
+        9 => "ARMv6K",
+        10 => "ARMv7",
+        11 => "ARMv6-M",
+        12 => "ARMv6S-M",
+        13 => "ARMv7E-M",
+        14 => "ARMv8-A",
+        15 => "ARMv8-R",
+        16 => "ARMv8-M baseline",
+        17 => "ARMv8-M mainline",
+        18 => "ARMv8.1-A",
+        19 => "ARMv8.2-A",
+        20 => "ARMv8.3-A",
+        21 => "ARMv8.1-M mainline",
+        22 => "ARMv9-A",
+        other => return format!("CPU architecture {other}"),
+    };
+    return String::from(name);
+}
+
+/// The FPU a `Tag_FP_arch` value names.
+pub fn fp_arch_name(fp_arch: u64) -> String {
+    let name = match fp_arch {
+        0 => "no FPU",
+        1 => "VFPv1",
+        2 => "VFPv2",
+        3 => "VFPv3",
+        4 => "VFPv3-D16",
+        5 => "VFPv4",
+        6 => "VFPv4-D16",
+        7 => "ARMv8 FP",
+        8 => "ARMv8 FP-D16",
+        other => return format!("FPU architecture {other}"),
+    };
+    return String::from(name);
+}
+
+/// The SIMD extension a `Tag_Advanced_SIMD_arch` value names.
+pub fn simd_arch_name(simd_arch: u64) -> String {
+    let name = match simd_arch {
+        0 => "no NEON",
+        1 => "NEON",
+        2 => "NEON with FMA",
+        3 => "ARMv8 NEON",
+        4 => "ARMv8.1 NEON",
+        other => return format!("SIMD architecture {other}"),
+    };
+    return String::from(name);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::{ArmAttributes, FloatAbi, parse_attributes};
+
+    /// An `aeabi` section with the given file attributes, as (tag, value)
+    /// pairs of single-byte ULEB128 numbers.
+    fn section(pairs: &[(u8, u8)]) -> Vec<u8> {
+        let mut body: Vec<u8> = vec![5];
+        body.extend_from_slice(b"7-A\0");
+        for (tag, value) in pairs {
+            body.extend_from_slice(&[*tag, *value]);
+        }
+        let file_len = u32::try_from(body.len() + 5).unwrap();
+        let mut section = b"aeabi\0".to_vec();
+        section.push(1);
+        section.extend_from_slice(&file_len.to_le_bytes());
+        section.extend_from_slice(&body);
+        let section_len = u32::try_from(section.len() + 4).unwrap();
+        let mut data = vec![b'A'];
+        data.extend_from_slice(&section_len.to_le_bytes());
+        data.extend_from_slice(&section);
+        return data;
+    }
+
+    #[test]
+    fn reads_the_fixture() {
+        let attributes = ArmAttributes::read(Cursor::new(include_bytes!("fixtures/sample.bin")))
+            .unwrap()
+            .expect("an ARM binary");
+        assert_eq!(attributes.cpu_arch, Some(10));
+        assert_eq!(attributes.fp_arch, Some(3));
+        assert_eq!(attributes.float_abi(), Some(FloatAbi::Soft));
+
+        let other = ArmAttributes::read(Cursor::new(include_bytes!("fixtures/exe_rdynamic.bin")));
+        assert_eq!(other.unwrap(), None);
+    }
+
+    #[test]
+    fn compares_with_a_baseline() {
+        let baseline = parse_attributes(&section(&[(6, 10), (10, 3), (12, 1)]), false).unwrap();
+        let armv8 = parse_attributes(&section(&[(6, 14), (10, 7), (12, 3)]), false).unwrap();
+        let d16 = parse_attributes(&section(&[(6, 10), (10, 4)]), false).unwrap();
+        let hard = parse_attributes(&section(&[(6, 10), (28, 1)]), false).unwrap();
+
+        assert_eq!(
+            armv8.exceeds(&baseline),
+            vec![
+                "needs ARMv8-A, but the firmware is built for ARMv7",
+                "needs ARMv8 FP, but the firmware is built for VFPv3",
+                "needs ARMv8 NEON, but the firmware is built for NEON",
+            ]
+        );
+        assert!(d16.exceeds(&baseline).is_empty());
+        assert_eq!(
+            baseline.exceeds(&d16),
+            vec![
+                "needs VFPv3, but the firmware is built for VFPv3-D16",
+                "needs NEON, but the firmware is built for no NEON",
+            ]
+        );
+        assert_eq!(
+            hard.exceeds(&baseline),
+            vec!["is hard-float, but the firmware is soft-float"]
+        );
+        assert_eq!(baseline.max(armv8).cpu_arch, Some(14));
+        assert_eq!(d16.max(baseline).fp_arch, Some(3));
+    }
+}
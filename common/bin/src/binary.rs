@@ -1,12 +1,22 @@
+use std::collections::HashSet;
+
 use elf::dynamic::Dyn;
 use elf::endian::AnyEndian;
 use elf::symbol::Symbol;
 use elf::{ElfStream, abi};
 
-use crate::BinaryInfo;
-use crate::reloc::lazy_bound_symbols;
+use crate::attributes::arm_attributes;
+use crate::dlopen::dlopen_names;
+use crate::library::{defined_symbols, has_symbol};
+use crate::reloc::{copy_relocated_symbols, lazy_bound_symbols};
+use crate::{BinaryInfo, ElfArch};
 
 impl BinaryInfo {
+    /// Whether the binary exports `symbol`. See [`crate::LibraryInfo::has_symbol`].
+    pub fn has_symbol(&self, symbol: &str) -> bool {
+        return has_symbol(&self.symbols, symbol);
+    }
+
     pub fn parse<S, N>(source: S, name: N, with_rpath: bool) -> Result<Self, elf::ParseError>
     where
         S: std::io::Read + std::io::Seek,
@@ -15,6 +25,7 @@ impl BinaryInfo {
         let mut rpath = Vec::<String>::new();
         let mut needed = Vec::<String>::new();
         let mut elf = ElfStream::<AnyEndian, S>::open_stream(source)?;
+        let arch = ElfArch::of(&elf.ehdr);
 
         let dynamic_entries: Vec<Dyn> = elf
             .dynamic()?
@@ -47,6 +58,9 @@ impl BinaryInfo {
         }
 
         let lazy_syms = lazy_bound_symbols(&mut elf, &dynamic_entries)?;
+        let copied_syms = copy_relocated_symbols(&mut elf)?;
+        let dlopen = dlopen_names(&mut elf)?;
+        let arm = arm_attributes(&mut elf)?;
 
         let symbols: Vec<(Symbol, String)> = match elf.dynamic_symbol_table()? {
             Some((sym_table, str)) => sym_table
@@ -61,6 +75,15 @@ impl BinaryInfo {
             None => Vec::new(),
         };
         let ver_table = elf.symbol_version_table()?;
+        let mut defined = defined_symbols(&symbols, ver_table.as_ref());
+        // A copy of a library's data object is the library's definition.
+        let copied: HashSet<&str> = copied_syms
+            .iter()
+            .filter_map(|index| symbols.get(*index))
+            .map(|(_, name)| name.as_str())
+            .collect();
+        defined.retain(|def| !copied.contains(def.split('@').next().unwrap_or_default()));
+        defined.sort_unstable();
 
         let mut undefined = Vec::<String>::new();
         let mut undefined_lazy = Vec::<String>::new();
@@ -88,6 +111,10 @@ impl BinaryInfo {
             needed,
             undefined,
             undefined_lazy,
+            symbols: defined,
+            arch: Some(arch),
+            dlopen,
+            arm,
         });
     }
 }
@@ -106,6 +133,50 @@ mod tests {
         assert_eq!(info.needed[0], "libc.so.6");
     }
 
+    /// `exe_rdynamic.bin` is an x86-64 build of a two-function program, linked
+    /// with `gcc -O2 -rdynamic -s` so that both functions are exported.
+    #[test]
+    fn collects_the_exported_symbols() {
+        let mut content = Cursor::new(include_bytes!("fixtures/exe_rdynamic.bin"));
+        let info = BinaryInfo::parse(&mut content, "exe_rdynamic.bin", true)
+            .expect("should not have any error");
+        assert!(info.has_symbol("host_api"), "got {:?}", info.symbols);
+        assert!(
+            !info.has_symbol("__libc_start_main"),
+            "an import is not an export"
+        );
+        // The linker's section markers are left out, as for a library.
+        assert!(!info.has_symbol("_edata"));
+
+        // A program linked without `-rdynamic` exports nothing.
+        let mut content = Cursor::new(include_bytes!("fixtures/sample.bin"));
+        let info =
+            BinaryInfo::parse(&mut content, "sample.bin", true).expect("should not have any error");
+        assert!(info.symbols.is_empty(), "got {:?}", info.symbols);
+    }
+
+    #[test]
+    fn reads_the_arch_from_the_header() {
+        let mut content = Cursor::new(include_bytes!("fixtures/sample.bin"));
+        let info =
+            BinaryInfo::parse(&mut content, "sample.bin", true).expect("should not have any error");
+        let arch = info.arch.expect("parsed from a file");
+        assert_eq!(arch.lib_dir(), "lib");
+        assert_eq!(arch.platform(), Some("v7l"));
+    }
+
+    /// `exe_copy.bin` is an x86-64 build of a program reading `stdout` and
+    /// `optarg`, linked with `gcc -O2 -no-pie -fno-pic -s`. Both are copy
+    /// relocated into the executable, which still does not define them.
+    #[test]
+    fn leaves_out_copy_relocated_objects() {
+        let mut content = Cursor::new(include_bytes!("fixtures/exe_copy.bin"));
+        let info = BinaryInfo::parse(&mut content, "exe_copy.bin", true)
+            .expect("should not have any error");
+        assert!(!info.has_symbol("stdout"), "got {:?}", info.symbols);
+        assert!(!info.has_symbol("optarg"), "got {:?}", info.symbols);
+    }
+
     /// The fixture calls `puts`/`abort` through the PLT and does not force eager
     /// binding, so every import lands in the lazy list.
     #[test]
@@ -0,0 +1,70 @@
+//! Shared objects a binary may load at run time.
+//!
+//! A library passed to `dlopen` is in no `DT_NEEDED` entry, so only its name
+//! gives it away: a string constant such as `"libwayland-client.so.0"` in the
+//! read-only data. This scan finds those names. It cannot tell a name that is
+//! loaded from one that is only printed, so callers treat the result as a hint.
+
+use elf::endian::EndianParse;
+use elf::{ElfStream, ParseError};
+
+/// The shared object names in the binary's `.rodata`, sorted and without
+/// duplicates. A path keeps only its file name.
+pub(crate) fn dlopen_names<E, S>(elf: &mut ElfStream<E, S>) -> Result<Vec<String>, ParseError>
+where
+    E: EndianParse,
+    S: std::io::Read + std::io::Seek,
+{
+    let Some(rodata) = elf.section_header_by_name(".rodata")?.copied() else {
+        return Ok(Vec::new());
+    };
+    let (data, _) = elf.section_data(&rodata)?;
+    let mut names: Vec<String> = data
+        .split(|b| !b.is_ascii_graphic())
+        .filter_map(|run| std::str::from_utf8(run).ok())
+        .map(|run| run.rsplit('/').next().unwrap_or(run))
+        .filter(|name| is_soname(name))
+        .map(String::from)
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    return Ok(names);
+}
+
+/// Whether `name` looks like `libfoo.so` or `libfoo.so.1.2`. Format strings
+/// and anything with other characters are left out.
+fn is_soname(name: &str) -> bool {
+    let Some((stem, version)) = name.split_once(".so") else {
+        return false;
+    };
+    if stem.is_empty()
+        || stem.starts_with('.')
+        || !stem
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+' | '.'))
+    {
+        return false;
+    }
+    return version.is_empty()
+        || version.strip_prefix('.').is_some_and(|v| {
+            !v.is_empty() && v.split('.').all(|part| part.parse::<u32>().is_ok())
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_soname;
+
+    #[test]
+    fn recognises_shared_object_names() {
+        assert!(is_soname("libwayland-client.so.0"));
+        assert!(is_soname("libGLESv2.so"));
+        assert!(is_soname("libstdc++.so.6"));
+        assert!(is_soname("libgstcoreelements.so"));
+        assert!(!is_soname("lib%s.so"));
+        assert!(!is_soname(".so"));
+        assert!(!is_soname("libfoo.so.x"));
+        assert!(!is_soname("libfoo.soname"));
+        assert!(!is_soname("libfoo.so."));
+    }
+}
@@ -1,11 +1,16 @@
 use serde::{Deserialize, Serialize};
 
+pub mod arch;
 pub mod artifact;
+pub mod attributes;
 pub mod binary;
+mod dlopen;
 pub mod library;
 mod reloc;
 
+pub use arch::{ElfArch, ElfClass};
 pub use artifact::{ArtifactKind, BundledArtifact};
+pub use attributes::{ArmAttributes, FloatAbi};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BinaryInfo {
@@ -18,6 +23,23 @@ pub struct BinaryInfo {
     /// than a failure. See [`crate::reloc::lazy_bound_symbols`].
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub undefined_lazy: Vec<String>,
+    /// The dynamic symbols the binary defines, sorted. An executable linked
+    /// with `-rdynamic` exports most of its own functions, and the libraries
+    /// and plugins it loads may import them.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub symbols: Vec<String>,
+    /// The class and machine from the ELF header. `None` when the info was not
+    /// read from a file.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub arch: Option<ElfArch>,
+    /// Shared object names in the read-only data, which the binary may pass
+    /// to `dlopen`. See [`crate::dlopen`].
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub dlopen: Vec<String>,
+    /// The CPU and float ABI an ARM binary is built for. See
+    /// [`crate::attributes`].
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub arm: Option<ArmAttributes>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -34,6 +56,14 @@ pub struct LibraryInfo {
     /// Imports the loader binds lazily. See [`BinaryInfo::undefined_lazy`].
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     pub undefined_lazy: Vec<String>,
+    /// Names the library may pass to `dlopen`, read only along with its
+    /// imports. See [`BinaryInfo::dlopen`].
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub dlopen: Vec<String>,
+    /// The CPU and float ABI it is built for, read only along with its
+    /// imports. See [`BinaryInfo::arm`].
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub arm: Option<ArmAttributes>,
     #[serde(skip_serializing, default)]
     pub rpath: Vec<String>,
     #[serde(skip_serializing, default = "LibraryPriority::default")]
@@ -2,14 +2,17 @@ use std::cmp::Ordering;
 use std::collections::HashSet;
 
 use elf::dynamic::Dyn;
-use elf::endian::AnyEndian;
+use elf::endian::{AnyEndian, EndianParse};
+use elf::gnu_symver::SymbolVersionTable;
 use elf::symbol::Symbol;
 use elf::{ElfStream, ParseError, abi};
 
+use crate::attributes::arm_attributes;
+use crate::dlopen::dlopen_names;
 use crate::reloc::lazy_bound_symbols;
 use crate::{LibraryInfo, LibraryPriority};
 
-const IGNORED_SYMBOLS: &[&str] = &[
+pub(crate) const IGNORED_SYMBOLS: &[&str] = &[
     "__bss_end__",
     "_bss_end__",
     "__bss_start",
@@ -21,24 +24,61 @@ const IGNORED_SYMBOLS: &[&str] = &[
     "_edata",
 ];
 
+/// Whether `symbol` is in `symbols`, which must be sorted. An unversioned
+/// `symbol` matches any version of it.
+pub(crate) fn has_symbol(symbols: &[String], symbol: &str) -> bool {
+    symbols
+        .binary_search_by(|def| {
+            let ordering = symbol.cmp(def);
+            if ordering != Ordering::Equal && def.contains('@') && !symbol.contains('@') {
+                let sym_len = symbol.len();
+                if def.len() >= sym_len {
+                    return symbol.cmp(&def[..sym_len]).reverse();
+                }
+            }
+            return ordering.reverse();
+        })
+        .is_ok()
+}
+
+/// The symbols an object defines in its dynamic symbol table, one entry per
+/// version as `name@version`. Not sorted.
+pub(crate) fn defined_symbols<E: EndianParse>(
+    all_syms: &[(Symbol, String)],
+    ver_table: Option<&SymbolVersionTable<'_, E>>,
+) -> Vec<String> {
+    return all_syms
+        .iter()
+        .enumerate()
+        .flat_map(|(index, (sym, name))| {
+            if sym.is_undefined() || sym.st_name == 0 || IGNORED_SYMBOLS.contains(&&**name) {
+                return vec![];
+            }
+            if let Some(ver_table) = ver_table {
+                if let Some(ver) = ver_table.get_definition(index).ok().flatten() {
+                    return ver
+                        .names
+                        .filter_map(|ver_name| {
+                            if let Ok(ver_name) = ver_name {
+                                return Some(format!("{name}@{ver_name}"));
+                            }
+                            return None;
+                        })
+                        .collect();
+                }
+            }
+            return vec![name.clone()];
+        })
+        .collect();
+}
+
 impl LibraryInfo {
     pub fn has_name(&self, name: &str) -> bool {
         self.name == name || self.names.iter().any(|n| n == name)
     }
 
     pub fn has_symbol(&self, symbol: &str) -> bool {
-        self.symbols
-            .binary_search_by(|def| {
-                let ordering = symbol.cmp(def);
-                if ordering != Ordering::Equal && def.contains('@') && !symbol.contains('@') {
-                    let sym_len = symbol.len();
-                    if def.len() >= sym_len {
-                        return symbol.cmp(&def[..sym_len]).reverse();
-                    }
-                }
-                return ordering.reverse();
-            })
-            .is_ok()
+        return has_symbol(&self.symbols, symbol);
     }
 
     pub fn parse<S, N>(source: S, with_undefined: bool, name: N) -> Result<Self, ParseError>
@@ -85,10 +125,14 @@ impl LibraryInfo {
             }
         }
 
-        let lazy_syms = if with_undefined {
-            lazy_bound_symbols(&mut elf, &dynamic_entries)?
+        let (lazy_syms, dlopen, arm) = if with_undefined {
+            (
+                lazy_bound_symbols(&mut elf, &dynamic_entries)?,
+                dlopen_names(&mut elf)?,
+                arm_attributes(&mut elf)?,
+            )
         } else {
-            HashSet::new()
+            (HashSet::new(), Vec::new(), None)
         };
 
         let all_syms: Vec<(Symbol, String)> = match elf.dynamic_symbol_table()? {
@@ -104,29 +148,7 @@ impl LibraryInfo {
             None => Vec::new(),
         };
         let ver_table = elf.symbol_version_table()?;
-        let mut symbols: Vec<String> = all_syms
-            .iter()
-            .enumerate()
-            .flat_map(|(index, (sym, name))| {
-                if sym.is_undefined() || sym.st_name == 0 || IGNORED_SYMBOLS.contains(&&**name) {
-                    return vec![];
-                }
-                if let Some(ver_table) = &ver_table {
-                    if let Some(ver) = ver_table.get_definition(index).ok().flatten() {
-                        return ver
-                            .names
-                            .filter_map(|ver_name| {
-                                if let Ok(ver_name) = ver_name {
-                                    return Some(format!("{name}@{ver_name}"));
-                                }
-                                return None;
-                            })
-                            .collect();
-                    }
-                }
-                return vec![name.clone()];
-            })
-            .collect();
+        let mut symbols = defined_symbols(&all_syms, ver_table.as_ref());
 
         let mut undefined = Vec::<String>::new();
         let mut undefined_lazy = Vec::<String>::new();
@@ -165,6 +187,8 @@ impl LibraryInfo {
             symbols,
             undefined,
             undefined_lazy,
+            dlopen,
+            arm,
             rpath,
             names: Vec::new(),
             priority: LibraryPriority::default(),
@@ -82,3 +82,51 @@ fn binds_now(dynamic: &[Dyn]) -> bool {
         _ => false,
     });
 }
+
+/// `R_386_COPY`, which the `elf` crate does not name.
+const R_386_COPY: u32 = 5;
+
+/// The dynamic symbol indices a copy relocation fills.
+///
+/// A non-PIE executable that reads a library's data object, such as `stdout`
+/// or `optarg`, reserves room for it and has the loader copy it there. Its
+/// dynamic symbol table then defines the object, but the definition is still
+/// the library's: every other object is bound to the copy by design.
+pub(crate) fn copy_relocated_symbols<E, S>(
+    elf: &mut ElfStream<E, S>,
+) -> Result<HashSet<usize>, ParseError>
+where
+    E: EndianParse,
+    S: std::io::Read + std::io::Seek,
+{
+    let copy = match elf.ehdr.e_machine {
+        abi::EM_ARM => abi::R_ARM_COPY,
+        abi::EM_AARCH64 => abi::R_AARCH64_COPY,
+        abi::EM_X86_64 => abi::R_X86_64_COPY,
+        abi::EM_386 => R_386_COPY,
+        _ => return Ok(HashSet::new()),
+    };
+    let headers: Vec<SectionHeader> = elf
+        .section_headers()
+        .iter()
+        .filter(|shdr| shdr.sh_type == abi::SHT_REL || shdr.sh_type == abi::SHT_RELA)
+        .copied()
+        .collect();
+    let mut copied = HashSet::new();
+    for shdr in headers {
+        if shdr.sh_type == abi::SHT_REL {
+            for rel in elf.section_data_as_rels(&shdr)? {
+                if rel.r_type == copy {
+                    copied.insert(rel.r_sym as usize);
+                }
+            }
+        } else {
+            for rel in elf.section_data_as_relas(&shdr)? {
+                if rel.r_type == copy {
+                    copied.insert(rel.r_sym as usize);
+                }
+            }
+        }
+    }
+    return Ok(copied);
+}
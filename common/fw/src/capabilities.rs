@@ -0,0 +1,329 @@
+//! Versions of the middleware a firmware ships, read from `packages.json`.
+//!
+//! Apps often bundle their own copy of one of these libraries. Knowing the
+//! firmware's version tells whether the copy is needed at all, or whether it is
+//! older than the one it shadows.
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+use bin_lib::LibraryInfo;
+use semver::Version;
+
+use crate::Firmware;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Middleware {
+    Qt,
+    Sdl2,
+    GStreamer,
+    Glib,
+    Wayland,
+    OpenSsl,
+    Curl,
+    Ffmpeg,
+    PulseAudio,
+}
+
+impl Middleware {
+    pub const ALL: [Middleware; 9] = [
+        Middleware::Qt,
+        Middleware::Sdl2,
+        Middleware::GStreamer,
+        Middleware::Glib,
+        Middleware::Wayland,
+        Middleware::OpenSsl,
+        Middleware::Curl,
+        Middleware::Ffmpeg,
+        Middleware::PulseAudio,
+    ];
+
+    /// Package names to try, in order. Each is tried with the `lib32-` prefix
+    /// that 64-bit firmwares give their 32-bit userland first. A trailing `*`
+    /// stands for a soname number, as in `libavcodec57`.
+    fn packages(self) -> &'static [&'static str] {
+        return match self {
+            Middleware::Qt => &["qtbase"],
+            Middleware::Sdl2 => &["libsdl2-webos", "libsdl2-2.0-0", "libsdl2"],
+            Middleware::GStreamer => &["gstreamer1.0"],
+            Middleware::Glib => &["libglib-2.0-0", "glib-2.0"],
+            Middleware::Wayland => &["wayland", "libwayland-client0"],
+            Middleware::OpenSsl => &["openssl", "libssl3", "libssl1.1", "libssl1.0.0"],
+            Middleware::Curl => &["curl", "libcurl4", "libcurl5"],
+            Middleware::Ffmpeg => &["ffmpeg", "libavcodec*"],
+            Middleware::PulseAudio => &["pulseaudio", "libpulse0"],
+        };
+    }
+
+    /// The library that stands for the middleware, by its name up to `.so`.
+    fn library(self) -> &'static str {
+        return match self {
+            Middleware::Qt => "libQt5Core",
+            Middleware::Sdl2 => "libSDL2-2.0",
+            Middleware::GStreamer => "libgstreamer-1.0",
+            Middleware::Glib => "libglib-2.0",
+            Middleware::Wayland => "libwayland-client",
+            Middleware::OpenSsl => "libssl",
+            Middleware::Curl => "libcurl",
+            Middleware::Ffmpeg => "libavcodec",
+            Middleware::PulseAudio => "libpulse",
+        };
+    }
+
+    /// The middleware a library stands for, if any.
+    pub fn of_library(lib: &LibraryInfo) -> Option<Middleware> {
+        let (stem, _) = lib.name.split_once(".so")?;
+        if stem == "libQt6Core" {
+            return Some(Middleware::Qt);
+        }
+        return Middleware::ALL
+            .into_iter()
+            .find(|middleware| middleware.library() == stem);
+    }
+
+    /// The version of a bundled copy, from the full file name of the library
+    /// (`libglib-2.0.so.0.7200.3` is glib 2.72.3). `None` where the file name
+    /// does not carry the release version.
+    pub fn bundled_version(self, lib: &LibraryInfo) -> Option<Version> {
+        let suffix = lib
+            .names
+            .iter()
+            .chain([&lib.name])
+            .filter_map(|name| name.split_once(".so.").map(|(_, suffix)| suffix))
+            .max_by_key(|suffix| suffix.len())?;
+        let numbers: Vec<u64> = suffix
+            .split('.')
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .ok()?;
+        return match (self, numbers.as_slice()) {
+            (Middleware::Qt, [major, minor, patch]) => Some(Version::new(*major, *minor, *patch)),
+            // SDL 2.0.x counted releases in the third number, later ones use
+            // the GLib scheme of `minor * 100 + micro`.
+            (Middleware::Sdl2, [0, micro, _]) if *micro < 100 => Some(Version::new(2, 0, *micro)),
+            (Middleware::Sdl2 | Middleware::Glib, [0, minor, patch]) => {
+                Some(Version::new(2, minor / 100, *patch))
+            }
+            (Middleware::GStreamer, [0, minor, patch]) => {
+                Some(Version::new(1, minor / 100, *patch))
+            }
+            (Middleware::Wayland, [0, minor, patch]) => Some(Version::new(1, *minor, *patch)),
+            _ => None,
+        };
+    }
+}
+
+impl Display for Middleware {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Middleware::Qt => "Qt",
+            Middleware::Sdl2 => "SDL2",
+            Middleware::GStreamer => "GStreamer",
+            Middleware::Glib => "GLib",
+            Middleware::Wayland => "Wayland",
+            Middleware::OpenSsl => "OpenSSL",
+            Middleware::Curl => "curl",
+            Middleware::Ffmpeg => "FFmpeg",
+            Middleware::PulseAudio => "PulseAudio",
+        })
+    }
+}
+
+impl Firmware {
+    /// The version of one middleware this firmware ships.
+    pub fn middleware_version(&self, middleware: Middleware) -> Option<Version> {
+        return middleware.packages().iter().find_map(|name| {
+            let Some(prefix) = name.strip_suffix('*') else {
+                return self
+                    .pkg_version(&format!("lib32-{name}"))
+                    .or_else(|| self.pkg_version(name));
+            };
+            let numbered = |key: &&String| {
+                let number = key
+                    .strip_prefix("lib32-")
+                    .unwrap_or(key)
+                    .strip_prefix(prefix);
+                return number
+                    .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+            };
+            let key = self.packages.keys().filter(numbered).min()?;
+            return self.pkg_version(key);
+        });
+    }
+
+    /// Every middleware this firmware ships, with its version.
+    pub fn capabilities(&self) -> BTreeMap<Middleware, Version> {
+        return Middleware::ALL
+            .into_iter()
+            .filter_map(|middleware| {
+                let version = self.middleware_version(middleware);
+                return version.map(|version| (middleware, version));
+            })
+            .collect();
+    }
+}
+
+/// How a bundled copy of a middleware library compares with the firmware's.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BundledCopy {
+    /// The firmware has no such library.
+    Needed,
+    /// The firmware has the same library, at the same version as the copy.
+    Redundant,
+    /// The firmware has a library of the same soname, but either version is
+    /// unknown, so the two cannot be told apart.
+    SameSoname,
+    /// The copy is older than the firmware's, which it shadows.
+    Older { firmware: Version },
+    /// The copy is newer than the firmware's, which it replaces for the app.
+    Newer { firmware: Version },
+}
+
+impl BundledCopy {
+    pub fn compare(firmware: &Firmware, lib: &LibraryInfo) -> Option<BundledCopy> {
+        let middleware = Middleware::of_library(lib)?;
+        // Another major version, such as Qt 6 over the firmware's Qt 5, does
+        // not replace the firmware's copy.
+        if firmware.find_library(&lib.name).is_none() {
+            return Some(BundledCopy::Needed);
+        }
+        let bundled = middleware.bundled_version(lib);
+        let (Some(bundled), Some(system)) = (bundled, firmware.middleware_version(middleware))
+        else {
+            return Some(BundledCopy::SameSoname);
+        };
+        if bundled < system {
+            return Some(BundledCopy::Older { firmware: system });
+        }
+        if bundled > system {
+            return Some(BundledCopy::Newer { firmware: system });
+        }
+        return Some(BundledCopy::Redundant);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    use bin_lib::{LibraryInfo, LibraryPriority};
+    use semver::Version;
+
+    use super::{BundledCopy, Middleware};
+    use crate::Firmware;
+
+    fn lib(name: &str, file: &str) -> LibraryInfo {
+        return LibraryInfo {
+            name: String::from(name),
+            package: None,
+            needed: vec![],
+            symbols: vec![],
+            names: vec![String::from(file)],
+            undefined: vec![],
+            undefined_lazy: vec![],
+            dlopen: vec![],
+            arm: None,
+            rpath: vec![],
+            priority: LibraryPriority::Rpath,
+        };
+    }
+
+    #[test]
+    fn reads_bundled_versions_from_file_names() {
+        let version = |name: &str, file: &str| {
+            let lib = lib(name, file);
+            return Middleware::of_library(&lib)?.bundled_version(&lib);
+        };
+        assert_eq!(
+            version("libSDL2-2.0.so.0", "libSDL2-2.0.so.0.14.0"),
+            Some(Version::new(2, 0, 14))
+        );
+        assert_eq!(
+            version("libSDL2-2.0.so.0", "libSDL2-2.0.so.0.2800.5"),
+            Some(Version::new(2, 28, 5))
+        );
+        assert_eq!(
+            version("libglib-2.0.so.0", "libglib-2.0.so.0.7200.3"),
+            Some(Version::new(2, 72, 3))
+        );
+        assert_eq!(
+            version("libQt5Core.so.5", "libQt5Core.so.5.15.2"),
+            Some(Version::new(5, 15, 2))
+        );
+        assert_eq!(version("libssl.so.3", "libssl.so.3"), None);
+        assert_eq!(version("libfoo.so.1", "libfoo.so.1.2.3"), None);
+    }
+
+    #[test]
+    fn resolves_capabilities_from_real_data() {
+        let data = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../data");
+        if !data.exists() {
+            return;
+        }
+        let firmwares = Firmware::list(&data).expect("list firmwares");
+        let by_release: HashMap<String, &Firmware> = firmwares
+            .iter()
+            .map(|f| (f.info.release.to_string(), f))
+            .collect();
+        let version = |release: &str, middleware| {
+            return by_release
+                .get(release)
+                .and_then(|f| f.middleware_version(middleware));
+        };
+
+        assert_eq!(
+            version("4.10.0", Middleware::Sdl2),
+            Some(Version::new(2, 0, 5))
+        );
+        assert_eq!(
+            version("4.10.0", Middleware::Qt),
+            Some(Version::new(5, 6, 2))
+        );
+        assert_eq!(
+            version("10.2.0", Middleware::OpenSsl),
+            Some(Version::new(3, 0, 13))
+        );
+        assert_eq!(
+            version("4.10.0", Middleware::Ffmpeg),
+            Some(Version::new(3, 3, 0))
+        );
+        assert_eq!(
+            version("10.2.0", Middleware::PulseAudio),
+            Some(Version::new(15, 0, 0))
+        );
+
+        let webos4 = by_release["4.10.0"];
+        let compare = |name: &str, file: &str| BundledCopy::compare(webos4, &lib(name, file));
+        assert_eq!(
+            compare("libSDL2-2.0.so.0", "libSDL2-2.0.so.0.4.0"),
+            Some(BundledCopy::Older {
+                firmware: Version::new(2, 0, 5)
+            })
+        );
+        assert_eq!(
+            compare("libSDL2-2.0.so.0", "libSDL2-2.0.so.0.14.0"),
+            Some(BundledCopy::Newer {
+                firmware: Version::new(2, 0, 5)
+            })
+        );
+        assert_eq!(
+            compare("libSDL2-2.0.so.0", "libSDL2-2.0.so.0.5.0"),
+            Some(BundledCopy::Redundant)
+        );
+        // No version in the file name: the same soname is all that is known.
+        assert_eq!(
+            compare("libSDL2-2.0.so.0", "libSDL2-2.0.so.0"),
+            Some(BundledCopy::SameSoname)
+        );
+        assert_eq!(
+            compare("libSDL2-2.0.so.0", "libSDL2-2.0.so.0.x"),
+            Some(BundledCopy::SameSoname)
+        );
+        assert_eq!(
+            compare("libQt6Core.so.6", "libQt6Core.so.6.5.3"),
+            Some(BundledCopy::Needed)
+        );
+        assert_eq!(compare("libfoo.so.1", "libfoo.so.1.0.0"), None);
+    }
+}
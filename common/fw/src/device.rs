@@ -0,0 +1,261 @@
+//! What a firmware runs on: the product platform, the board and the chip.
+//!
+//! An OTA ID such as `HE_DTV_W21P_AFADATAA` names the product (`DTV`) and the
+//! board (`W21P`). The chip (`soc`), such as `k8lp` or `m16p`, is not in it, so
+//! `webosbrew-fw-symbols-extract` records it from the rootfs. Data extracted
+//! before that has no chip, but the platform and board still come from the OTA ID.
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::FirmwareInfo;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Platform {
+    Tv,
+    Signage,
+    Projector,
+}
+
+impl Platform {
+    /// The platform for the product field of an OTA ID.
+    fn from_product(product: &str) -> Option<Platform> {
+        return match product {
+            "DTV" => Some(Platform::Tv),
+            "IDS" | "SGN" => Some(Platform::Signage),
+            "PJT" | "PRJ" => Some(Platform::Projector),
+            _ => None,
+        };
+    }
+}
+
+impl Display for Platform {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Platform::Tv => "tv",
+            Platform::Signage => "signage",
+            Platform::Projector => "projector",
+        })
+    }
+}
+
+impl FromStr for Platform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        return match s.to_ascii_lowercase().as_str() {
+            "tv" => Ok(Platform::Tv),
+            "signage" => Ok(Platform::Signage),
+            "projector" => Ok(Platform::Projector),
+            _ => Err(format!(
+                "unknown platform {s}, expected tv, signage or projector"
+            )),
+        };
+    }
+}
+
+impl FirmwareInfo {
+    /// The product platform, as recorded or from the OTA ID.
+    pub fn platform(&self) -> Option<Platform> {
+        return self
+            .platform
+            .or_else(|| Platform::from_product(self.ota_id.split('_').nth(1)?));
+    }
+
+    /// The board, such as `W21P`, as recorded or from the OTA ID.
+    pub fn board(&self) -> Option<&str> {
+        if let Some(board) = &self.board {
+            return Some(board);
+        }
+        return self
+            .ota_id
+            .split('_')
+            .nth(2)
+            .filter(|board| !board.is_empty());
+    }
+
+    /// A short description of the device, such as `tv W21P k8lp`, or `None`
+    /// when nothing is known.
+    pub fn device_label(&self) -> Option<String> {
+        let parts: Vec<String> = [
+            self.platform().map(|platform| platform.to_string()),
+            self.board().map(String::from),
+            self.soc.clone(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if parts.is_empty() {
+            return None;
+        }
+        return Some(parts.join(" "));
+    }
+}
+
+/// Select firmwares by device. An unset field matches everything; names are
+/// compared without case.
+#[derive(Debug, Clone, Default)]
+pub struct DeviceFilter {
+    pub soc: Option<String>,
+    pub board: Option<String>,
+    pub platform: Option<Platform>,
+}
+
+impl DeviceFilter {
+    pub fn matches(&self, info: &FirmwareInfo) -> bool {
+        let same = |wanted: &Option<String>, actual: Option<&str>| -> bool {
+            return wanted.as_ref().is_none_or(|wanted| {
+                actual.is_some_and(|actual| actual.eq_ignore_ascii_case(wanted))
+            });
+        };
+        return same(&self.soc, info.soc.as_deref())
+            && same(&self.board, info.board())
+            && self
+                .platform
+                .is_none_or(|platform| info.platform() == Some(platform));
+    }
+}
+
+/// Group `items` by the [`FirmwareInfo::device_label`] of their firmware, in
+/// the order each device first appears. The items of firmwares with no label
+/// make up a group of their own, under `None`.
+pub fn group_by_device<T, F>(
+    items: impl IntoIterator<Item = T>,
+    info: F,
+) -> Vec<(Option<String>, Vec<T>)>
+where
+    F: Fn(&T) -> &FirmwareInfo,
+{
+    let mut groups: Vec<(Option<String>, Vec<T>)> = Vec::new();
+    for item in items {
+        let label = info(&item).device_label();
+        match groups.iter_mut().find(|(seen, _)| *seen == label) {
+            Some((_, group)) => group.push(item),
+            None => groups.push((label, vec![item])),
+        }
+    }
+    return groups;
+}
+
+/// The machine a rootfs was built for, from `MACHINE = "..."` in `/etc/build`.
+/// LG names its machines after the chip.
+pub fn machine_from_build(build: &str) -> Option<String> {
+    return build.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        if key.trim() != "MACHINE" {
+            return None;
+        }
+        let value = value.trim().trim_matches('"');
+        return (!value.is_empty()).then(|| String::from(value));
+    });
+}
+
+/// The machine from opkg's `arch.conf`: the architecture with the highest
+/// priority, unless it is a generic CPU architecture.
+pub fn machine_from_arch_conf(arch_conf: &str) -> Option<String> {
+    let (arch, _) = arch_conf
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            if fields.next() != Some("arch") {
+                return None;
+            }
+            let arch = fields.next()?;
+            let priority: u32 = fields.next()?.parse().ok()?;
+            return Some((arch, priority));
+        })
+        .max_by_key(|(_, priority)| *priority)?;
+    let generic = [
+        "all", "any", "noarch", "arm", "armv", "cortex", "aarch64", "i686", "x86",
+    ];
+    if generic.iter().any(|prefix| arch.starts_with(prefix)) {
+        return None;
+    }
+    return Some(String::from(arch));
+}
+
+#[cfg(test)]
+mod tests {
+    use semver::Version;
+
+    use super::{
+        DeviceFilter, Platform, group_by_device, machine_from_arch_conf, machine_from_build,
+    };
+    use crate::FirmwareInfo;
+
+    fn info(ota_id: &str, soc: Option<&str>) -> FirmwareInfo {
+        return FirmwareInfo {
+            version: String::from("03.40.82.01"),
+            ota_id: String::from(ota_id),
+            release: Version::new(6, 4, 0),
+            soc: soc.map(String::from),
+            board: None,
+            platform: None,
+            product: None,
+            node: None,
+            web_engine: None,
+            cpu: None,
+            arch: None,
+        };
+    }
+
+    #[test]
+    fn decodes_the_ota_id() {
+        let tv = info("HE_DTV_W21P_AFADATAA", Some("k8lp"));
+        assert_eq!(tv.platform(), Some(Platform::Tv));
+        assert_eq!(tv.board(), Some("W21P"));
+        assert_eq!(tv.device_label().as_deref(), Some("tv W21P k8lp"));
+        assert_eq!(info("odd", None).device_label(), None);
+    }
+
+    #[test]
+    fn groups_by_device() {
+        let firmwares = [
+            info("HE_DTV_W21P_AFADATAA", Some("k8lp")),
+            info("HE_DTV_W22O_AFABATAA", None),
+            info("odd", None),
+            info("HE_DTV_W21P_AFADATAA", Some("k8lp")),
+        ];
+        let groups: Vec<(Option<String>, usize)> = group_by_device(&firmwares, |fw| *fw)
+            .into_iter()
+            .map(|(label, group)| (label, group.len()))
+            .collect();
+        assert_eq!(
+            groups,
+            vec![
+                (Some(String::from("tv W21P k8lp")), 2),
+                (Some(String::from("tv W22O")), 1),
+                (None, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn filters_by_device() {
+        let tv = info("HE_DTV_W21P_AFADATAA", Some("k8lp"));
+        let filter = |soc: Option<&str>, board: Option<&str>, platform| DeviceFilter {
+            soc: soc.map(String::from),
+            board: board.map(String::from),
+            platform,
+        };
+        assert!(filter(None, None, None).matches(&tv));
+        assert!(filter(Some("K8LP"), Some("w21p"), Some(Platform::Tv)).matches(&tv));
+        assert!(!filter(Some("m16p"), None, None).matches(&tv));
+        assert!(!filter(None, None, Some(Platform::Signage)).matches(&tv));
+        assert!(!filter(Some("k8lp"), None, None).matches(&info("HE_DTV_W21P_AFADATAA", None)));
+    }
+
+    #[test]
+    fn reads_the_machine() {
+        assert_eq!(
+            machine_from_build("DISTRO = \"starfish\"\nMACHINE = \"m16p\"\n").as_deref(),
+            Some("m16p")
+        );
+        let arch_conf = "arch all 1\narch any 6\narch armv7a 11\narch k8lp 16\n";
+        assert_eq!(machine_from_arch_conf(arch_conf).as_deref(), Some("k8lp"));
+        assert_eq!(machine_from_arch_conf("arch all 1\narch armv7a 11\n"), None);
+    }
+}
@@ -1,12 +1,17 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::env;
 use std::fmt::{Display, Formatter};
 use std::fs::File;
 use std::io::{BufReader, Error, ErrorKind};
 use std::path::{Path, PathBuf};
 
-use bin_lib::LibraryInfo;
+use bin_lib::{ElfArch, LibraryInfo};
 
-use crate::{Firmware, FirmwareInfo, PackageEntry, ReleaseCodename};
+use crate::packed::PACK_EXTENSION;
+use crate::{
+    DATA_DIR_ENV, Firmware, FirmwareInfo, Jail, Libraries, LunaRegistry, PackageEntry,
+    ReleaseCodename, library_key,
+};
 
 impl FirmwareInfo {
     pub fn codename(&self) -> Option<ReleaseCodename> {
@@ -36,30 +41,147 @@ impl FirmwareInfo {
             _ => None,
         }
     }
+
+    /// The name of the firmware's directory in a data directory.
+    pub fn dir_name(&self) -> String {
+        return format!("{}-{}", self.version, self.ota_id);
+    }
 }
 
 impl Display for FirmwareInfo {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // A TV firmware is just "webOS", as it was before products were recorded.
+        let product = match self.product.as_deref() {
+            None | Some("webOS TV") => "webOS",
+            Some(product) => product,
+        };
         f.write_fmt(format_args!(
-            "Firmware {}, webOS {}, OTA ID: {}",
+            "Firmware {}, {product} {}, OTA ID: {}",
             self.version, self.release, self.ota_id
-        ))
+        ))?;
+        if let Some(soc) = &self.soc {
+            f.write_fmt(format_args!(", SoC: {soc}"))?;
+        }
+        Ok(())
     }
 }
 
 impl Firmware {
+    /// Find a library for a binary of the firmware's own class and machine.
+    /// See [`Firmware::find_library_for`].
     pub fn find_library(&self, name: &str) -> Option<LibraryInfo> {
-        if let Some(lib_name) = self.index.get(name) {
-            let path = self.path.join(lib_name);
-            return File::open(path)
-                .and_then(|file| {
-                    return serde_json::from_reader(BufReader::new(file)).map_err(|e| {
-                        Error::new(ErrorKind::InvalidData, format!("Bad library info: {e:?}"))
-                    });
-                })
-                .ok();
+        return self.find_library_for(name, None);
+    }
+
+    /// Find a library for a binary of class and machine `arch`, or of the
+    /// firmware's own when `None`. The bare name is tried next, as data
+    /// extracted before libraries were keyed by [`library_key`] has it. So is
+    /// a name from [`Firmware::library_names`] that is already a key.
+    pub fn find_library_for(&self, name: &str, arch: Option<ElfArch>) -> Option<LibraryInfo> {
+        let arch = arch.or(self.info.arch);
+        return self
+            .find_library_by_key(&library_key(name, arch))
+            .or_else(|| {
+                return arch.and_then(|_| self.find_library_by_key(name));
+            });
+    }
+
+    fn find_library_by_key(&self, key: &str) -> Option<LibraryInfo> {
+        match &self.libraries {
+            Libraries::Dir { path, index } => {
+                let lib_name = index.get(key)?;
+                return File::open(path.join(lib_name))
+                    .and_then(|file| {
+                        return serde_json::from_reader(BufReader::new(file)).map_err(|e| {
+                            Error::new(ErrorKind::InvalidData, format!("Bad library info: {e:?}"))
+                        });
+                    })
+                    .ok();
+            }
+            Libraries::Packed { db, index } => {
+                return index.get(key).map(|id| db.library(*id));
+            }
+            Libraries::Rootfs {
+                reader,
+                index,
+                package_files,
+                parsed,
+            } => {
+                return Firmware::find_rootfs_library(
+                    reader.as_ref(),
+                    index,
+                    package_files,
+                    parsed,
+                    key,
+                );
+            }
+        }
+    }
+
+    /// Every library name in the firmware's index, aliases included, sorted.
+    /// Those of the firmware's own class and machine are bare names, the
+    /// others keys such as `elf64-aarch64/libc.so.6`.
+    pub fn library_names(&self) -> Vec<&str> {
+        let keys: Vec<&str> = match &self.libraries {
+            Libraries::Dir { index, .. } => index.keys().map(String::as_str).collect(),
+            Libraries::Packed { index, .. } => index.keys().map(String::as_str).collect(),
+            Libraries::Rootfs { index, .. } => index.keys().map(String::as_str).collect(),
+        };
+        let prefix = self.info.arch.map(|arch| format!("{}/", arch.tag()));
+        let mut names: Vec<&str> = keys
+            .into_iter()
+            .map(|key| {
+                return prefix
+                    .as_ref()
+                    .and_then(|prefix| key.strip_prefix(prefix.as_str()))
+                    .unwrap_or(key);
+            })
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        return names;
+    }
+
+    /// Every library in the firmware, once, by its name. Aliases in the index
+    /// resolve to the same library. A library of another class and machine is
+    /// under its key, like in [`Firmware::library_names`].
+    pub fn libraries(&self) -> BTreeMap<String, LibraryInfo> {
+        let mut libs = BTreeMap::new();
+        for name in self.library_names() {
+            if let Some(lib) = self.find_library(name) {
+                let key = match name.rsplit_once('/') {
+                    Some((tag, _)) => format!("{tag}/{}", lib.name),
+                    None => lib.name.clone(),
+                };
+                libs.entry(key).or_insert(lib);
+            }
         }
-        None
+        return libs;
+    }
+
+    /// The firmware's OS packages by name, from `packages.json`. Empty for
+    /// data without one.
+    pub fn packages(&self) -> &HashMap<String, PackageEntry> {
+        return &self.packages;
+    }
+
+    /// The commands on the firmware's `PATH`, busybox applets included, from
+    /// `executables.json`. `None` for data extracted before they were recorded.
+    pub fn executables(&self) -> Option<&BTreeSet<String>> {
+        return self.executables.as_ref();
+    }
+
+    /// The Luna bus services and their known methods, from `luna.json`. `None`
+    /// for data extracted before they were recorded.
+    pub fn luna(&self) -> Option<&LunaRegistry> {
+        return self.luna.as_ref();
+    }
+
+    /// What a developer-mode native app sees of the firmware, from
+    /// `jail.json`. `None` when the firmware has no jail, or for data
+    /// extracted before it was recorded.
+    pub fn jail(&self) -> Option<&Jail> {
+        return self.jail.as_ref();
     }
 
     pub fn load<P>(path: P) -> Result<Firmware, Error>
@@ -85,42 +207,116 @@ impl Firmware {
             .ok()
             .and_then(|file| serde_json::from_reader(BufReader::new(file)).ok())
             .unwrap_or_default();
+        let executables: Option<BTreeSet<String>> = File::open(path.join("executables.json"))
+            .ok()
+            .and_then(|file| serde_json::from_reader(BufReader::new(file)).ok());
+        let luna: Option<LunaRegistry> = File::open(path.join("luna.json"))
+            .ok()
+            .and_then(|file| serde_json::from_reader(BufReader::new(file)).ok());
+        let jail: Option<Jail> = File::open(path.join("jail.json"))
+            .ok()
+            .and_then(|file| serde_json::from_reader(BufReader::new(file)).ok());
 
         Ok(Firmware {
-            path: path.to_path_buf(),
             info,
-            index,
+            data_dir: path.to_path_buf(),
+            libraries: Libraries::Dir {
+                path: path.to_path_buf(),
+                index,
+            },
             packages,
+            executables,
+            luna,
+            jail,
         })
     }
 
+    /// Every firmware in a data directory: each firmware directory, and each
+    /// pack (`*.fwdb`) in it. A directory that a pack already covers is not
+    /// read. `data_path` may also be a pack itself.
     pub fn list<P>(data_path: P) -> Result<Vec<Firmware>, Error>
     where
         P: AsRef<Path>,
     {
-        let mut firmwares: Vec<Firmware> = data_path
-            .as_ref()
+        let data_path = data_path.as_ref();
+        if data_path.is_file() {
+            let mut firmwares = Firmware::load_pack(data_path)?;
+            firmwares.sort_by(|a, b| a.info.release.cmp(&b.info.release));
+            return Ok(firmwares);
+        }
+        let mut entries: Vec<PathBuf> = data_path
             .read_dir()
             .map_err(|e| {
                 Error::new(
                     e.kind(),
                     format!(
                         "Failed to open data directory {}: {e}",
-                        data_path.as_ref().to_string_lossy()
+                        data_path.to_string_lossy()
                     ),
                 )
             })?
-            .filter_map(|ent| {
-                if let Ok(ent) = ent {
-                    return Firmware::load(ent.path()).ok();
-                }
-                return None;
-            })
+            .filter_map(|ent| ent.ok().map(|ent| ent.path()))
+            .collect();
+        entries.sort();
+        let (packs, dirs): (Vec<PathBuf>, Vec<PathBuf>) = entries
+            .into_iter()
+            .partition(|path| path.extension().is_some_and(|ext| ext == PACK_EXTENSION));
+        let mut firmwares: Vec<Firmware> = packs
+            .iter()
+            .filter_map(|pack| Firmware::load_pack(pack).ok())
+            .flatten()
             .collect();
+        let packed: HashSet<String> = firmwares.iter().map(|fw| fw.info.dir_name()).collect();
+        firmwares.extend(dirs.iter().filter_map(|dir| {
+            let name = dir.file_name()?.to_string_lossy();
+            if packed.contains(name.as_ref()) {
+                return None;
+            }
+            return Firmware::load(dir).ok();
+        }));
+        for firmware in &mut firmwares {
+            firmware.data_dir = data_path.to_path_buf();
+        }
         firmwares.sort_by(|a, b| a.info.release.cmp(&b.info.release));
         Ok(firmwares)
     }
 
+    /// The data directories to read, in order of precedence: `given` (from
+    /// `--data-dir`), then each entry of [`DATA_DIR_ENV`], then
+    /// [`Firmware::data_path`].
+    pub fn data_dirs(given: &[PathBuf]) -> Vec<PathBuf> {
+        let mut dirs = given.to_vec();
+        if let Some(env) = env::var_os(DATA_DIR_ENV) {
+            dirs.extend(env::split_paths(&env).filter(|dir| !dir.as_os_str().is_empty()));
+        }
+        dirs.push(Firmware::data_path());
+        return dirs;
+    }
+
+    /// Every firmware in `data_dirs`, merged. A firmware (the same version and
+    /// OTA ID) in more than one directory is taken from the first. A directory
+    /// that cannot be read is an error, except the built-in one when there are
+    /// others: private data does not need the public set installed.
+    pub fn list_all(data_dirs: &[PathBuf]) -> Result<Vec<Firmware>, Error> {
+        let built_in = Firmware::data_path();
+        let mut firmwares: Vec<Firmware> = Vec::new();
+        let mut seen: HashSet<String> = HashSet::new();
+        for dir in data_dirs {
+            let listed = match Firmware::list(dir) {
+                Ok(listed) => listed,
+                Err(_) if *dir == built_in && data_dirs.len() > 1 => continue,
+                Err(e) => return Err(e),
+            };
+            firmwares.extend(
+                listed
+                    .into_iter()
+                    .filter(|firmware| seen.insert(firmware.info.dir_name())),
+            );
+        }
+        firmwares.sort_by(|a, b| a.info.release.cmp(&b.info.release));
+        return Ok(firmwares);
+    }
+
     pub fn data_path() -> PathBuf {
         if cfg!(feature = "linux-install") {
             PathBuf::from("/usr/share/webosbrew/compat-checker/data")
@@ -134,3 +330,119 @@ impl Firmware {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use bin_lib::{ElfArch, ElfClass};
+
+    use crate::Firmware;
+
+    fn write_firmware(data_dir: &Path, version: &str, release: &str) {
+        let dir = data_dir.join(format!("{version}-HE_DTV_TEST"));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("info.json"),
+            format!(r#"{{"version":"{version}","ota_id":"HE_DTV_TEST","release":"{release}"}}"#),
+        )
+        .unwrap();
+        fs::write(dir.join("index.json"), "{}").unwrap();
+    }
+
+    /// The same firmware in two data directories is taken from the first, and
+    /// each firmware remembers where it came from.
+    #[test]
+    fn merges_data_dirs_first_wins() {
+        let private = tempfile::TempDir::new().unwrap();
+        let public = tempfile::TempDir::new().unwrap();
+        write_firmware(private.path(), "05.40.20.01", "4.10.0");
+        write_firmware(public.path(), "05.40.20.01", "4.10.0");
+        write_firmware(public.path(), "03.30.60.01", "3.4.0");
+        let dirs = [private.path().to_path_buf(), public.path().to_path_buf()];
+
+        let firmwares = Firmware::list_all(&dirs).expect("list");
+
+        let listed: Vec<(String, &PathBuf)> = firmwares
+            .iter()
+            .map(|fw| (fw.info.version.clone(), &fw.data_dir))
+            .collect();
+        assert_eq!(
+            listed,
+            vec![
+                (String::from("03.30.60.01"), &dirs[1]),
+                (String::from("05.40.20.01"), &dirs[0]),
+            ]
+        );
+    }
+
+    /// A 32-bit and a 64-bit `libc.so.6` are both indexed, and each binary
+    /// gets its own. Bare names from older data are found for either.
+    #[test]
+    fn finds_libraries_by_class_and_machine() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let dir = dir.path();
+        fs::write(
+            dir.join("info.json"),
+            r#"{"version":"05.40.20.01","ota_id":"HE_DTV_TEST","release":"4.10.0",
+                "arch":{"class":"Elf32","machine":40}}"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("index.json"),
+            r#"{"elf32-arm/libc.so.6": "elf32-arm/libc.so.6.json",
+                "elf64-aarch64/libc.so.6": "elf64-aarch64/libc.so.6.json",
+                "libold.so.1": "libold.so.1.json"}"#,
+        )
+        .unwrap();
+        for (file, package) in [
+            ("elf32-arm/libc.so.6.json", "lib32-glibc"),
+            ("elf64-aarch64/libc.so.6.json", "glibc"),
+            ("libold.so.1.json", "old"),
+        ] {
+            let path = dir.join(file);
+            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(
+                &path,
+                format!(r#"{{"name":"{name}","package":"{package}","needed":[],"symbols":[]}}"#),
+            )
+            .unwrap();
+        }
+        let firmware = Firmware::load(dir).unwrap();
+        let package = |arch: Option<ElfArch>, name: &str| {
+            return firmware
+                .find_library_for(name, arch)
+                .and_then(|lib| lib.package);
+        };
+        let aarch64 = ElfArch {
+            class: ElfClass::Elf64,
+            machine: 183,
+        };
+
+        assert_eq!(package(None, "libc.so.6").as_deref(), Some("lib32-glibc"));
+        assert_eq!(
+            package(Some(aarch64), "libc.so.6").as_deref(),
+            Some("glibc")
+        );
+        assert_eq!(
+            package(Some(aarch64), "libold.so.1").as_deref(),
+            Some("old")
+        );
+        assert_eq!(
+            firmware.library_names(),
+            vec!["elf64-aarch64/libc.so.6", "libc.so.6", "libold.so.1"]
+        );
+        assert_eq!(
+            firmware.libraries().keys().collect::<Vec<_>>(),
+            vec!["elf64-aarch64/libc.so.6", "libc.so.6", "libold.so.1"]
+        );
+    }
+
+    #[test]
+    fn unreadable_data_dir_is_an_error() {
+        let missing = PathBuf::from("/nonexistent/webosbrew-data");
+        assert!(Firmware::list_all(&[missing]).is_err());
+    }
+}
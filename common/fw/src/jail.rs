@@ -0,0 +1,134 @@
+//! The jail webOS runs developer-mode native apps in.
+//!
+//! On a retail TV, `jailer` starts a native app installed in developer mode in
+//! a root built from bind mounts of parts of the rootfs. A library outside
+//! them is not there for the app, even though the firmware has it.
+//!
+//! The configuration has one directive per line. Those with `bind` in their
+//! name, such as `mount_bind /usr/lib`, make their first argument visible.
+//! `include FILE` reads another file. Everything else is left alone.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ldconfig::in_root;
+use crate::reader::RootfsReader;
+
+/// The jail configuration for developer-mode native apps, on the firmware.
+pub const JAIL_CONFIG: &str = "/etc/jail_native_devmode.conf";
+
+/// How deep `include` directives are followed.
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// What a jailed app sees of a firmware.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Jail {
+    /// The paths on the firmware the jail mounts, as in `/usr/lib`.
+    pub paths: BTreeSet<String>,
+    /// The keys of the libraries in the firmware's index whose file is
+    /// outside every path. See [`crate::library_key`].
+    #[serde(default)]
+    pub hidden: BTreeSet<String>,
+}
+
+impl Jail {
+    /// Read the jail configuration under `root`. `None` without one. Files
+    /// that cannot be read are added to `skipped`.
+    pub fn read(reader: &dyn RootfsReader, root: &Path, skipped: &mut Vec<String>) -> Option<Jail> {
+        let path = in_root(root, JAIL_CONFIG);
+        if !reader.is_file(&path) {
+            return None;
+        }
+        let mut jail = Jail::default();
+        jail.read_config(reader, root, JAIL_CONFIG, 0, skipped);
+        return Some(jail);
+    }
+
+    fn read_config(
+        &mut self,
+        reader: &dyn RootfsReader,
+        root: &Path,
+        config: &str,
+        depth: usize,
+        skipped: &mut Vec<String>,
+    ) {
+        if depth > MAX_INCLUDE_DEPTH {
+            skipped.push(format!("{config}: includes nest too deep"));
+            return;
+        }
+        let text = match reader.read_to_string(&in_root(root, config)) {
+            Ok(text) => text,
+            Err(e) => {
+                skipped.push(format!("{config}: {e}"));
+                return;
+            }
+        };
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            let mut words = line.split_whitespace();
+            let (Some(directive), Some(arg)) = (words.next(), words.next()) else {
+                continue;
+            };
+            if !arg.starts_with('/') {
+                continue;
+            }
+            if directive == "include" {
+                self.read_config(reader, root, arg, depth + 1, skipped);
+            } else if directive.contains("bind") {
+                let path = arg.trim_end_matches('/');
+                self.paths
+                    .insert(String::from(if path.is_empty() { "/" } else { path }));
+            }
+        }
+    }
+
+    /// Whether `path`, on the firmware, is inside one of the mounted paths.
+    pub fn is_visible(&self, path: &str) -> bool {
+        return self.paths.iter().any(|mounted| {
+            return mounted == "/"
+                || path
+                    .strip_prefix(mounted.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'));
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::Jail;
+    use crate::reader::HostReader;
+
+    #[test]
+    fn reads_bind_mounts_and_includes() {
+        let root = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(root.path().join("etc/jail.d")).unwrap();
+        fs::write(
+            root.path().join("etc/jail_native_devmode.conf"),
+            "# dev-mode apps\nmount_bind /usr/lib/\nmount_tmpfs /tmp\ninclude /etc/jail.d/gpu.conf\n",
+        )
+        .unwrap();
+        fs::write(
+            root.path().join("etc/jail.d/gpu.conf"),
+            "mount_bind_ro /mnt/bsppart/lib # drivers\n",
+        )
+        .unwrap();
+
+        let mut skipped = Vec::new();
+        let jail = Jail::read(&HostReader, root.path(), &mut skipped).unwrap();
+
+        assert!(skipped.is_empty());
+        assert_eq!(
+            jail.paths.iter().map(String::as_str).collect::<Vec<_>>(),
+            vec!["/mnt/bsppart/lib", "/usr/lib"]
+        );
+        assert!(jail.is_visible("/usr/lib/libfoo.so.1"));
+        assert!(jail.is_visible("/mnt/bsppart/lib/libEGL.so"));
+        assert!(!jail.is_visible("/usr/lib2/libfoo.so"));
+        assert!(!jail.is_visible("/lib/libc.so.6"));
+        assert!(Jail::read(&HostReader, root.path().join("etc").as_path(), &mut skipped).is_none());
+    }
+}
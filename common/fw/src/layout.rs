@@ -0,0 +1,541 @@
+//! Where a firmware keeps its rootfs, its libraries and its opkg database.
+//!
+//! A webOS TV image is unpacked into `<version>-<ota_id>/`, with the rootfs in
+//! `rootfs.pak.unsquashfs` and the board support libraries in
+//! `bsppart-*.pak.unsquashfs`. A TV rootfs dumped on its own has
+//! `/etc/starfish-release` at its top. webOS OSE and LG signage builds are a
+//! plain rootfs, described by `/etc/os-release`.
+
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use path_slash::PathBufExt;
+use regex::Regex;
+use semver::Version as SemVer;
+
+use crate::FirmwareInfo;
+use crate::device::{machine_from_arch_conf, machine_from_build};
+use crate::ldconfig::{CachedLibrary, in_root, read_ld_so_cache, read_ld_so_conf};
+use crate::reader::RootfsReader;
+use crate::rootfs::FirmwareId;
+
+pub(crate) trait FirmwareLayout {
+    /// Whether `input` is laid out this way.
+    fn detect(&self, reader: &dyn RootfsReader, input: &Path) -> bool;
+
+    fn rootfs(&self, input: &Path) -> PathBuf;
+
+    /// What the firmware is. The version and OTA ID in `id` win over what
+    /// the input says.
+    fn fw_info(
+        &self,
+        reader: &dyn RootfsReader,
+        input: &Path,
+        id: &FirmwareId,
+    ) -> Result<FirmwareInfo, Error>;
+
+    /// The directories the loader searches without the cache, as paths on
+    /// the firmware.
+    fn trusted_dirs(&self) -> &'static [&'static str];
+
+    /// Where a path on the firmware is in `input`, if anywhere.
+    fn host_path(&self, reader: &dyn RootfsReader, input: &Path, path: &str) -> Option<PathBuf>;
+
+    /// The directories the dynamic loader searches, in order: the trusted
+    /// ones, then those `ld.so.conf` lists. Only those that exist.
+    fn lib_paths(&self, reader: &dyn RootfsReader, input: &Path) -> Vec<PathBuf> {
+        let host_path = |path: &str| self.host_path(reader, input, path);
+        let mut paths: Vec<PathBuf> = Vec::new();
+        for dir in self
+            .trusted_dirs()
+            .iter()
+            .map(|dir| String::from(*dir))
+            .chain(read_ld_so_conf(reader, LD_SO_CONF, &host_path))
+        {
+            if let Some(path) = host_path(&dir).filter(|path| reader.is_dir(path)) {
+                if !paths.contains(&path) {
+                    paths.push(path);
+                }
+            }
+        }
+        return paths;
+    }
+
+    /// The libraries in `ld.so.cache`, or `None` when there is no cache.
+    fn ld_cache(
+        &self,
+        reader: &dyn RootfsReader,
+        input: &Path,
+    ) -> Option<Result<Vec<CachedLibrary>, Error>> {
+        let path = self.host_path(reader, input, LD_SO_CACHE)?;
+        let data = match reader.read(&path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == ErrorKind::NotFound => return None,
+            Err(e) => return Some(Err(e)),
+        };
+        return Some(read_ld_so_cache(&data));
+    }
+
+    /// The opkg database directories, each three levels below its root, as in
+    /// `<root>/usr/lib/opkg`. One may hold an `info` directory, a `status`
+    /// file or both. An earlier one takes precedence.
+    fn opkg_dirs(&self, reader: &dyn RootfsReader, input: &Path) -> Result<Vec<PathBuf>, Error>;
+}
+
+const LD_SO_CONF: &str = "/etc/ld.so.conf";
+const LD_SO_CACHE: &str = "/etc/ld.so.cache";
+
+/// The opkg database directories in each image of a webOS TV, from its root.
+const STARFISH_OPKG_DIRS: [&str; 3] = ["usr/lib/opkg", "var/lib/opkg", "bsp/var/lib/opkg"];
+
+/// Every known layout, tried in order.
+const LAYOUTS: [&(dyn FirmwareLayout + Sync); 3] = [&StarfishTv, &StarfishRootfs, &OsRelease];
+
+pub(crate) fn detect_layout(
+    reader: &dyn RootfsReader,
+    input: &Path,
+) -> Result<&'static dyn FirmwareLayout, Error> {
+    return LAYOUTS
+        .into_iter()
+        .find(|layout| layout.detect(reader, input))
+        .map(|layout| layout as &dyn FirmwareLayout)
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                format!(
+                    "No webOS TV image, /etc/starfish-release or /etc/os-release in {}",
+                    input.display()
+                ),
+            )
+        });
+}
+
+/// A webOS TV image, unpacked with `unsquashfs`.
+struct StarfishTv;
+
+impl FirmwareLayout for StarfishTv {
+    fn detect(&self, reader: &dyn RootfsReader, input: &Path) -> bool {
+        return reader.is_file(&self.rootfs(input).join("etc/starfish-release"));
+    }
+
+    fn rootfs(&self, input: &Path) -> PathBuf {
+        return input.join("rootfs.pak.unsquashfs");
+    }
+
+    fn fw_info(
+        &self,
+        reader: &dyn RootfsReader,
+        input: &Path,
+        id: &FirmwareId,
+    ) -> Result<FirmwareInfo, Error> {
+        let named = id_from_dir_name(input);
+        let (Some(version), Some(ota_id)) = (
+            id.version.clone().or_else(|| named.version.clone()),
+            id.ota_id.clone().or(named.ota_id),
+        ) else {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                format!("Bad input path {}", input.display()),
+            ));
+        };
+        return starfish_info(reader, &self.rootfs(input), Some(version), ota_id);
+    }
+
+    fn trusted_dirs(&self) -> &'static [&'static str] {
+        return &["/lib", "/usr/lib"];
+    }
+
+    fn host_path(&self, reader: &dyn RootfsReader, input: &Path, path: &str) -> Option<PathBuf> {
+        // The board support image is mounted at /mnt/bsppart on the TV, and
+        // unpacked next to the rootfs here.
+        if let Some(rest) = path.strip_prefix("/mnt/bsppart") {
+            if rest.is_empty() || rest.starts_with('/') {
+                let bsp_regex = Regex::new(r"^bsppart(-\w+)?\.pak\.unsquashfs$").unwrap();
+                let mut dirs: Vec<PathBuf> = reader
+                    .read_dir(input)
+                    .ok()?
+                    .into_iter()
+                    .filter(|path| {
+                        return path
+                            .file_name()
+                            .is_some_and(|name| bsp_regex.is_match(&name.to_string_lossy()));
+                    })
+                    .collect();
+                dirs.sort();
+                return Some(in_root(dirs.first()?, rest));
+            }
+        }
+        return Some(in_root(&self.rootfs(input), path));
+    }
+
+    fn opkg_dirs(&self, reader: &dyn RootfsReader, input: &Path) -> Result<Vec<PathBuf>, Error> {
+        let mut images: Vec<PathBuf> = reader.read_dir(input)?;
+        images.sort();
+        // The rootfs's database comes first, then those of the other images.
+        let rootfs = self.rootfs(input);
+        images.sort_by_key(|image| *image != rootfs);
+        return Ok(images
+            .iter()
+            .flat_map(|image| {
+                return STARFISH_OPKG_DIRS
+                    .iter()
+                    .map(|dir| image.join(PathBuf::from_slash(*dir)));
+            })
+            .filter(|dir| reader.is_dir(dir))
+            .collect());
+    }
+}
+
+/// A webOS TV rootfs on its own, dumped from a TV or unpacked from
+/// `rootfs.pak`. Nothing in it names the OTA ID, so that comes from the
+/// directory name, `<version>-<ota_id>`, or from the caller. The version falls
+/// back to the release.
+struct StarfishRootfs;
+
+impl FirmwareLayout for StarfishRootfs {
+    fn detect(&self, reader: &dyn RootfsReader, input: &Path) -> bool {
+        return reader.is_file(&input.join("etc/starfish-release"));
+    }
+
+    fn rootfs(&self, input: &Path) -> PathBuf {
+        return input.to_path_buf();
+    }
+
+    fn fw_info(
+        &self,
+        reader: &dyn RootfsReader,
+        input: &Path,
+        id: &FirmwareId,
+    ) -> Result<FirmwareInfo, Error> {
+        let named = id_from_dir_name(input);
+        let ota_id = id.ota_id.clone().or(named.ota_id).ok_or_else(|| {
+            return Error::new(
+                ErrorKind::NotFound,
+                format!(
+                    "No OTA ID for {}: name the directory <version>-<ota_id>, or give one",
+                    input.display()
+                ),
+            );
+        })?;
+        return starfish_info(reader, input, id.version.clone().or(named.version), ota_id);
+    }
+
+    fn trusted_dirs(&self) -> &'static [&'static str] {
+        return &["/lib", "/usr/lib"];
+    }
+
+    fn host_path(&self, _reader: &dyn RootfsReader, input: &Path, path: &str) -> Option<PathBuf> {
+        return Some(in_root(input, path));
+    }
+
+    fn opkg_dirs(&self, reader: &dyn RootfsReader, input: &Path) -> Result<Vec<PathBuf>, Error> {
+        return Ok(STARFISH_OPKG_DIRS
+            .iter()
+            .map(|dir| input.join(PathBuf::from_slash(*dir)))
+            .filter(|dir| reader.is_dir(dir))
+            .collect());
+    }
+}
+
+/// A plain rootfs described by `/etc/os-release`: webOS OSE, or LG signage.
+/// It has no OTA ID, so one is made up as `<ID>_<product>_<machine>`, which
+/// decodes like a TV one: `webos_OSE_raspberrypi4`, `webos_SGN_m16p`.
+struct OsRelease;
+
+impl OsRelease {
+    fn read(reader: &dyn RootfsReader, input: &Path) -> Result<HashMap<String, String>, Error> {
+        let text = reader.read_to_string(&input.join("etc").join("os-release"))?;
+        return Ok(parse_os_release(&text));
+    }
+}
+
+impl FirmwareLayout for OsRelease {
+    fn detect(&self, reader: &dyn RootfsReader, input: &Path) -> bool {
+        return reader.is_file(&input.join("etc/os-release"));
+    }
+
+    fn rootfs(&self, input: &Path) -> PathBuf {
+        return input.to_path_buf();
+    }
+
+    fn fw_info(
+        &self,
+        reader: &dyn RootfsReader,
+        input: &Path,
+        id: &FirmwareId,
+    ) -> Result<FirmwareInfo, Error> {
+        let fields = OsRelease::read(reader, input)?;
+        let field = |key: &str| fields.get(key).map(String::as_str).unwrap_or_default();
+        let version = field("VERSION_ID");
+        let release = parse_release(version).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Bad VERSION_ID in os-release: {version}"),
+            )
+        })?;
+        let name = match field("NAME") {
+            "" => "webOS",
+            name => name,
+        };
+        let signage = name.to_ascii_lowercase().contains("signage");
+        let soc = extract_machine(reader, input);
+        let ota_id = [
+            match field("ID") {
+                "" => "webos",
+                id => id,
+            },
+            if signage { "SGN" } else { "OSE" },
+            soc.as_deref().unwrap_or_default(),
+        ]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("_");
+        let mut info = FirmwareInfo {
+            version: id.version.clone().unwrap_or_else(|| String::from(version)),
+            ota_id: id.ota_id.clone().unwrap_or(ota_id),
+            release,
+            soc,
+            board: None,
+            platform: None,
+            product: Some(String::from(name)),
+            node: None,
+            web_engine: None,
+            cpu: None,
+            arch: None,
+        };
+        info.board = info.board().map(String::from);
+        info.platform = info.platform();
+        return Ok(info);
+    }
+
+    fn trusted_dirs(&self) -> &'static [&'static str] {
+        return &["/lib", "/usr/lib", "/lib64", "/usr/lib64"];
+    }
+
+    fn host_path(&self, _reader: &dyn RootfsReader, input: &Path, path: &str) -> Option<PathBuf> {
+        return Some(in_root(input, path));
+    }
+
+    fn opkg_dirs(&self, reader: &dyn RootfsReader, input: &Path) -> Result<Vec<PathBuf>, Error> {
+        return Ok(["usr/lib/opkg", "var/lib/opkg"]
+            .iter()
+            .map(|p| input.join(PathBuf::from_slash(*p)))
+            .filter(|p| reader.is_dir(p))
+            .collect());
+    }
+}
+
+/// The version and OTA ID in an input named `<version>-<ota_id>`, as in
+/// `05.40.20-HE_DTV_W21P_AFADATAA`. An OTA ID always has fields, so a name such
+/// as `tv-dump` has neither.
+fn id_from_dir_name(input: &Path) -> FirmwareId {
+    let Some((version, ota_id)) = input
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|s| s.split_once('-'))
+        .filter(|(_, ota_id)| ota_id.contains('_'))
+    else {
+        return FirmwareId::default();
+    };
+    return FirmwareId {
+        version: Some(String::from(version)),
+        ota_id: Some(String::from(ota_id)),
+    };
+}
+
+/// A webOS TV firmware, with the release from the rootfs's
+/// `/etc/starfish-release`. Without a version, the release stands in for it.
+fn starfish_info(
+    reader: &dyn RootfsReader,
+    rootfs: &Path,
+    version: Option<String>,
+    ota_id: String,
+) -> Result<FirmwareInfo, Error> {
+    let starfish_release = reader.read_to_string(&rootfs.join("etc").join("starfish-release"))?;
+    let release_regex = Regex::new("release (\\d+\\.\\d+\\.\\d+)").unwrap();
+    let release = release_regex
+        .captures(&starfish_release)
+        .and_then(|cap| cap.get(1))
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                format!("Bad starfish-release: {starfish_release}"),
+            )
+        })?;
+    let release = SemVer::parse(release.as_str()).map_err(|e| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("Invalid version {}: {e:?}", release.as_str()),
+        )
+    })?;
+    let mut info = FirmwareInfo {
+        version: version.unwrap_or_else(|| release.to_string()),
+        ota_id,
+        release,
+        soc: extract_machine(reader, rootfs),
+        board: None,
+        platform: None,
+        product: Some(String::from("webOS TV")),
+        node: None,
+        web_engine: None,
+        cpu: None,
+        arch: None,
+    };
+    info.board = info.board().map(String::from);
+    info.platform = info.platform();
+    return Ok(info);
+}
+
+/// `KEY=value` lines, with the value unquoted.
+fn parse_os_release(text: &str) -> HashMap<String, String> {
+    return text
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.trim().split_once('=')?;
+            let value = value.trim().trim_matches('"').trim_matches('\'');
+            return Some((String::from(key), String::from(value)));
+        })
+        .collect();
+}
+
+/// A release such as `2.24.0`, or a shorter `4.1`, as a version.
+fn parse_release(version: &str) -> Option<SemVer> {
+    let mut parts = version.split('.').map(str::parse::<u64>);
+    let major = parts.next()?.ok()?;
+    let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+    let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+    return Some(SemVer::new(major, minor, patch));
+}
+
+/// The machine the rootfs was built for, which LG names after the chip.
+fn extract_machine(reader: &dyn RootfsReader, rootfs: &Path) -> Option<String> {
+    let etc = rootfs.join("etc");
+    return reader
+        .read_to_string(&etc.join("build"))
+        .ok()
+        .and_then(|build| machine_from_build(&build))
+        .or_else(|| {
+            let arch_conf = reader
+                .read_to_string(&etc.join("opkg").join("arch.conf"))
+                .ok()?;
+            return machine_from_arch_conf(&arch_conf);
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use semver::Version;
+
+    use super::detect_layout;
+    use crate::Platform;
+    use crate::reader::HostReader;
+    use crate::rootfs::FirmwareId;
+
+    #[test]
+    fn reads_an_ose_rootfs() {
+        let input = tempfile::TempDir::new().unwrap();
+        let root = input.path();
+        fs::create_dir_all(root.join("etc/opkg")).unwrap();
+        fs::create_dir_all(root.join("usr/lib")).unwrap();
+        fs::create_dir_all(root.join("var/lib/opkg/info")).unwrap();
+        fs::write(
+            root.join("etc/os-release"),
+            "ID=webos\nNAME=\"webOS OSE\"\nVERSION_ID=\"2.24.0\"\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("etc/opkg/arch.conf"),
+            "arch all 1\narch cortexa72 11\narch raspberrypi4 16\n",
+        )
+        .unwrap();
+
+        let layout = detect_layout(&HostReader, root).unwrap();
+        let info = layout
+            .fw_info(&HostReader, root, &FirmwareId::default())
+            .unwrap();
+
+        assert_eq!(info.release, Version::new(2, 24, 0));
+        assert_eq!(info.product.as_deref(), Some("webOS OSE"));
+        assert_eq!(info.dir_name(), "2.24.0-webos_OSE_raspberrypi4");
+        assert_eq!(info.board(), Some("raspberrypi4"));
+        assert_eq!(info.platform(), None);
+        assert_eq!(
+            layout.lib_paths(&HostReader, root),
+            vec![root.join("usr/lib")]
+        );
+        assert_eq!(
+            layout.opkg_dirs(&HostReader, root).unwrap(),
+            vec![root.join("var/lib/opkg")]
+        );
+    }
+
+    #[test]
+    fn marks_signage_by_name() {
+        let input = tempfile::TempDir::new().unwrap();
+        let root = input.path();
+        fs::create_dir_all(root.join("etc")).unwrap();
+        fs::write(
+            root.join("etc/os-release"),
+            "ID=webos\nNAME=\"webOS Signage\"\nVERSION_ID=6.1\n",
+        )
+        .unwrap();
+
+        let info = detect_layout(&HostReader, root)
+            .unwrap()
+            .fw_info(&HostReader, root, &FirmwareId::default())
+            .unwrap();
+
+        assert_eq!(info.release, Version::new(6, 1, 0));
+        assert_eq!(info.platform(), Some(Platform::Signage));
+        assert_eq!(info.ota_id, "webos_SGN");
+    }
+
+    #[test]
+    fn reads_a_bare_tv_rootfs() {
+        let input = tempfile::TempDir::new().unwrap();
+        let root = input.path().join("rootfs");
+        fs::create_dir_all(root.join("etc")).unwrap();
+        fs::create_dir_all(root.join("usr/lib/opkg")).unwrap();
+        fs::write(
+            root.join("etc/starfish-release"),
+            "Rockhopper release 11.2.0-37 (queue-qilian)\n",
+        )
+        .unwrap();
+        // Newer TVs have one too, but it does not say which TV.
+        fs::write(root.join("etc/os-release"), "ID=webos\nVERSION_ID=11.2.0\n").unwrap();
+
+        let layout = detect_layout(&HostReader, &root).unwrap();
+        assert!(
+            layout
+                .fw_info(&HostReader, &root, &FirmwareId::default())
+                .is_err()
+        );
+        let id = FirmwareId {
+            version: None,
+            ota_id: Some(String::from("HE_DTV_W24O_AFABATAA")),
+        };
+        let info = layout.fw_info(&HostReader, &root, &id).unwrap();
+
+        assert_eq!(info.release, Version::new(11, 2, 0));
+        assert_eq!(info.dir_name(), "11.2.0-HE_DTV_W24O_AFABATAA");
+        assert_eq!(info.board(), Some("W24O"));
+        assert_eq!(info.platform(), Some(Platform::Tv));
+        assert_eq!(
+            layout.opkg_dirs(&HostReader, &root).unwrap(),
+            vec![root.join("usr/lib/opkg")]
+        );
+
+        // Named like an unpacked image, it needs nothing more.
+        let named = input.path().join("33.21.70-HE_DTV_W24O_AFABATAA");
+        fs::rename(&root, &named).unwrap();
+        let info = detect_layout(&HostReader, &named)
+            .unwrap()
+            .fw_info(&HostReader, &named, &FirmwareId::default())
+            .unwrap();
+        assert_eq!(info.version, "33.21.70");
+    }
+}
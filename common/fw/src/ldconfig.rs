@@ -0,0 +1,400 @@
+//! The dynamic loader's configuration: `/etc/ld.so.conf` and the cache
+//! `ldconfig` builds from it.
+//!
+//! Past the trusted directories (`/lib`, `/usr/lib`), the loader searches no
+//! directory itself. It looks each name up in `/etc/ld.so.cache`, which
+//! `ldconfig` wrote from the directories `ld.so.conf` lists. The cache is
+//! keyed by soname, so it can name a library no file in those directories is
+//! called by.
+
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use path_slash::PathBufExt;
+use regex::Regex;
+
+use crate::reader::RootfsReader;
+
+/// How deep `include` directives may nest before the rest are ignored.
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+const CACHE_MAGIC_OLD: &[u8] = b"ld.so-1.7.0";
+const CACHE_MAGIC_NEW: &[u8] = b"glibc-ld.so.cache1.1";
+/// The size of the old format's header and of each of its entries.
+const OLD_HEADER_SIZE: usize = 16;
+const OLD_ENTRY_SIZE: usize = 12;
+/// The size of the new format's header and of each of its entries.
+const NEW_HEADER_SIZE: usize = 48;
+const NEW_ENTRY_SIZE: usize = 24;
+/// The new format's endianness flag for a big-endian target.
+const FLAG_BIG_ENDIAN: u8 = 3;
+
+/// One library in `ld.so.cache`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedLibrary {
+    /// The soname the loader finds it by.
+    pub name: String,
+    /// The file, as a path on the firmware.
+    pub path: String,
+}
+
+/// The directories an `ld.so.conf` lists, as paths on the firmware, in order
+/// and without duplicates. `host_path` maps a path on the firmware to the file
+/// to read, or `None` when it is nowhere in the input.
+pub(crate) fn read_ld_so_conf<F>(
+    reader: &dyn RootfsReader,
+    conf: &str,
+    host_path: &F,
+) -> Vec<String>
+where
+    F: Fn(&str) -> Option<PathBuf>,
+{
+    let mut dirs = Vec::new();
+    read_conf_file(reader, conf, host_path, 0, &mut dirs);
+    return dirs;
+}
+
+fn read_conf_file<F>(
+    reader: &dyn RootfsReader,
+    conf: &str,
+    host_path: &F,
+    depth: usize,
+    dirs: &mut Vec<String>,
+) where
+    F: Fn(&str) -> Option<PathBuf>,
+{
+    let Some(text) = host_path(conf).and_then(|path| reader.read_to_string(&path).ok()) else {
+        return;
+    };
+    let conf_dir = conf.rsplit_once('/').map_or("", |(dir, _)| dir);
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let mut words = line.split_whitespace();
+        match words.next() {
+            // A `hwcap` line names a hardware capability subdirectory. It adds
+            // no directory.
+            None | Some("hwcap") => {}
+            Some("include") => {
+                if depth >= MAX_INCLUDE_DEPTH {
+                    continue;
+                }
+                for pattern in words {
+                    // A relative pattern is relative to the including file.
+                    let pattern = if pattern.starts_with('/') {
+                        String::from(pattern)
+                    } else {
+                        format!("{conf_dir}/{pattern}")
+                    };
+                    for file in expand_glob(reader, &pattern, host_path) {
+                        read_conf_file(reader, &file, host_path, depth + 1, dirs);
+                    }
+                }
+            }
+            Some(_) => {
+                for dir in line.split(|c: char| c.is_whitespace() || c == ',' || c == ':') {
+                    // Old versions allowed a library type after the directory,
+                    // as in `/usr/lib=libc6`.
+                    let dir = dir.split('=').next().unwrap_or_default();
+                    let dir = dir.trim_end_matches('/');
+                    if dir.starts_with('/') && !dirs.iter().any(|seen| seen == dir) {
+                        dirs.push(String::from(dir));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The files on the firmware an absolute `pattern` matches, sorted, as `glob`
+/// would return them to `ldconfig`.
+fn expand_glob<F>(reader: &dyn RootfsReader, pattern: &str, host_path: &F) -> Vec<String>
+where
+    F: Fn(&str) -> Option<PathBuf>,
+{
+    let mut matches = vec![String::new()];
+    for component in pattern.split('/').filter(|c| !c.is_empty()) {
+        if !component.contains(['*', '?', '[']) {
+            for path in &mut matches {
+                path.push('/');
+                path.push_str(component);
+            }
+            continue;
+        }
+        let Some(regex) = glob_regex(component) else {
+            return Vec::new();
+        };
+        let mut next = Vec::new();
+        for dir in &matches {
+            let Some(entries) = host_path(if dir.is_empty() { "/" } else { dir })
+                .and_then(|host| reader.read_dir(&host).ok())
+            else {
+                continue;
+            };
+            let mut names: Vec<String> = entries
+                .into_iter()
+                .filter_map(|path| path.file_name()?.to_str().map(String::from))
+                .filter(|name| !name.starts_with('.') && regex.is_match(name))
+                .collect();
+            names.sort();
+            next.extend(names.into_iter().map(|name| format!("{dir}/{name}")));
+        }
+        matches = next;
+    }
+    matches.retain(|path| host_path(path).is_some_and(|host| reader.is_file(&host)));
+    return matches;
+}
+
+/// A regex matching what one glob path component does.
+fn glob_regex(component: &str) -> Option<Regex> {
+    let mut regex = String::from("^");
+    let mut chars = component.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' => {
+                regex.push('[');
+                if chars.next_if_eq(&'!').is_some() {
+                    regex.push('^');
+                }
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                    if c == '\\' {
+                        regex.push('\\');
+                    }
+                    regex.push(c);
+                }
+                regex.push(']');
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    return Regex::new(&regex).ok();
+}
+
+/// Every library in an `ld.so.cache`, in the order the loader tries them. Reads
+/// the old format, the new one, and the old one with the new one after it.
+pub fn read_ld_so_cache(data: &[u8]) -> Result<Vec<CachedLibrary>, Error> {
+    if data.starts_with(CACHE_MAGIC_NEW) {
+        return read_new_cache(data, 0);
+    }
+    if !data.starts_with(CACHE_MAGIC_OLD) {
+        return Err(bad_cache("unknown format"));
+    }
+    // The old format records no byte order. A count that does not fit the
+    // file means the other one.
+    let (nlibs, big_endian) = [false, true]
+        .into_iter()
+        .find_map(|big_endian| {
+            let nlibs = read_u32(data, OLD_HEADER_SIZE - 4, big_endian)? as usize;
+            let end = OLD_HEADER_SIZE.checked_add(nlibs.checked_mul(OLD_ENTRY_SIZE)?)?;
+            return (end <= data.len()).then_some((nlibs, big_endian));
+        })
+        .ok_or_else(|| bad_cache("library count out of range"))?;
+    let entries_end = OLD_HEADER_SIZE + nlibs * OLD_ENTRY_SIZE;
+    // ldconfig writes the new format after the old one, aligned, and the
+    // loader prefers it.
+    let new_start = entries_end.next_multiple_of(8);
+    if data
+        .get(new_start..)
+        .is_some_and(|rest| rest.starts_with(CACHE_MAGIC_NEW))
+    {
+        return read_new_cache(data, new_start);
+    }
+    let mut libs = Vec::with_capacity(nlibs);
+    for i in 0..nlibs {
+        let entry = OLD_HEADER_SIZE + i * OLD_ENTRY_SIZE;
+        let (Some(key), Some(value)) = (
+            read_u32(data, entry + 4, big_endian),
+            read_u32(data, entry + 8, big_endian),
+        ) else {
+            return Err(bad_cache("truncated entry"));
+        };
+        libs.push(CachedLibrary {
+            name: read_string(data, entries_end, key)?,
+            path: read_string(data, entries_end, value)?,
+        });
+    }
+    return Ok(libs);
+}
+
+/// The new format, from its header at `start`. Its strings are placed from
+/// the header.
+fn read_new_cache(data: &[u8], start: usize) -> Result<Vec<CachedLibrary>, Error> {
+    let header = data
+        .get(start..start + NEW_HEADER_SIZE)
+        .ok_or_else(|| bad_cache("truncated header"))?;
+    let big_endian = header[28] & 3 == FLAG_BIG_ENDIAN;
+    let nlibs = read_u32(header, 20, big_endian).unwrap_or_default() as usize;
+    let entries = start + NEW_HEADER_SIZE;
+    if nlibs
+        .checked_mul(NEW_ENTRY_SIZE)
+        .and_then(|size| size.checked_add(entries))
+        .is_none_or(|end| end > data.len())
+    {
+        return Err(bad_cache("library count out of range"));
+    }
+    let mut libs = Vec::with_capacity(nlibs);
+    for i in 0..nlibs {
+        let entry = entries + i * NEW_ENTRY_SIZE;
+        let (Some(key), Some(value)) = (
+            read_u32(data, entry + 4, big_endian),
+            read_u32(data, entry + 8, big_endian),
+        ) else {
+            return Err(bad_cache("truncated entry"));
+        };
+        libs.push(CachedLibrary {
+            name: read_string(data, start, key)?,
+            path: read_string(data, start, value)?,
+        });
+    }
+    return Ok(libs);
+}
+
+fn read_u32(data: &[u8], offset: usize, big_endian: bool) -> Option<u32> {
+    let bytes: [u8; 4] = data.get(offset..offset + 4)?.try_into().ok()?;
+    return Some(if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    });
+}
+
+/// The NUL-terminated string `offset` bytes after `base`.
+fn read_string(data: &[u8], base: usize, offset: u32) -> Result<String, Error> {
+    let start = base + offset as usize;
+    let bytes = data
+        .get(start..)
+        .ok_or_else(|| bad_cache("string offset out of range"))?;
+    let end = bytes
+        .iter()
+        .position(|b| *b == 0)
+        .ok_or_else(|| bad_cache("unterminated string"))?;
+    return Ok(String::from_utf8_lossy(&bytes[..end]).into_owned());
+}
+
+fn bad_cache(reason: &str) -> Error {
+    return Error::new(ErrorKind::InvalidData, format!("Bad ld.so.cache: {reason}"));
+}
+
+/// Map a path on the firmware into `root`.
+pub(crate) fn in_root(root: &Path, path: &str) -> PathBuf {
+    return root.join(PathBuf::from_slash(path.trim_start_matches('/')));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{CachedLibrary, in_root, read_ld_so_cache, read_ld_so_conf};
+    use crate::reader::HostReader;
+
+    fn le(n: usize) -> [u8; 4] {
+        return u32::try_from(n).unwrap().to_le_bytes();
+    }
+
+    /// A cache in the new format, as ldconfig writes it for a little-endian
+    /// target, optionally after the old one.
+    fn cache(libs: &[(&str, &str)], with_old: bool) -> Vec<u8> {
+        let mut strings = Vec::new();
+        let mut offsets = Vec::new();
+        let strings_start = 48 + 24 * libs.len();
+        for (name, path) in libs {
+            let name_offset = strings_start + strings.len();
+            strings.extend_from_slice(name.as_bytes());
+            strings.push(0);
+            let path_offset = strings_start + strings.len();
+            strings.extend_from_slice(path.as_bytes());
+            strings.push(0);
+            offsets.push((le(name_offset), le(path_offset)));
+        }
+        let mut new = b"glibc-ld.so.cache1.1".to_vec();
+        new.extend_from_slice(&le(libs.len()));
+        new.extend_from_slice(&le(strings.len()));
+        new.extend_from_slice(&[2, 0, 0, 0]);
+        new.extend_from_slice(&[0; 16]);
+        for (key, value) in &offsets {
+            new.extend_from_slice(&0x0303_i32.to_le_bytes());
+            new.extend_from_slice(key);
+            new.extend_from_slice(value);
+            new.extend_from_slice(&[0; 12]);
+        }
+        new.extend_from_slice(&strings);
+        if !with_old {
+            return new;
+        }
+        // The old entries are never read when the new format follows.
+        let mut data = b"ld.so-1.7.0\0".to_vec();
+        data.extend_from_slice(&le(libs.len()));
+        data.resize(16 + 12 * libs.len(), 0);
+        data.resize(data.len().next_multiple_of(8), 0);
+        data.extend_from_slice(&new);
+        return data;
+    }
+
+    #[test]
+    fn reads_both_cache_formats() {
+        let libs = [
+            ("libfoo.so.1", "/usr/lib/vendor/libfoo.so.1.2"),
+            ("libc.so.6", "/lib/libc.so.6"),
+        ];
+        let expected: Vec<CachedLibrary> = libs
+            .iter()
+            .map(|(name, path)| CachedLibrary {
+                name: String::from(*name),
+                path: String::from(*path),
+            })
+            .collect();
+
+        assert_eq!(read_ld_so_cache(&cache(&libs, false)).unwrap(), expected);
+        assert_eq!(read_ld_so_cache(&cache(&libs, true)).unwrap(), expected);
+        assert!(read_ld_so_cache(b"not a cache").is_err());
+        assert!(read_ld_so_cache(&cache(&libs, false)[..60]).is_err());
+    }
+
+    #[test]
+    fn reads_old_cache_format() {
+        let mut data = b"ld.so-1.7.0\0".to_vec();
+        data.extend_from_slice(&1_u32.to_le_bytes());
+        data.extend_from_slice(&1_i32.to_le_bytes());
+        data.extend_from_slice(&0_u32.to_le_bytes());
+        data.extend_from_slice(&8_u32.to_le_bytes());
+        data.extend_from_slice(b"libz.so\0/lib/libz.so.1\0");
+
+        assert_eq!(
+            read_ld_so_cache(&data).unwrap(),
+            vec![CachedLibrary {
+                name: String::from("libz.so"),
+                path: String::from("/lib/libz.so.1"),
+            }]
+        );
+    }
+
+    #[test]
+    fn follows_includes_and_skips_comments() {
+        let input = tempfile::TempDir::new().unwrap();
+        let root = input.path();
+        fs::create_dir_all(root.join("etc/ld.so.conf.d")).unwrap();
+        fs::write(
+            root.join("etc/ld.so.conf"),
+            "# Vendor libraries\n/usr/lib/vendor\ninclude ld.so.conf.d/*.conf\nhwcap 0 neon\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("etc/ld.so.conf.d/b.conf"),
+            "/opt/b/lib, /usr/lib/vendor/ # again\n",
+        )
+        .unwrap();
+        fs::write(root.join("etc/ld.so.conf.d/a.conf"), "/opt/a/lib=libc6\n").unwrap();
+        fs::write(root.join("etc/ld.so.conf.d/notes.txt"), "/opt/notes\n").unwrap();
+
+        let dirs = read_ld_so_conf(&HostReader, "/etc/ld.so.conf", &|path: &str| {
+            Some(in_root(root, path))
+        });
+
+        assert_eq!(dirs, vec!["/usr/lib/vendor", "/opt/a/lib", "/opt/b/lib"]);
+    }
+}
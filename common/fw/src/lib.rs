@@ -1,16 +1,38 @@
+use bin_lib::{ArmAttributes, ElfArch};
 use semver::Version;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{Display, Formatter};
 use std::path::PathBuf;
+use std::sync::Arc;
 
+pub mod capabilities;
+pub mod device;
 pub mod firmware;
+pub mod jail;
+pub(crate) mod layout;
+pub mod ldconfig;
+pub mod luna;
+pub mod packed;
+pub mod reader;
+pub mod rootfs;
 pub mod runtime;
+pub mod validate;
 pub(crate) mod version;
 use version::version_deserialize;
 use version::version_serialize;
+use version::{optional_version_deserialize, optional_version_serialize};
 
+pub use capabilities::{BundledCopy, Middleware};
+pub use device::{DeviceFilter, Platform, group_by_device};
+pub use jail::Jail;
+pub use luna::LunaRegistry;
 pub use runtime::WebEngine;
 
+/// Extra firmware data directories, separated like `PATH`. See
+/// [`Firmware::data_dirs`].
+pub const DATA_DIR_ENV: &str = "WEBOSBREW_FW_DATA";
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FirmwareInfo {
     pub version: String,
@@ -20,30 +42,116 @@ pub struct FirmwareInfo {
         deserialize_with = "version_deserialize"
     )]
     pub release: Version,
+    /// The chip, such as `k8lp`, from the rootfs's machine config. See
+    /// [`FirmwareInfo::board`] and [`FirmwareInfo::platform`] for the others.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub soc: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub board: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform: Option<Platform>,
+    /// The product family, such as `webOS TV` or `webOS OSE`. Data extracted
+    /// before it was recorded is all `webOS TV`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub product: Option<String>,
+    /// The Node.js version, read from the `node` binary. Preferred over the
+    /// one in `packages.json`. See [`Firmware::node_version`].
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "optional_version_serialize",
+        deserialize_with = "optional_version_deserialize"
+    )]
+    pub node: Option<Version>,
+    /// The web engine, read from the web runtime's libraries. Preferred over
+    /// the one in `packages.json`. See [`Firmware::web_engine`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub web_engine: Option<WebEngine>,
+    /// The CPU features and float ABI the firmware's libc and libstdc++ are
+    /// built for, which a package's ARM binaries must not exceed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu: Option<ArmAttributes>,
+    /// The class and machine of the firmware's own userland, from the first
+    /// `libc.so.6` the loader finds. A library of another one is indexed under
+    /// its [`ElfArch::tag`]. See [`library_key`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arch: Option<ElfArch>,
 }
 
 #[derive(Debug)]
 pub struct Firmware {
     pub info: FirmwareInfo,
-    path: PathBuf,
-    index: HashMap<String, String>,
+    /// The data directory the firmware was listed from, or the firmware's own
+    /// directory or pack when it was loaded on its own.
+    pub data_dir: PathBuf,
+    libraries: Libraries,
     packages: HashMap<String, PackageEntry>,
+    executables: Option<BTreeSet<String>>,
+    luna: Option<LunaRegistry>,
+    jail: Option<Jail>,
+}
+
+/// Where a firmware's library info is read from.
+#[derive(Debug)]
+pub(crate) enum Libraries {
+    /// A firmware directory: `index.json` maps each key to a JSON file. See
+    /// [`library_key`].
+    Dir {
+        path: PathBuf,
+        index: HashMap<String, String>,
+    },
+    /// A pack, shared with the other firmwares loaded from it. See
+    /// [`packed`].
+    Packed {
+        db: Arc<packed::PackedLibraries>,
+        index: BTreeMap<String, u32>,
+    },
+    /// A rootfs: each name maps to the file it resolves to, parsed when first
+    /// looked up. See [`rootfs`].
+    Rootfs {
+        reader: Arc<dyn reader::RootfsReader>,
+        index: HashMap<String, PathBuf>,
+        package_files: BTreeMap<PathBuf, String>,
+        parsed: rootfs::ParsedLibraries,
+    },
+}
+
+/// The key a library called `name` is indexed by: the name under the tag of
+/// its class and machine, as in `elf32-arm/libc.so.6`. A file whose class and
+/// machine are unknown, such as a linker script, and data extracted before
+/// libraries were keyed this way, have the bare name.
+pub fn library_key(name: &str, arch: Option<ElfArch>) -> String {
+    return match arch {
+        Some(arch) => format!("{}/{name}", arch.tag()),
+        None => String::from(name),
+    };
 }
 
 /// One entry in a firmware's `packages.json`, e.g.
 /// `"lib32-nodejs": { "version": { "upstream": "16.20.2", ... } }`.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct PackageEntry {
     pub version: PackageVersion,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct PackageVersion {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub epoch: Option<u32>,
     pub upstream: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub debian_revision: Option<String>,
 }
 
+impl Display for PackageVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.debian_revision {
+            Some(revision) => f.write_fmt(format_args!("{}-{revision}", self.upstream)),
+            None => f.write_str(&self.upstream),
+        }
+    }
+}
+
 pub enum ReleaseCodename {
     Afro,
     Beehive,
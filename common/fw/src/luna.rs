@@ -0,0 +1,338 @@
+//! The Luna bus services a firmware registers, read from the luna-service2
+//! configuration in its rootfs.
+//!
+//! Service files (`*.service`, D-Bus style) and role files name the services.
+//! API permission files group their methods into what a client may be granted,
+//! as `service/method` or `service/category/*`. A method in no group cannot be
+//! called by an app, so a service's methods are only known when it has one.
+//! The groups are also the ACG (access control group) names an app asks for in
+//! its `requiredPermissions`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use path_slash::PathBufExt;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::reader::RootfsReader;
+
+/// Where luna-service2 keeps its configuration, from the root. Older firmwares
+/// use `ls2`.
+pub const LUNA_DIRS: [&str; 2] = ["usr/share/luna-service2", "usr/share/ls2"];
+
+/// The subdirectories holding service files.
+const SERVICE_DIRS: [&str; 3] = ["services.d", "system-services", "services"];
+/// The subdirectories holding role files. `roles/pub` and `roles/prv` are the
+/// public and private buses of the old format.
+const ROLE_DIRS: [&str; 3] = ["roles.d", "roles/pub", "roles/prv"];
+/// The subdirectory holding API permission files.
+const API_PERMISSION_DIR: &str = "api-permissions.d";
+
+/// Every service on a firmware, the methods it is known to have, and the
+/// permission groups they are in. Service names and methods may be patterns
+/// with `*` in them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LunaRegistry {
+    /// Service name to its methods, relative to the service, as in
+    /// `getVolume` or `master/*`. Empty when none are listed.
+    #[serde(default)]
+    pub services: BTreeMap<String, BTreeSet<String>>,
+    /// Permission group name to its entries, as in `com.webos.audio/master/*`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub groups: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl LunaRegistry {
+    /// Read the registry under `root`. Files that cannot be read or parsed
+    /// are added to `skipped` and left out.
+    pub fn read(reader: &dyn RootfsReader, root: &Path, skipped: &mut Vec<String>) -> LunaRegistry {
+        let mut registry = LunaRegistry::default();
+        for dir in LUNA_DIRS {
+            let dir = root.join(PathBuf::from_slash(dir));
+            for sub in SERVICE_DIRS {
+                for (path, text) in read_files(reader, &dir.join(sub), "service", skipped) {
+                    let names = service_names(&text);
+                    if names.is_empty() {
+                        skipped.push(format!("{}: no Name", path.to_string_lossy()));
+                    }
+                    registry.add_services(names);
+                }
+            }
+            for sub in ROLE_DIRS {
+                for (path, text) in read_files(reader, &dir.join(sub), "json", skipped) {
+                    match serde_json::from_str(&text) {
+                        Ok(role) => registry.add_services(role_names(&role)),
+                        Err(e) => skipped.push(format!("{}: {e}", path.to_string_lossy())),
+                    }
+                }
+            }
+            for (path, text) in read_files(reader, &dir.join(API_PERMISSION_DIR), "json", skipped) {
+                match serde_json::from_str::<BTreeMap<String, Vec<String>>>(&text) {
+                    Ok(groups) => {
+                        for (group, entries) in groups {
+                            for entry in &entries {
+                                registry.add_method(entry);
+                            }
+                            registry.groups.entry(group).or_default().extend(entries);
+                        }
+                    }
+                    Err(e) => skipped.push(format!("{}: {e}", path.to_string_lossy())),
+                }
+            }
+        }
+        return registry;
+    }
+
+    fn add_services<I: IntoIterator<Item = String>>(&mut self, names: I) {
+        // A role that may take any name says nothing about which are taken.
+        for name in names.into_iter().filter(|name| name != "*") {
+            self.services.entry(name).or_default();
+        }
+    }
+
+    /// Add a `service/method` entry from an API permission file. One without
+    /// a method covers the whole service.
+    fn add_method(&mut self, entry: &str) {
+        let (service, method) = entry.split_once('/').unwrap_or((entry, "*"));
+        let method = method.trim_start_matches('/');
+        self.services
+            .entry(String::from(service))
+            .or_default()
+            .insert(String::from(if method.is_empty() { "*" } else { method }));
+    }
+
+    /// The methods of the service called `name`, merged from every entry that
+    /// matches it. `None` when there is no such service.
+    fn methods(&self, name: &str) -> Option<BTreeSet<&str>> {
+        let mut found = false;
+        let mut methods = BTreeSet::new();
+        for (service, service_methods) in &self.services {
+            if matches(service, name) {
+                found = true;
+                methods.extend(service_methods.iter().map(String::as_str));
+            }
+        }
+        return found.then_some(methods);
+    }
+
+    /// Whether a service called `name` is registered.
+    pub fn has_service(&self, name: &str) -> bool {
+        return self.methods(name).is_some();
+    }
+
+    /// The permission groups that grant calling `method` on `service`.
+    pub fn groups_granting(&self, service: &str, method: &str) -> Vec<&str> {
+        let method = method.trim_matches('/');
+        return self
+            .groups
+            .iter()
+            .filter(|(_, entries)| {
+                return entries.iter().any(|entry| {
+                    let (pattern, methods) = entry.split_once('/').unwrap_or((entry, "*"));
+                    let methods = methods.trim_start_matches('/');
+                    return matches(pattern, service)
+                        && (methods.is_empty() || matches(methods, method));
+                });
+            })
+            .map(|(group, _)| group.as_str())
+            .collect();
+    }
+
+    /// Whether the service has `method`, as in `getVolume` or
+    /// `master/getVolume`. `None` when the service is missing or its methods
+    /// are not known.
+    pub fn has_method(&self, service: &str, method: &str) -> Option<bool> {
+        let methods = self.methods(service)?;
+        if methods.is_empty() {
+            return None;
+        }
+        let method = method.trim_matches('/');
+        return Some(methods.iter().any(|pattern| matches(pattern, method)));
+    }
+}
+
+/// The files with `ext` directly in `dir`, in name order. A missing directory
+/// has none.
+fn read_files(
+    reader: &dyn RootfsReader,
+    dir: &Path,
+    ext: &str,
+    skipped: &mut Vec<String>,
+) -> Vec<(PathBuf, String)> {
+    let Ok(entries) = reader.read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .into_iter()
+        .filter(|path| path.extension().is_some_and(|e| e == ext) && reader.is_file(path))
+        .collect();
+    paths.sort();
+    return paths
+        .into_iter()
+        .filter_map(|path| match reader.read_to_string(&path) {
+            Ok(text) => Some((path, text)),
+            Err(e) => {
+                skipped.push(format!("{}: {e}", path.to_string_lossy()));
+                None
+            }
+        })
+        .collect();
+}
+
+/// The names in a service file's `Name=` lines. One line may list several,
+/// separated by `;`.
+fn service_names(text: &str) -> Vec<String> {
+    return text
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("Name="))
+        .flat_map(|names| names.split(';'))
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(String::from)
+        .collect();
+}
+
+/// The services a role file lets its executable register. The new format has
+/// `allowedNames` at the top, the old one under `role`, with each permission
+/// naming a service too.
+fn role_names(role: &Value) -> Vec<String> {
+    let strings = |value: Option<&Value>| -> Vec<String> {
+        return value
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .filter(|name| !name.is_empty())
+            .map(String::from)
+            .collect();
+    };
+    let mut names = strings(role.get("allowedNames"));
+    names.extend(strings(
+        role.get("role").and_then(|r| r.get("allowedNames")),
+    ));
+    if let Some(permissions) = role.get("permissions").and_then(Value::as_array) {
+        names.extend(
+            permissions
+                .iter()
+                .filter_map(|p| p.get("service").and_then(Value::as_str))
+                .filter(|name| !name.is_empty())
+                .map(String::from),
+        );
+    }
+    return names;
+}
+
+/// Whether `name` matches `pattern`, where each `*` stands for any run of
+/// characters.
+fn matches(pattern: &str, name: &str) -> bool {
+    let Some((first, rest)) = pattern.split_once('*') else {
+        return pattern == name;
+    };
+    let Some(mut name) = name.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = rest.split('*').collect();
+    let last = parts.pop().unwrap_or_default();
+    for part in parts {
+        match name.find(part) {
+            Some(pos) => name = &name[pos + part.len()..],
+            None => return false,
+        }
+    }
+    return name.len() >= last.len() && name.ends_with(last);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{LunaRegistry, matches};
+    use crate::reader::HostReader;
+
+    #[test]
+    fn reads_services_roles_and_permissions() {
+        let root = tempfile::TempDir::new().unwrap();
+        let ls2 = root.path().join("usr/share/luna-service2");
+        for dir in ["services.d", "roles.d", "api-permissions.d"] {
+            fs::create_dir_all(ls2.join(dir)).unwrap();
+        }
+        fs::create_dir_all(root.path().join("usr/share/ls2/roles/pub")).unwrap();
+        fs::write(
+            ls2.join("services.d/audio.service"),
+            "[D-BUS Service]\nName=com.webos.audio;com.palm.audio\nExec=/usr/sbin/audiod\n",
+        )
+        .unwrap();
+        fs::write(
+            ls2.join("roles.d/settings.role.json"),
+            r#"{"exeName": "/usr/sbin/settingsd", "allowedNames": ["com.webos.settingsservice*"]}"#,
+        )
+        .unwrap();
+        fs::write(
+            root.path().join("usr/share/ls2/roles/pub/old.json"),
+            r#"{"role": {"exeName": "x", "allowedNames": ["com.palm.old"]},
+                "permissions": [{"service": "com.palm.other", "outbound": ["*"]}]}"#,
+        )
+        .unwrap();
+        fs::write(
+            ls2.join("api-permissions.d/audio.api.json"),
+            r#"{"audio.operation": ["com.webos.audio/master/*", "com.webos.audio/getStatus"],
+                "apps": ["com.webos.applicationManager/launch"]}"#,
+        )
+        .unwrap();
+        fs::write(ls2.join("api-permissions.d/broken.api.json"), "{").unwrap();
+
+        let mut skipped = Vec::new();
+        let registry = LunaRegistry::read(&HostReader, root.path(), &mut skipped);
+
+        assert_eq!(skipped.len(), 1);
+        for service in [
+            "com.webos.audio",
+            "com.palm.audio",
+            "com.webos.settingsservice",
+            "com.palm.old",
+            "com.palm.other",
+            "com.webos.applicationManager",
+        ] {
+            assert!(registry.has_service(service), "{service}");
+        }
+        assert!(!registry.has_service("com.webos.service.tv"));
+        assert_eq!(
+            registry.has_method("com.webos.audio", "master/getVolume"),
+            Some(true)
+        );
+        assert_eq!(
+            registry.has_method("com.webos.audio", "/getStatus"),
+            Some(true)
+        );
+        assert_eq!(
+            registry.has_method("com.webos.audio", "setMuted"),
+            Some(false)
+        );
+        assert_eq!(registry.has_method("com.palm.audio", "anything"), None);
+        assert_eq!(registry.has_method("com.webos.nothing", "getStatus"), None);
+        assert_eq!(
+            registry.groups.keys().collect::<Vec<_>>(),
+            vec!["apps", "audio.operation"]
+        );
+        assert_eq!(
+            registry.groups_granting("com.webos.audio", "master/setVolume"),
+            vec!["audio.operation"]
+        );
+        assert!(
+            registry
+                .groups_granting("com.webos.audio", "setMuted")
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn matches_wildcards() {
+        assert!(matches("com.webos.*", "com.webos.audio"));
+        assert!(matches("*", ""));
+        assert!(matches("a*b*c", "aXbYc"));
+        assert!(!matches("a*b*c", "aXcYb"));
+        assert!(!matches("ab*ba", "aba"));
+        assert!(!matches("com.webos.audio", "com.webos.audio2"));
+    }
+}
@@ -0,0 +1,455 @@
+//! The packed firmware database: the libraries of many firmwares in one file.
+//!
+//! The directory layout keeps one JSON file per library, so libc's symbol list
+//! is written again for every firmware and [`Firmware::list`] opens thousands
+//! of files. A pack stores each distinct string once and each distinct library
+//! once across all the firmwares in it, and refers to both by index. It is
+//! still JSON, written without indentation.
+//!
+//! [`PackBuilder`] imports firmware directories, so the directory layout stays
+//! the one fw-extract writes and a pack is made from it.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Error, ErrorKind};
+use std::path::Path;
+use std::sync::Arc;
+
+use bin_lib::LibraryInfo;
+use serde::{Deserialize, Serialize};
+
+use crate::{Firmware, FirmwareInfo, Jail, Libraries, LunaRegistry};
+
+/// The file extension of a pack in a data directory.
+pub const PACK_EXTENSION: &str = "fwdb";
+
+/// Bumped whenever the layout below changes incompatibly.
+const PACK_FORMAT: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PackedDb {
+    format: u32,
+    #[serde(flatten)]
+    libraries: PackedLibraries,
+    firmwares: Vec<PackedFirmware>,
+}
+
+/// The part of a pack every firmware loaded from it shares.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct PackedLibraries {
+    /// Sorted, and front-coded in the file. See [`front_coded`].
+    #[serde(with = "front_coded")]
+    strings: Vec<String>,
+    libraries: Vec<PackedLibrary>,
+}
+
+/// A [`LibraryInfo`] with every string replaced by its index in `strings`.
+/// The strings are sorted, so a sorted symbol list is a rising run of indices
+/// and `symbols` holds only the gap from each index to the one before it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+struct PackedLibrary {
+    name: u32,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    package: Option<u32>,
+    needed: Vec<u32>,
+    symbols: Vec<u32>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    names: Vec<u32>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    undefined: Vec<u32>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    undefined_lazy: Vec<u32>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    dlopen: Vec<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PackedFirmware {
+    info: FirmwareInfo,
+    /// Library name to its index in `libraries`.
+    index: BTreeMap<String, u32>,
+    /// The firmware's `packages.json` as is.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    packages: Option<serde_json::Value>,
+    /// The firmware's `executables.json`, if it has one.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    executables: Option<BTreeSet<String>>,
+    /// The firmware's `luna.json`, if it has one.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    luna: Option<LunaRegistry>,
+    /// The firmware's `jail.json`, if it has one.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    jail: Option<Jail>,
+}
+
+impl PackedLibraries {
+    pub(crate) fn library(&self, index: u32) -> LibraryInfo {
+        let lib = &self.libraries[index as usize];
+        let string = |index: &u32| self.strings[*index as usize].clone();
+        let strings = |indices: &[u32]| indices.iter().map(string).collect();
+        return LibraryInfo {
+            name: string(&lib.name),
+            package: lib.package.as_ref().map(string),
+            needed: strings(&lib.needed),
+            symbols: undo_gaps(&lib.symbols).map(|i| string(&i)).collect(),
+            names: strings(&lib.names),
+            undefined: strings(&lib.undefined),
+            undefined_lazy: strings(&lib.undefined_lazy),
+            dlopen: strings(&lib.dlopen),
+            arm: None,
+            rpath: Vec::new(),
+            priority: bin_lib::LibraryPriority::default(),
+        };
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        return self.libraries.len();
+    }
+}
+
+impl PackedDb {
+    /// Reject a pack whose indices point past its tables, so that lookups
+    /// never have to.
+    fn validate(&self) -> Result<(), String> {
+        let strings = self.libraries.strings.len();
+        let in_strings = |indices: &[u32]| indices.iter().all(|i| (*i as usize) < strings);
+        for lib in &self.libraries.libraries {
+            let symbols: Vec<u32> = undo_gaps(&lib.symbols).collect();
+            if symbols.len() != lib.symbols.len() {
+                return Err(String::from("symbol index overflow"));
+            }
+            let fields = [
+                std::slice::from_ref(&lib.name),
+                lib.package.as_slice(),
+                &lib.needed,
+                &symbols,
+                &lib.names,
+                &lib.undefined,
+                &lib.undefined_lazy,
+                &lib.dlopen,
+            ];
+            if !fields.iter().all(|field| in_strings(field)) {
+                return Err(String::from("string index out of range"));
+            }
+        }
+        let libraries = self.libraries.len();
+        for firmware in &self.firmwares {
+            if firmware.index.values().any(|i| *i as usize >= libraries) {
+                return Err(format!("library index out of range in {}", firmware.info));
+            }
+        }
+        return Ok(());
+    }
+}
+
+impl Firmware {
+    /// Load every firmware in a pack.
+    pub fn load_pack<P>(path: P) -> Result<Vec<Firmware>, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let db: PackedDb = File::open(path).and_then(|file| {
+            return serde_json::from_reader(BufReader::new(file)).map_err(|e| {
+                Error::new(ErrorKind::InvalidData, format!("Bad firmware pack: {e:?}"))
+            });
+        })?;
+        if db.format != PACK_FORMAT {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Unsupported firmware pack format {} in {}",
+                    db.format,
+                    path.to_string_lossy()
+                ),
+            ));
+        }
+        db.validate()
+            .map_err(|e| Error::new(ErrorKind::InvalidData, format!("Bad firmware pack: {e}")))?;
+        let shared = Arc::new(db.libraries);
+        return Ok(db
+            .firmwares
+            .into_iter()
+            .map(|firmware| Firmware {
+                info: firmware.info,
+                data_dir: path.to_path_buf(),
+                libraries: Libraries::Packed {
+                    db: shared.clone(),
+                    index: firmware.index,
+                },
+                packages: firmware
+                    .packages
+                    .and_then(|packages| serde_json::from_value(packages).ok())
+                    .unwrap_or_default(),
+                executables: firmware.executables,
+                luna: firmware.luna,
+                jail: firmware.jail,
+            })
+            .collect());
+    }
+}
+
+/// Builds a pack from firmware directories.
+#[derive(Default)]
+pub struct PackBuilder {
+    libraries: PackedLibraries,
+    firmwares: Vec<PackedFirmware>,
+    string_ids: HashMap<String, u32>,
+    library_ids: HashMap<PackedLibrary, u32>,
+}
+
+impl PackBuilder {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// Import one firmware directory: its `info.json`, `index.json`, every
+    /// library file the index names, and `packages.json`, `executables.json`,
+    /// `luna.json` and `jail.json` if there are.
+    pub fn add_dir<P>(&mut self, path: P) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let info: FirmwareInfo = read_json(&path.join("info.json"), "firmware info")?;
+        let files: BTreeMap<String, String> =
+            read_json(&path.join("index.json"), "firmware index")?;
+        let packages: Option<serde_json::Value> =
+            read_json(&path.join("packages.json"), "package list").ok();
+        let executables: Option<BTreeSet<String>> =
+            read_json(&path.join("executables.json"), "executable list").ok();
+        let luna: Option<LunaRegistry> = read_json(&path.join("luna.json"), "Luna services").ok();
+        let jail: Option<Jail> = read_json(&path.join("jail.json"), "jail").ok();
+
+        // Several names point at the same file. Read each file once.
+        let mut file_ids: HashMap<&str, u32> = HashMap::new();
+        let mut index: BTreeMap<String, u32> = BTreeMap::new();
+        for (name, file) in &files {
+            let id = if let Some(id) = file_ids.get(file.as_str()) {
+                *id
+            } else {
+                let lib: LibraryInfo = match read_json(&path.join(file), "library info") {
+                    Ok(lib) => lib,
+                    // A name whose file is missing finds nothing in the
+                    // directory either. Leave it out.
+                    Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                    Err(e) => return Err(e),
+                };
+                let id = self.intern_library(&lib);
+                file_ids.insert(file, id);
+                id
+            };
+            index.insert(name.clone(), id);
+        }
+        self.firmwares.push(PackedFirmware {
+            info,
+            index,
+            packages,
+            executables,
+            luna,
+            jail,
+        });
+        return Ok(());
+    }
+
+    /// Write the pack to `path`.
+    pub fn write<P>(self, path: P) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        let mut libraries = self.libraries;
+        // Number the strings in sorted order, then store each symbol list as
+        // the gaps between its indices.
+        let mut order: Vec<usize> = (0..libraries.strings.len()).collect();
+        order.sort_by(|a, b| libraries.strings[*a].cmp(&libraries.strings[*b]));
+        let mut renumber = vec![0; order.len()];
+        for (new, old) in order.iter().enumerate() {
+            renumber[*old] = u32::try_from(new).expect("too many strings");
+        }
+        let mut strings: Vec<Option<String>> = libraries.strings.into_iter().map(Some).collect();
+        libraries.strings = order
+            .iter()
+            .map(|old| strings[*old].take().unwrap_or_default())
+            .collect();
+        let renumber_all = |indices: &mut Vec<u32>| {
+            for index in indices.iter_mut() {
+                *index = renumber[*index as usize];
+            }
+        };
+        for lib in &mut libraries.libraries {
+            lib.name = renumber[lib.name as usize];
+            if let Some(package) = &mut lib.package {
+                *package = renumber[*package as usize];
+            }
+            renumber_all(&mut lib.needed);
+            renumber_all(&mut lib.names);
+            renumber_all(&mut lib.undefined);
+            renumber_all(&mut lib.undefined_lazy);
+            renumber_all(&mut lib.dlopen);
+            renumber_all(&mut lib.symbols);
+            lib.symbols.sort_unstable();
+            let mut previous = 0;
+            for index in &mut lib.symbols {
+                (*index, previous) = (*index - previous, *index);
+            }
+        }
+        let db = PackedDb {
+            format: PACK_FORMAT,
+            libraries,
+            firmwares: self.firmwares,
+        };
+        let writer = BufWriter::new(File::create(path)?);
+        return serde_json::to_writer(writer, &db).map_err(Error::other);
+    }
+
+    fn intern_library(&mut self, lib: &LibraryInfo) -> u32 {
+        let packed = PackedLibrary {
+            name: self.intern(&lib.name),
+            package: lib.package.as_deref().map(|p| self.intern(p)),
+            needed: self.intern_all(&lib.needed),
+            symbols: self.intern_all(&lib.symbols),
+            names: self.intern_all(&lib.names),
+            undefined: self.intern_all(&lib.undefined),
+            undefined_lazy: self.intern_all(&lib.undefined_lazy),
+            dlopen: self.intern_all(&lib.dlopen),
+        };
+        if let Some(id) = self.library_ids.get(&packed) {
+            return *id;
+        }
+        let id = u32::try_from(self.libraries.libraries.len()).expect("too many libraries");
+        self.libraries.libraries.push(packed.clone());
+        self.library_ids.insert(packed, id);
+        return id;
+    }
+
+    fn intern_all(&mut self, strings: &[String]) -> Vec<u32> {
+        return strings.iter().map(|s| self.intern(s)).collect();
+    }
+
+    fn intern(&mut self, string: &str) -> u32 {
+        if let Some(id) = self.string_ids.get(string) {
+            return *id;
+        }
+        let id = u32::try_from(self.libraries.strings.len()).expect("too many strings");
+        self.libraries.strings.push(String::from(string));
+        self.string_ids.insert(String::from(string), id);
+        return id;
+    }
+}
+
+/// A sorted string table as `[shared, suffix]` pairs: the length in bytes of
+/// the prefix a string shares with the one before it, then the rest. Mangled
+/// C++ names share long prefixes, so this halves the table.
+mod front_coded {
+    use serde::de::Error as _;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(
+        strings: &[String],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut previous = "";
+        let mut pairs: Vec<(usize, &str)> = Vec::with_capacity(strings.len());
+        for string in strings {
+            let mut shared = previous
+                .bytes()
+                .zip(string.bytes())
+                .take_while(|(a, b)| a == b)
+                .count();
+            while !string.is_char_boundary(shared) {
+                shared -= 1;
+            }
+            pairs.push((shared, &string[shared..]));
+            previous = string;
+        }
+        return pairs.serialize(serializer);
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<String>, D::Error> {
+        let pairs: Vec<(usize, String)> = Vec::deserialize(deserializer)?;
+        let mut strings: Vec<String> = Vec::with_capacity(pairs.len());
+        for (shared, suffix) in pairs {
+            let previous = strings.last().map_or("", String::as_str);
+            if shared > previous.len() || !previous.is_char_boundary(shared) {
+                return Err(D::Error::custom("bad shared prefix length"));
+            }
+            strings.push(format!("{}{suffix}", &previous[..shared]));
+        }
+        return Ok(strings);
+    }
+}
+
+/// The indices a gap list stands for. Stops early if one would overflow.
+fn undo_gaps(gaps: &[u32]) -> impl Iterator<Item = u32> + '_ {
+    return gaps.iter().scan(0u32, |index, gap| {
+        *index = index.checked_add(*gap)?;
+        return Some(*index);
+    });
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path, what: &str) -> Result<T, Error> {
+    return File::open(path).and_then(|file| {
+        return serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| Error::new(ErrorKind::InvalidData, format!("Bad {what}: {e:?}")));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::PackBuilder;
+    use crate::Firmware;
+
+    /// Pack two committed firmwares and read them back: every library comes
+    /// out as the directory layout has it, and the ones the two share are
+    /// stored once.
+    #[test]
+    fn pack_round_trips_real_data() {
+        let data = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../data");
+        let dirs = [
+            data.join("05.40.20.01-HE_DTV_W19P_AFADATAA"),
+            data.join("05.50.15.01-HE_DTV_W18R_AFAAATAA"),
+        ];
+        if !dirs.iter().all(|dir| dir.exists()) {
+            return; // data may be absent in some checkouts
+        }
+        let mut builder = PackBuilder::new();
+        for dir in &dirs {
+            builder.add_dir(dir).expect("pack firmware");
+        }
+        let temp = tempfile::TempDir::new().unwrap();
+        let pack = temp.path().join("firmwares.fwdb");
+        builder.write(&pack).expect("write pack");
+
+        let packed = Firmware::load_pack(&pack).expect("load pack");
+        assert_eq!(packed.len(), 2);
+        // A data directory with only the pack lists both firmwares.
+        assert_eq!(Firmware::list(temp.path()).expect("list").len(), 2);
+        let mut distinct = 0;
+        let mut total = 0;
+        for (dir, firmware) in dirs.iter().zip(&packed) {
+            let original = Firmware::load(dir).expect("load directory");
+            assert_eq!(firmware.info.version, original.info.version);
+            assert_eq!(firmware.node_version(), original.node_version());
+            for name in ["libc.so.6", "libEGL.so.1", "libstdc++.so.6"] {
+                let (a, b) = (firmware.find_library(name), original.find_library(name));
+                assert_eq!(
+                    a.as_ref().map(|lib| (&lib.name, &lib.needed, &lib.symbols)),
+                    b.as_ref().map(|lib| (&lib.name, &lib.needed, &lib.symbols)),
+                    "{name} differs"
+                );
+            }
+            if let crate::Libraries::Packed { db, index } = &firmware.libraries {
+                distinct = db.len();
+                let mut ids: Vec<&u32> = index.values().collect();
+                ids.sort_unstable();
+                ids.dedup();
+                total += ids.len();
+            }
+        }
+        assert!(distinct < total, "{distinct} of {total} libraries stored");
+    }
+}
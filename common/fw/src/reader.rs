@@ -0,0 +1,157 @@
+//! How the files of a rootfs are read.
+//!
+//! A rootfs is usually a directory: mounted, or unpacked with `unsquashfs`.
+//! `webosbrew-fw-symbols-extract` also reads the images themselves, in place,
+//! by giving [`crate::rootfs::Rootfs`] a reader of its own. Paths are still
+//! paths on the host, as if the images had been unpacked where the reader
+//! says.
+
+use std::fmt::Debug;
+use std::fs;
+use std::fs::File;
+use std::io::{Error, Read, Seek};
+use std::path::{Path, PathBuf};
+
+/// A file to read from, and to move around in.
+pub trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    File,
+    Dir,
+    Symlink,
+    /// A device, a pipe or a socket.
+    Other,
+}
+
+/// What is at a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    pub kind: EntryKind,
+    /// The permission bits, as in `0o755`.
+    pub mode: u32,
+}
+
+impl Entry {
+    pub fn is_file(&self) -> bool {
+        return self.kind == EntryKind::File;
+    }
+
+    pub fn is_dir(&self) -> bool {
+        return self.kind == EntryKind::Dir;
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        return self.kind == EntryKind::Symlink;
+    }
+
+    /// Whether anyone may run it.
+    pub fn is_executable(&self) -> bool {
+        return self.mode & 0o111 != 0;
+    }
+}
+
+/// The file operations a rootfs is read with. They follow their namesakes in
+/// [`std::fs`].
+pub trait RootfsReader: Debug + Send + Sync {
+    /// What is at `path`, following symlinks.
+    fn metadata(&self, path: &Path) -> Result<Entry, Error>;
+
+    /// What is at `path`, without following a symlink there.
+    fn symlink_metadata(&self, path: &Path) -> Result<Entry, Error>;
+
+    /// The entries of the directory `path`, as paths under it, in no
+    /// particular order.
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, Error>;
+
+    fn read_link(&self, path: &Path) -> Result<PathBuf, Error>;
+
+    /// Open the file at `path`, following symlinks.
+    fn open(&self, path: &Path) -> Result<Box<dyn ReadSeek + '_>, Error>;
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>, Error> {
+        let mut data = Vec::new();
+        self.open(path)?.read_to_end(&mut data)?;
+        return Ok(data);
+    }
+
+    fn read_to_string(&self, path: &Path) -> Result<String, Error> {
+        let mut text = String::new();
+        self.open(path)?.read_to_string(&mut text)?;
+        return Ok(text);
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        return self.metadata(path).is_ok();
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        return self.metadata(path).is_ok_and(|entry| entry.is_file());
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        return self.metadata(path).is_ok_and(|entry| entry.is_dir());
+    }
+}
+
+/// Read the host's own filesystem.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HostReader;
+
+impl HostReader {
+    fn entry(metadata: &fs::Metadata) -> Entry {
+        let file_type = metadata.file_type();
+        let kind = if file_type.is_symlink() {
+            EntryKind::Symlink
+        } else if file_type.is_dir() {
+            EntryKind::Dir
+        } else if file_type.is_file() {
+            EntryKind::File
+        } else {
+            EntryKind::Other
+        };
+        return Entry {
+            kind,
+            mode: mode(metadata),
+        };
+    }
+}
+
+impl RootfsReader for HostReader {
+    fn metadata(&self, path: &Path) -> Result<Entry, Error> {
+        return Ok(HostReader::entry(&fs::metadata(path)?));
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> Result<Entry, Error> {
+        return Ok(HostReader::entry(&fs::symlink_metadata(path)?));
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, Error> {
+        return path
+            .read_dir()?
+            .map(|ent| ent.map(|ent| ent.path()))
+            .collect();
+    }
+
+    fn read_link(&self, path: &Path) -> Result<PathBuf, Error> {
+        return fs::read_link(path);
+    }
+
+    fn open(&self, path: &Path) -> Result<Box<dyn ReadSeek + '_>, Error> {
+        return Ok(Box::new(File::open(path)?));
+    }
+}
+
+#[cfg(unix)]
+fn mode(metadata: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    return metadata.permissions().mode() & 0o7777;
+}
+
+/// There are no permission bits to go by. Anyone may run anything.
+#[cfg(not(unix))]
+fn mode(_metadata: &fs::Metadata) -> u32 {
+    return 0o755;
+}
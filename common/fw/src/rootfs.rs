@@ -0,0 +1,1066 @@
+//! A firmware read straight from its rootfs.
+//!
+//! `webosbrew-fw-symbols-extract` scans a rootfs this way and saves what it
+//! finds in a data directory. [`Firmware::from_rootfs`] keeps it in memory
+//! instead, so a firmware dumped from a device can be checked right away. Its
+//! libraries are only parsed when they are looked up.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ffi::OsStr;
+use std::io::{BufRead, BufReader, Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use bin_lib::{ArmAttributes, ElfArch, LibraryInfo};
+use debian_control::{Binary, Control};
+use debversion::{AsVersion, Version as DebVersion};
+use path_slash::{PathBufExt, PathExt};
+use regex::Regex;
+
+use crate::jail::Jail;
+use crate::layout::detect_layout;
+use crate::ldconfig::{CachedLibrary, in_root};
+use crate::reader::{HostReader, RootfsReader};
+use crate::runtime::{NODE_BINARIES, read_node_version, read_web_engine};
+use crate::{
+    Firmware, FirmwareInfo, Libraries, LunaRegistry, PackageEntry, PackageVersion, library_key,
+};
+
+/// The libraries whose ARM attributes make up the firmware's CPU baseline.
+/// Everything on the firmware links to them, so the CPU runs what they need.
+pub const BASELINE_LIBRARIES: [&str; 2] = ["libc.so.6", "libstdc++.so.6"];
+
+/// The file in an opkg database directory listing every package, with its
+/// state.
+const OPKG_STATUS: &str = "status";
+
+/// The directories on the default `PATH`, from the root.
+pub const PATH_DIRS: [&str; 5] = ["bin", "sbin", "usr/bin", "usr/sbin", "usr/local/bin"];
+
+/// The lists of applet links busybox installs, in `/etc`. Yocto splits them by
+/// whether the applet needs the setuid binary.
+const BUSYBOX_LINKS: [&str; 3] = [
+    "busybox.links",
+    "busybox.links.suid",
+    "busybox.links.nosuid",
+];
+
+/// Where the parts of a firmware are mounted on it, the most specific first.
+const MOUNT_POINTS: [&str; 2] = ["/mnt/bsppart", "/"];
+
+/// A rootfs, mounted or unpacked. For a webOS TV image, the directory holding
+/// the unpacked `.pak` images. Its files are read through a [`RootfsReader`],
+/// the host's own unless [`Rootfs::open_with`] is given another.
+#[derive(Debug)]
+pub struct Rootfs {
+    pub info: FirmwareInfo,
+    /// The root of the filesystem.
+    pub path: PathBuf,
+    /// The directories the dynamic loader searches, in order.
+    pub lib_paths: Vec<PathBuf>,
+    /// The opkg database directories, each three levels below its root, as in
+    /// `<root>/usr/lib/opkg`. An earlier one takes precedence.
+    pub opkg_dirs: Vec<PathBuf>,
+    /// The libraries in `ld.so.cache`, in the loader's order. Empty without a
+    /// cache.
+    pub ld_cache: Result<Vec<CacheEntry>, String>,
+    /// Each directory in the input holding a part of the firmware, with where
+    /// it is mounted on it.
+    mounts: Vec<(PathBuf, String)>,
+    reader: Arc<dyn RootfsReader>,
+}
+
+/// What names a firmware, for a rootfs that does not say: a webOS TV rootfs
+/// has no OTA ID in it. Either one, when given, wins over the rootfs.
+#[derive(Debug, Clone, Default)]
+pub struct FirmwareId {
+    pub version: Option<String>,
+    pub ota_id: Option<String>,
+}
+
+/// A library in `ld.so.cache`.
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    pub library: CachedLibrary,
+    /// Where the file is in the input.
+    pub file: Option<PathBuf>,
+}
+
+/// A library in one of the loader's directories.
+#[derive(Debug, Clone)]
+pub struct LibraryFile {
+    /// The name the loader finds it by.
+    pub name: String,
+    /// The entry in the directory.
+    pub path: PathBuf,
+    /// The file the entry resolves to: `path` itself, or the final target of
+    /// a symlink.
+    pub target: PathBuf,
+    /// The class and machine of `target`. `None` when it is not an ELF file.
+    pub arch: Option<ElfArch>,
+}
+
+impl LibraryFile {
+    pub fn is_link(&self) -> bool {
+        return self.path != self.target;
+    }
+
+    /// The key the firmware index has the library under. See
+    /// [`library_key`].
+    pub fn key(&self) -> String {
+        return library_key(&self.name, self.arch);
+    }
+}
+
+/// What a rootfs holds, before any library is parsed.
+#[derive(Debug, Default)]
+pub struct RootfsScan {
+    /// The installed OS packages, from the opkg status files and control
+    /// files.
+    pub packages: BTreeMap<String, PackageEntry>,
+    /// The package that installed each file, from the opkg lists and the
+    /// conffiles in the status files. Without any list, each library is given
+    /// the package named after its soname, if there is one.
+    pub package_files: BTreeMap<PathBuf, String>,
+    /// Every library in the loader's directories, in search order, then those
+    /// `ld.so.cache` resolves elsewhere or alone. A name found again later for
+    /// the same class and machine replaces the first.
+    pub libraries: Vec<LibraryFile>,
+    /// Where `ld.so.cache` and the directories disagree. The cache wins, as it
+    /// does for the loader.
+    pub cache_notes: Vec<String>,
+    /// The commands on the default `PATH`: executable files, links to them,
+    /// and busybox applets.
+    pub executables: BTreeSet<String>,
+    /// The Luna bus services and their methods, from the luna-service2
+    /// configuration.
+    pub luna: LunaRegistry,
+    /// What a developer-mode native app sees. `None` without a jail
+    /// configuration.
+    pub jail: Option<Jail>,
+    /// What could not be read, and why. The scan goes on without it.
+    pub skipped: Vec<String>,
+}
+
+impl Rootfs {
+    pub fn open<P: AsRef<Path>>(input: P, id: &FirmwareId) -> Result<Rootfs, Error> {
+        return Rootfs::open_with(Arc::new(HostReader), input, id);
+    }
+
+    /// Open a rootfs whose files `reader` reads, as if `input` were on the
+    /// host.
+    pub fn open_with<P: AsRef<Path>>(
+        reader: Arc<dyn RootfsReader>,
+        input: P,
+        id: &FirmwareId,
+    ) -> Result<Rootfs, Error> {
+        let input = input.as_ref();
+        let host = reader.as_ref();
+        let layout = detect_layout(host, input)?;
+        let ld_cache = match layout.ld_cache(host, input) {
+            None => Ok(Vec::new()),
+            Some(Ok(libs)) => Ok(libs
+                .into_iter()
+                .map(|library| CacheEntry {
+                    file: layout.host_path(host, input, &library.path),
+                    library,
+                })
+                .collect()),
+            Some(Err(e)) => Err(e.to_string()),
+        };
+        let mut rootfs = Rootfs {
+            info: layout.fw_info(host, input, id)?,
+            path: layout.rootfs(input),
+            lib_paths: layout.lib_paths(host, input),
+            opkg_dirs: layout.opkg_dirs(host, input)?,
+            ld_cache,
+            mounts: MOUNT_POINTS
+                .iter()
+                .filter_map(|point| {
+                    let path = layout.host_path(host, input, point)?;
+                    return Some((path, String::from(*point)));
+                })
+                .collect(),
+            reader,
+        };
+        rootfs.read_runtimes();
+        rootfs.read_cpu_baseline();
+        rootfs.read_arch();
+        return Ok(rootfs);
+    }
+
+    /// What the files of the rootfs are read with.
+    pub fn reader(&self) -> &dyn RootfsReader {
+        return self.reader.as_ref();
+    }
+
+    /// Read the Node.js and web engine versions out of their binaries into
+    /// `info`. Either is left out when it cannot be found.
+    fn read_runtimes(&mut self) {
+        self.info.node = NODE_BINARIES.iter().find_map(|path| {
+            let file = self.final_link_target(&in_root(&self.path, path)).ok()?;
+            return read_node_version(self.reader(), &file).ok().flatten();
+        });
+        self.info.web_engine = self.lib_paths.iter().find_map(|dir| {
+            let mut entries = self.reader.read_dir(dir).ok()?;
+            entries.sort();
+            // The real file is in a loader directory too. Links to it are
+            // skipped.
+            return entries
+                .iter()
+                .filter(|path| {
+                    return self
+                        .reader
+                        .symlink_metadata(path)
+                        .is_ok_and(|entry| entry.is_file());
+                })
+                .find_map(|path| {
+                    let name = path.file_name()?.to_string_lossy();
+                    return read_web_engine(self.reader(), &name, path).ok().flatten();
+                });
+        });
+    }
+
+    /// Read the ARM attributes of the baseline libraries into `info`, merged
+    /// into what a CPU running all of them has. Left out when none has any.
+    fn read_cpu_baseline(&mut self) {
+        self.info.cpu = BASELINE_LIBRARIES
+            .iter()
+            .filter_map(|name| {
+                let file = self.lib_paths.iter().find_map(|dir| {
+                    return self.final_link_target(&dir.join(name)).ok();
+                })?;
+                return ArmAttributes::read(self.reader.open(&file).ok()?)
+                    .ok()
+                    .flatten();
+            })
+            .reduce(ArmAttributes::max);
+    }
+
+    /// Read the class and machine of the first `libc.so.6` the loader finds
+    /// into `info`.
+    fn read_arch(&mut self) {
+        self.info.arch = self.lib_paths.iter().find_map(|dir| {
+            let file = self.final_link_target(&dir.join("libc.so.6")).ok()?;
+            return read_arch(self.reader(), &file);
+        });
+    }
+
+    pub fn scan(&self) -> RootfsScan {
+        let mut scan = RootfsScan::default();
+        let listed = self.scan_packages(&mut scan);
+        self.scan_libraries(&mut scan);
+        self.scan_ld_cache(&mut scan);
+        if !listed {
+            guess_library_packages(&mut scan);
+        }
+        self.scan_executables(&mut scan);
+        scan.luna = LunaRegistry::read(self.reader(), &self.path, &mut scan.skipped);
+        self.scan_jail(&mut scan);
+        return scan;
+    }
+
+    /// Read the jail configuration, and note each library the jail does not
+    /// mount: its entry in the loader's directory, or the file it links to.
+    fn scan_jail(&self, scan: &mut RootfsScan) {
+        let Some(mut jail) = Jail::read(self.reader(), &self.path, &mut scan.skipped) else {
+            return;
+        };
+        // A name found again later replaces the first, as in the index.
+        let mut files: HashMap<String, &LibraryFile> = HashMap::new();
+        for lib in &scan.libraries {
+            files.insert(lib.key(), lib);
+        }
+        for (key, lib) in files {
+            let visible = [&lib.path, &lib.target].iter().all(|file| {
+                return self
+                    .device_path(file)
+                    .is_some_and(|path| jail.is_visible(&path));
+            });
+            if !visible {
+                jail.hidden.insert(key);
+            }
+        }
+        scan.jail = Some(jail);
+    }
+
+    /// Where a file in the input is on the firmware.
+    fn device_path(&self, file: &Path) -> Option<String> {
+        return self.mounts.iter().find_map(|(dir, point)| {
+            let rest = file.strip_prefix(dir).ok()?;
+            return Some(format!(
+                "{}/{}",
+                point.trim_end_matches('/'),
+                rest.to_slash_lossy()
+            ));
+        });
+    }
+
+    /// Read each opkg database. Returns whether any had file lists.
+    ///
+    /// Within a database, the `status` file, which opkg itself goes by, wins
+    /// over the control files in `info`. A package it lists as not installed
+    /// is left out, even with a control file left behind. Across databases,
+    /// the first to have a package or file wins.
+    fn scan_packages(&self, scan: &mut RootfsScan) -> bool {
+        let mut listed = false;
+        for dir in &self.opkg_dirs {
+            // The database sits three levels below the rootfs root, as in
+            // <root>/usr/lib/opkg.
+            let Some(root) = dir.ancestors().nth(3) else {
+                scan.skipped
+                    .push(format!("{}: path is too short", dir.to_string_lossy()));
+                continue;
+            };
+            let mut packages: BTreeMap<String, PackageEntry> = BTreeMap::new();
+            let mut not_installed: HashSet<String> = HashSet::new();
+            let status = dir.join(OPKG_STATUS);
+            if self.reader.is_file(&status) {
+                for stanza in read_status(self.reader(), &status, &mut scan.skipped) {
+                    if !stanza.installed {
+                        not_installed.insert(stanza.name);
+                        continue;
+                    }
+                    for conffile in &stanza.conffiles {
+                        scan.package_files
+                            .entry(root.join(PathBuf::from_slash(conffile.trim_start_matches('/'))))
+                            .or_insert_with(|| stanza.name.clone());
+                    }
+                    packages.insert(stanza.name, stanza.entry);
+                }
+            }
+            let info = dir.join("info");
+            let entries = match self.reader.read_dir(&info) {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == ErrorKind::NotFound => Vec::new(),
+                Err(e) => {
+                    scan.skipped
+                        .push(format!("{}: {e}", info.to_string_lossy()));
+                    Vec::new()
+                }
+            };
+            for path in entries {
+                let (Some(stem), Some(ext)) = (path.file_stem(), path.extension()) else {
+                    continue;
+                };
+                let stem = stem.to_string_lossy();
+                if not_installed.contains(stem.as_ref()) {
+                    continue;
+                }
+                if ext == "list" {
+                    let file = match self.reader.open(&path) {
+                        Ok(file) => file,
+                        Err(e) => {
+                            scan.skipped
+                                .push(format!("{}: {e}", path.to_string_lossy()));
+                            continue;
+                        }
+                    };
+                    listed = true;
+                    for line in BufReader::new(file).lines() {
+                        let Ok(line) = line else {
+                            continue;
+                        };
+                        let Some(line) = line.split('\t').next() else {
+                            continue;
+                        };
+                        scan.package_files
+                            .entry(root.join(PathBuf::from_slash(line.trim_start_matches('/'))))
+                            .or_insert_with(|| String::from(stem.as_ref()));
+                    }
+                } else if ext == "control" && !packages.contains_key(stem.as_ref()) {
+                    match read_control(self.reader(), &path) {
+                        Ok((name, entry)) => {
+                            packages.entry(name).or_insert(entry);
+                        }
+                        Err(e) => {
+                            scan.skipped
+                                .push(format!("{}: {e}", path.to_string_lossy()));
+                        }
+                    }
+                }
+            }
+            for (name, entry) in packages {
+                scan.packages.entry(name).or_insert(entry);
+            }
+        }
+        return listed;
+    }
+
+    fn scan_libraries(&self, scan: &mut RootfsScan) {
+        let so_regex = Regex::new("^.+.so(\\.\\w+)*$").unwrap();
+        for lib_path in &self.lib_paths {
+            let Ok(entries) = self.reader.read_dir(lib_path) else {
+                continue;
+            };
+            for path in entries {
+                let (Some(name), Ok(metadata)) = (
+                    path.file_name().and_then(OsStr::to_str),
+                    self.reader.symlink_metadata(&path),
+                ) else {
+                    continue;
+                };
+                if !so_regex.is_match(name) {
+                    continue;
+                }
+                let target = if metadata.is_file() {
+                    path.clone()
+                } else if metadata.is_symlink() {
+                    match self.final_link_target(&path) {
+                        Ok(target) => target,
+                        Err(e) => {
+                            scan.skipped
+                                .push(format!("link target for {}: {e}", path.to_string_lossy()));
+                            continue;
+                        }
+                    }
+                } else {
+                    continue;
+                };
+                scan.libraries.push(LibraryFile {
+                    name: String::from(name),
+                    path,
+                    arch: read_arch(self.reader(), &target),
+                    target,
+                });
+            }
+        }
+    }
+
+    /// Check the libraries found in the directories against `ld.so.cache`,
+    /// which the loader looks in first, and add those only the cache finds.
+    fn scan_ld_cache(&self, scan: &mut RootfsScan) {
+        let entries = match &self.ld_cache {
+            Ok(entries) => entries,
+            Err(e) => {
+                scan.skipped.push(format!("ld.so.cache: {e}"));
+                return;
+            }
+        };
+        let found: HashMap<String, &Path> = scan
+            .libraries
+            .iter()
+            .map(|lib| (lib.key(), lib.target.as_path()))
+            .collect();
+        let mut keys = HashSet::new();
+        let mut cached = Vec::new();
+        for entry in entries {
+            let CachedLibrary { name, path } = &entry.library;
+            let Some(file) = entry
+                .file
+                .as_ref()
+                .filter(|file| self.reader.symlink_metadata(file).is_ok())
+            else {
+                scan.cache_notes
+                    .push(format!("{name} is cached as {path}, which is missing"));
+                continue;
+            };
+            let target = match self.final_link_target(file) {
+                Ok(target) => target,
+                Err(e) => {
+                    scan.skipped
+                        .push(format!("link target for {}: {e}", file.to_string_lossy()));
+                    continue;
+                }
+            };
+            let arch = read_arch(self.reader(), &target);
+            // A name is listed once for each architecture it was found for.
+            // The loader takes the first that fits.
+            let key = library_key(name, arch);
+            if !keys.insert(key.clone()) {
+                continue;
+            }
+            match found.get(&key) {
+                Some(found) if *found == target => continue,
+                Some(found) => scan.cache_notes.push(format!(
+                    "{name} is cached as {path}, but the directories have {}",
+                    found.to_string_lossy()
+                )),
+                None => {}
+            }
+            cached.push(LibraryFile {
+                name: name.clone(),
+                path: file.clone(),
+                target,
+                arch,
+            });
+        }
+        scan.libraries.extend(cached);
+    }
+
+    fn scan_executables(&self, scan: &mut RootfsScan) {
+        for dir in PATH_DIRS {
+            let Ok(entries) = self
+                .reader
+                .read_dir(&self.path.join(PathBuf::from_slash(dir)))
+            else {
+                continue;
+            };
+            for path in entries {
+                let (Some(name), Ok(entry)) =
+                    (path.file_name(), self.reader.symlink_metadata(&path))
+                else {
+                    continue;
+                };
+                // A link may point at an applet of a binary that is not there
+                // in a partial copy. Keep it all the same.
+                if entry.is_symlink() || (entry.is_file() && entry.is_executable()) {
+                    scan.executables
+                        .insert(String::from(name.to_string_lossy()));
+                }
+            }
+        }
+        for links in BUSYBOX_LINKS {
+            let Ok(text) = self
+                .reader
+                .read_to_string(&self.path.join("etc").join(links))
+            else {
+                continue;
+            };
+            scan.executables.extend(
+                text.lines()
+                    .filter_map(|line| line.trim().rsplit('/').next())
+                    .filter(|name| !name.is_empty())
+                    .map(String::from),
+            );
+        }
+    }
+
+    fn final_link_target(&self, link: &Path) -> Result<PathBuf, Error> {
+        let mut target = link.to_path_buf();
+        loop {
+            let metadata = self.reader.symlink_metadata(&target).map_err(|e| {
+                return Error::new(
+                    e.kind(),
+                    format!("Can't find symlink info for {}", target.to_string_lossy()),
+                );
+            })?;
+            if !metadata.is_symlink() {
+                return Ok(target);
+            }
+            target = self.join_link_target(&target, &self.reader.read_link(&target)?)?;
+        }
+    }
+
+    fn join_link_target(&self, link: &Path, target: &Path) -> Result<PathBuf, Error> {
+        if target.is_absolute() {
+            let joined = self.path.join(target.strip_prefix("/").unwrap_or(target));
+            if self.reader.exists(&joined) {
+                return Ok(joined);
+            }
+        } else {
+            for lib_path in link
+                .parent()
+                .into_iter()
+                .chain(self.lib_paths.iter().map(PathBuf::as_path))
+            {
+                let joined = lib_path.join(target);
+                if self.reader.exists(&joined) {
+                    return Ok(joined);
+                }
+            }
+        }
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            format!("Can't find link target {}", target.to_string_lossy()),
+        ));
+    }
+}
+
+/// One package in an opkg status file.
+struct StatusStanza {
+    name: String,
+    entry: PackageEntry,
+    /// Whether its `Status` says it is installed. One without a `Status` is.
+    installed: bool,
+    /// The paths in its `Conffiles`.
+    conffiles: Vec<String>,
+}
+
+/// The packages in an opkg status file: control stanzas one after another. A
+/// stanza that cannot be read is added to `skipped` and left out.
+fn read_status(
+    reader: &dyn RootfsReader,
+    path: &Path,
+    skipped: &mut Vec<String>,
+) -> Vec<StatusStanza> {
+    let read = reader.open(path).and_then(|file| {
+        return Control::read_relaxed(file)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, format!("{e:?}")));
+    });
+    let ctrl = match read {
+        Ok((ctrl, _)) => ctrl,
+        Err(e) => {
+            skipped.push(format!("{}: {e:?}", path.to_string_lossy()));
+            return Vec::new();
+        }
+    };
+    let mut stanzas = Vec::new();
+    for bin in ctrl.binaries() {
+        let (name, entry) = match package_entry(&bin) {
+            Ok(package) => package,
+            Err(e) => {
+                skipped.push(format!("{}: {e}", path.to_string_lossy()));
+                continue;
+            }
+        };
+        let paragraph = bin.as_deb822();
+        // As in `install ok installed`: the last word is the state.
+        let installed = paragraph
+            .get("Status")
+            .is_none_or(|status| status.split_whitespace().nth(2) == Some("installed"));
+        let conffiles = paragraph
+            .get("Conffiles")
+            .map(|conffiles| {
+                return conffiles
+                    .lines()
+                    .filter_map(|line| line.split_whitespace().next())
+                    .filter(|file| file.starts_with('/'))
+                    .map(String::from)
+                    .collect();
+            })
+            .unwrap_or_default();
+        stanzas.push(StatusStanza {
+            name,
+            entry,
+            installed,
+            conffiles,
+        });
+    }
+    return stanzas;
+}
+
+/// The package name and version in an opkg control file.
+fn read_control(reader: &dyn RootfsReader, path: &Path) -> Result<(String, PackageEntry), Error> {
+    let ctrl = Control::read(reader.open(path)?)
+        .map_err(|e| Error::new(ErrorKind::InvalidData, format!("{e:?}")))?;
+    let bin = ctrl
+        .binaries()
+        .next()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "no package"))?;
+    return package_entry(&bin);
+}
+
+/// The package name and version in one control stanza.
+fn package_entry(bin: &Binary) -> Result<(String, PackageEntry), Error> {
+    let version_str = bin
+        .as_deb822()
+        .get("Version")
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "no Version field"))?;
+    let version = version_str
+        .as_str()
+        .into_version()
+        .unwrap_or_else(|_| DebVersion {
+            epoch: None,
+            upstream_version: version_str.clone(),
+            debian_revision: None,
+        });
+    let name = bin
+        .name()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "no Package field"))?;
+    let entry = PackageEntry {
+        version: PackageVersion {
+            epoch: version.epoch,
+            upstream: version.upstream_version,
+            debian_revision: version.debian_revision,
+        },
+    };
+    return Ok((name, entry));
+}
+
+/// Give each library without a package the one named after its soname, as
+/// Debian and Yocto name library packages: `libfoo.so.1` is in `libfoo1`, and
+/// `libfoo2.so.3` in `libfoo2-3`. A multilib copy has the `lib32-` prefix.
+fn guess_library_packages(scan: &mut RootfsScan) {
+    for lib in &scan.libraries {
+        if scan.package_files.contains_key(&lib.target) {
+            continue;
+        }
+        let guess = soname_packages(&lib.name)
+            .into_iter()
+            .flat_map(|name| [format!("lib32-{name}"), name])
+            .find(|name| scan.packages.contains_key(name));
+        if let Some(package) = guess {
+            scan.package_files.insert(lib.target.clone(), package);
+        }
+    }
+}
+
+/// The package names a library called `name` is usually in, the likeliest
+/// first.
+fn soname_packages(name: &str) -> Vec<String> {
+    let Some((base, version)) = name.split_once(".so") else {
+        return Vec::new();
+    };
+    let base = base.to_lowercase().replace('_', "-");
+    let Some(major) = version
+        .strip_prefix('.')
+        .and_then(|version| version.split('.').next())
+    else {
+        return vec![base];
+    };
+    let sep = if base.ends_with(|c: char| c.is_ascii_digit()) {
+        "-"
+    } else {
+        ""
+    };
+    return vec![format!("{base}{sep}{major}"), base];
+}
+
+/// The class and machine of an ELF file. `None` for anything else.
+fn read_arch(reader: &dyn RootfsReader, file: &Path) -> Option<ElfArch> {
+    return ElfArch::read(reader.open(file).ok()?).ok();
+}
+
+/// Parse a library found by [`Rootfs::scan`], naming it after the file, and
+/// note the package that installed it.
+pub fn read_library(
+    reader: &dyn RootfsReader,
+    file: &Path,
+    package_files: &BTreeMap<PathBuf, String>,
+) -> Result<LibraryInfo, Error> {
+    let name = file
+        .file_name()
+        .map(OsStr::to_string_lossy)
+        .unwrap_or_default();
+    let mut info = LibraryInfo::parse(reader.open(file)?, false, &name).map_err(|e| {
+        return Error::new(
+            ErrorKind::InvalidData,
+            format!("Failed to parse library {name}: {e:?}"),
+        );
+    })?;
+    info.package = package_files.get(file).cloned();
+    return Ok(info);
+}
+
+/// Libraries parsed so far, by file. A library that fails to parse is kept as
+/// `None`, so it is not read again.
+pub(crate) type ParsedLibraries = Mutex<HashMap<PathBuf, Option<LibraryInfo>>>;
+
+impl Firmware {
+    /// A firmware read from a rootfs, as `webosbrew-fw-symbols-extract` would
+    /// have saved it.
+    pub fn from_rootfs<P: AsRef<Path>>(input: P, id: &FirmwareId) -> Result<Firmware, Error> {
+        let rootfs = Rootfs::open(&input, id)?;
+        let scan = rootfs.scan();
+        // A name found again further down the search path replaces the first,
+        // as it does in extracted data.
+        let index = scan
+            .libraries
+            .into_iter()
+            .map(|lib| (lib.key(), lib.target))
+            .collect();
+        return Ok(Firmware {
+            info: rootfs.info,
+            data_dir: input.as_ref().to_path_buf(),
+            libraries: Libraries::Rootfs {
+                reader: rootfs.reader,
+                index,
+                package_files: scan.package_files,
+                parsed: Mutex::default(),
+            },
+            packages: scan.packages.into_iter().collect(),
+            executables: Some(scan.executables),
+            luna: Some(scan.luna),
+            jail: scan.jail,
+        });
+    }
+
+    pub(crate) fn find_rootfs_library(
+        reader: &dyn RootfsReader,
+        index: &HashMap<String, PathBuf>,
+        package_files: &BTreeMap<PathBuf, String>,
+        parsed: &ParsedLibraries,
+        name: &str,
+    ) -> Option<LibraryInfo> {
+        let file = index.get(name)?;
+        let mut parsed = parsed.lock().unwrap_or_else(PoisonError::into_inner);
+        return parsed
+            .entry(file.clone())
+            .or_insert_with(|| read_library(reader, file, package_files).ok())
+            .clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use crate::Firmware;
+    use crate::rootfs::{FirmwareId, Rootfs, soname_packages};
+
+    #[test]
+    fn reads_status_files_without_lists() {
+        let input = tempfile::TempDir::new().unwrap();
+        let root = input.path();
+        fs::create_dir_all(root.join("var/lib/opkg/info")).unwrap();
+        fs::create_dir_all(root.join("usr/lib/opkg/info")).unwrap();
+        fs::create_dir_all(root.join("etc")).unwrap();
+        fs::write(
+            root.join("etc/os-release"),
+            "ID=webos\nNAME=\"webOS OSE\"\nVERSION_ID=\"2.24.0\"\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("usr/lib/opkg/status"),
+            "Package: libfoo1\nVersion: 1.2.3-r0\nStatus: install ok installed\n\n\
+             Package: lib32-libbar2-3\nVersion: 3.0-r1\nStatus: install ok installed\n\
+             Conffiles:\n /etc/bar.conf 0123456789abcdef\n\n\
+             Package: old\nVersion: 1.0\nStatus: deinstall ok not-installed\n",
+        )
+        .unwrap();
+        // Left behind by the removed package.
+        fs::write(
+            root.join("usr/lib/opkg/info/old.control"),
+            "Package: old\nVersion: 1.0\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("usr/lib/opkg/info/libfoo1.control"),
+            "Package: libfoo1\nVersion: 0.9-r0\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("var/lib/opkg/info/libfoo1.control"),
+            "Package: libfoo1\nVersion: 0.8-r0\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("var/lib/opkg/info/extra.control"),
+            "Package: extra\nVersion: 2.0\n",
+        )
+        .unwrap();
+        fs::write(root.join("usr/lib/libfoo.so.1"), b"not an ELF").unwrap();
+        fs::write(root.join("usr/lib/libbar2.so.3"), b"not an ELF").unwrap();
+        fs::write(root.join("usr/lib/libbaz.so.1"), b"not an ELF").unwrap();
+
+        let scan = Rootfs::open(root, &FirmwareId::default()).unwrap().scan();
+
+        assert!(scan.skipped.is_empty(), "{:?}", scan.skipped);
+        let versions: Vec<(&str, String)> = scan
+            .packages
+            .iter()
+            .map(|(name, entry)| (name.as_str(), entry.version.to_string()))
+            .collect();
+        assert_eq!(
+            versions,
+            vec![
+                ("extra", String::from("2.0")),
+                ("lib32-libbar2-3", String::from("3.0-r1")),
+                ("libfoo1", String::from("1.2.3-r0")),
+            ]
+        );
+        let files: Vec<(PathBuf, &str)> = scan
+            .package_files
+            .iter()
+            .map(|(file, package)| {
+                (
+                    file.strip_prefix(root).unwrap().to_path_buf(),
+                    package.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                (PathBuf::from("etc/bar.conf"), "lib32-libbar2-3"),
+                (PathBuf::from("usr/lib/libbar2.so.3"), "lib32-libbar2-3"),
+                (PathBuf::from("usr/lib/libfoo.so.1"), "libfoo1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn names_packages_after_sonames() {
+        assert_eq!(soname_packages("libfoo.so.1.2"), vec!["libfoo1", "libfoo"]);
+        assert_eq!(
+            soname_packages("libGLESv2.so.2"),
+            vec!["libglesv2-2", "libglesv2"]
+        );
+        assert_eq!(
+            soname_packages("libstdc++.so.6"),
+            vec!["libstdc++6", "libstdc++"]
+        );
+        assert_eq!(soname_packages("libfoo_bar.so"), vec!["libfoo-bar"]);
+        assert!(soname_packages("ld-linux.conf").is_empty());
+    }
+
+    #[test]
+    fn reads_packages_and_library_names() {
+        let input = tempfile::TempDir::new().unwrap();
+        let root = input.path();
+        fs::create_dir_all(root.join("etc")).unwrap();
+        fs::create_dir_all(root.join("usr/lib")).unwrap();
+        fs::create_dir_all(root.join("var/lib/opkg/info")).unwrap();
+        fs::write(
+            root.join("etc/os-release"),
+            "ID=webos\nNAME=\"webOS OSE\"\nVERSION_ID=\"2.24.0\"\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("var/lib/opkg/info/libfoo1.control"),
+            "Package: libfoo1\nVersion: 1:1.2.3-r0\nArchitecture: armv7a\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("var/lib/opkg/info/libfoo1.list"),
+            "/usr/lib/libfoo.so.1\n",
+        )
+        .unwrap();
+        fs::write(root.join("usr/lib/libfoo.so.1"), b"not an ELF").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("libfoo.so.1", root.join("usr/lib/libfoo.so")).unwrap();
+        fs::create_dir_all(root.join("bin")).unwrap();
+        fs::write(root.join("bin/busybox"), b"").unwrap();
+        fs::write(
+            root.join("etc/busybox.links"),
+            "/bin/busybox\n/usr/bin/wget\n",
+        )
+        .unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(root.join("bin/busybox"), fs::Permissions::from_mode(0o755))
+                .unwrap();
+            std::os::unix::fs::symlink("busybox", root.join("bin/ls")).unwrap();
+            fs::write(root.join("bin/README"), b"").unwrap();
+        }
+
+        let firmware = Firmware::from_rootfs(root, &FirmwareId::default()).unwrap();
+
+        assert_eq!(firmware.info.dir_name(), "2.24.0-webos_OSE");
+        let version = &firmware.packages()["libfoo1"].version;
+        assert_eq!(version.epoch, Some(1));
+        assert_eq!(version.to_string(), "1.2.3-r0");
+        #[cfg(unix)]
+        assert_eq!(firmware.library_names(), vec!["libfoo.so", "libfoo.so.1"]);
+        // Not an ELF file, so there is nothing to parse, every time it is
+        // asked for.
+        assert!(firmware.find_library("libfoo.so").is_none());
+        assert!(firmware.find_library("libfoo.so.1").is_none());
+        let executables: Vec<&str> = firmware
+            .executables()
+            .unwrap()
+            .iter()
+            .map(String::as_str)
+            .collect();
+        #[cfg(unix)]
+        assert_eq!(executables, vec!["busybox", "ls", "wget"]);
+        #[cfg(not(unix))]
+        assert_eq!(executables, vec!["busybox", "wget"]);
+    }
+
+    #[test]
+    fn indexes_what_the_loader_cache_finds() {
+        let input = tempfile::TempDir::new().unwrap();
+        let root = input.path();
+        fs::create_dir_all(root.join("etc/ld.so.conf.d")).unwrap();
+        fs::create_dir_all(root.join("usr/lib/vendor")).unwrap();
+        fs::write(
+            root.join("etc/os-release"),
+            "ID=webos\nVERSION_ID=\"2.24.0\"\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("etc/ld.so.conf"),
+            "include /etc/ld.so.conf.d/*.conf\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("etc/ld.so.conf.d/vendor.conf"),
+            "/usr/lib/vendor\n",
+        )
+        .unwrap();
+        fs::write(root.join("usr/lib/vendor/libvendor.so.2.0"), b"").unwrap();
+        // An old-format cache: the vendor library by its soname, and one the
+        // rootfs no longer has.
+        let strings = b"libvendor.so.2\0/usr/lib/vendor/libvendor.so.2.0\0libgone.so.1\0/usr/lib/libgone.so.1\0";
+        let mut cache = b"ld.so-1.7.0\0".to_vec();
+        cache.extend_from_slice(&2_u32.to_le_bytes());
+        for (key, value) in [(0_u32, 15_u32), (48, 61)] {
+            cache.extend_from_slice(&1_i32.to_le_bytes());
+            cache.extend_from_slice(&key.to_le_bytes());
+            cache.extend_from_slice(&value.to_le_bytes());
+        }
+        cache.extend_from_slice(strings);
+        fs::write(root.join("etc/ld.so.cache"), cache).unwrap();
+
+        let rootfs = Rootfs::open(root, &FirmwareId::default()).unwrap();
+        let scan = rootfs.scan();
+
+        assert_eq!(
+            rootfs.lib_paths,
+            vec![root.join("usr/lib"), root.join("usr/lib/vendor")]
+        );
+        let names: Vec<&str> = scan.libraries.iter().map(|lib| lib.name.as_str()).collect();
+        assert_eq!(names, vec!["libvendor.so.2.0", "libvendor.so.2"]);
+        assert_eq!(
+            scan.cache_notes,
+            vec!["libgone.so.1 is cached as /usr/lib/libgone.so.1, which is missing"]
+        );
+    }
+
+    #[test]
+    fn hides_libraries_the_jail_does_not_mount() {
+        let input = tempfile::TempDir::new().unwrap();
+        let root = input.path();
+        fs::create_dir_all(root.join("etc")).unwrap();
+        fs::create_dir_all(root.join("lib")).unwrap();
+        fs::create_dir_all(root.join("usr/lib")).unwrap();
+        fs::write(
+            root.join("etc/os-release"),
+            "ID=webos\nVERSION_ID=\"2.24.0\"\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("etc/jail_native_devmode.conf"),
+            "mount_bind /usr/lib\n",
+        )
+        .unwrap();
+        fs::write(root.join("lib/libc.so.6"), b"").unwrap();
+        fs::write(root.join("usr/lib/libfoo.so.1"), b"").unwrap();
+
+        let firmware = Firmware::from_rootfs(root, &FirmwareId::default()).unwrap();
+        let jail = firmware.jail().unwrap();
+
+        assert!(jail.hidden.contains("libc.so.6"));
+        assert!(!jail.hidden.contains("libfoo.so.1"));
+    }
+
+    #[test]
+    fn reads_a_bare_tv_rootfs() {
+        let input = tempfile::TempDir::new().unwrap();
+        let root = input.path();
+        fs::create_dir_all(root.join("etc")).unwrap();
+        fs::create_dir_all(root.join("usr/lib")).unwrap();
+        fs::create_dir_all(root.join("usr/lib/opkg/info")).unwrap();
+        fs::write(
+            root.join("etc/starfish-release"),
+            "Rockhopper release 11.2.0-37 (queue-qilian)\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("usr/lib/opkg/info/libfoo1.control"),
+            "Package: libfoo1\nVersion: 1.0-r0\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("usr/lib/opkg/info/libfoo1.list"),
+            "/usr/lib/libfoo.so.1\n",
+        )
+        .unwrap();
+        fs::write(root.join("usr/lib/libfoo.so.1"), b"not an ELF").unwrap();
+        let id = FirmwareId {
+            version: Some(String::from("33.21.70")),
+            ota_id: Some(String::from("HE_DTV_W24O_AFABATAA")),
+        };
+
+        let firmware = Firmware::from_rootfs(root, &id).unwrap();
+
+        assert_eq!(firmware.info.dir_name(), "33.21.70-HE_DTV_W24O_AFABATAA");
+        assert_eq!(firmware.info.release.to_string(), "11.2.0");
+        assert_eq!(firmware.packages()["libfoo1"].version.to_string(), "1.0-r0");
+        assert_eq!(firmware.library_names(), vec!["libfoo.so.1"]);
+    }
+}
@@ -4,10 +4,34 @@
 //! OS packages, so their versions live in each firmware's `packages.json` rather
 //! than in a table we would have to maintain by hand. The package *names* vary by
 //! generation, so resolution tries a family of candidates.
+//!
+//! Data without opkg info, or with a package name not on the list, has none. So
+//! `webosbrew-fw-symbols-extract` also reads the versions out of the binaries
+//! themselves and saves them in `info.json`, where they take precedence.
+
+use std::fmt::{Display, Formatter};
+use std::io::{Error, Read};
+use std::path::Path;
 
+use regex::bytes::Regex;
 use semver::Version;
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
 
 use crate::Firmware;
+use crate::reader::RootfsReader;
+
+/// Where webOS installs the Node.js binary, on the firmware.
+pub const NODE_BINARIES: [&str; 2] = ["/usr/bin/node", "/usr/bin/nodejs"];
+
+/// File name prefixes of the libraries that carry the web engine: the Chromium
+/// Browser Engine WAM links, or the LG `WebKit` port.
+const CHROMIUM_LIBRARIES: [&str; 1] = ["libcbe.so"];
+const WEBKIT_LIBRARIES: [&str; 2] = ["libQt5WebKit.so", "libQtWebKit.so"];
+
+/// How much of a binary is searched at once. Consecutive chunks overlap, so
+/// that a string across the boundary is still found.
+const CHUNK_SIZE: usize = 1 << 20;
+const CHUNK_OVERLAP: usize = 256;
 
 /// The web-app rendering engine a firmware ships. webOS has used two families:
 /// a modern Chromium-based runtime (WAM) and, on the earliest TVs, an LG `WebKit`
@@ -28,15 +52,58 @@ impl WebEngine {
     }
 }
 
+impl Display for WebEngine {
+    /// The family and the full version, e.g. "Chromium 120.0.6099", as it is
+    /// saved in `info.json`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WebEngine::Chromium(v) => f.write_fmt(format_args!("Chromium {v}")),
+            WebEngine::WebKit(v) => f.write_fmt(format_args!("WebKit {v}")),
+        }
+    }
+}
+
+impl Serialize for WebEngine {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for WebEngine {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let str = String::deserialize(deserializer)?;
+        let (family, version) = str
+            .split_once(' ')
+            .ok_or_else(|| de::Error::custom(format!("bad web engine {str}")))?;
+        let version = Version::parse(version).map_err(de::Error::custom)?;
+        match family {
+            "Chromium" => Ok(WebEngine::Chromium(version)),
+            "WebKit" => Ok(WebEngine::WebKit(version)),
+            _ => Err(de::Error::custom(format!("unknown web engine {family}"))),
+        }
+    }
+}
+
 impl Firmware {
-    /// The Node.js version this firmware ships, if a node package is present.
+    /// The Node.js version this firmware ships: the one read from the binary,
+    /// else the node package's.
     pub fn node_version(&self) -> Option<Version> {
+        if let Some(node) = &self.info.node {
+            return Some(node.clone());
+        }
         ["lib32-nodejs", "nodejs"]
             .iter()
             .find_map(|name| self.pkg_version(name))
     }
 
-    /// The web-app engine this firmware ships. Resolution order:
+    /// The web-app engine this firmware ships. The one read from the web
+    /// runtime's libraries comes first. Otherwise, by package:
     /// 1. Chromium family: `lib32-webruntime` → `webruntime` → highest
     ///    `chromium<NN>` → `chromium-webos` → `chromium`;
     /// 2. `WebKit` family: `webkit-starfish` → `qt5-qtwebkit` → `libQt5WebKit`.
@@ -44,6 +111,9 @@ impl Firmware {
     /// `com.webos.app.browser` is deliberately ignored — it is the built-in
     /// browser *app*, not the web-app runtime.
     pub fn web_engine(&self) -> Option<WebEngine> {
+        if let Some(engine) = &self.info.web_engine {
+            return Some(engine.clone());
+        }
         for name in ["lib32-webruntime", "webruntime"] {
             if let Some(v) = self.pkg_version(name) {
                 return Some(WebEngine::Chromium(v));
@@ -66,7 +136,7 @@ impl Firmware {
     }
 
     /// Parse a package's `upstream` version string into a [`Version`].
-    fn pkg_version(&self, name: &str) -> Option<Version> {
+    pub(crate) fn pkg_version(&self, name: &str) -> Option<Version> {
         self.packages
             .get(name)
             .and_then(|entry| parse_leading_semver(&entry.version.upstream))
@@ -92,11 +162,97 @@ impl Firmware {
     }
 }
 
+/// The Node.js version a `node` binary was built as, from the headers URL
+/// `process.release` carries, or else the bare `v1.2.3` of `process.version`.
+pub fn read_node_version(reader: &dyn RootfsReader, file: &Path) -> Result<Option<Version>, Error> {
+    return find_version(
+        reader,
+        file,
+        &[
+            Regex::new(r"(?-u)node-v(\d+\.\d+\.\d+)-headers").unwrap(),
+            Regex::new(r"(?-u)\x00v(\d+\.\d+\.\d+)\x00").unwrap(),
+        ],
+    );
+}
+
+/// The web engine in one of the web runtime's libraries, if `file_name` is
+/// one. Chromium is read from a `Chrome/` user agent, or else its bare
+/// `NN.0.NNNN.NN` version; `WebKit` from its `AppleWebKit/` user agent.
+pub fn read_web_engine(
+    reader: &dyn RootfsReader,
+    file_name: &str,
+    file: &Path,
+) -> Result<Option<WebEngine>, Error> {
+    if CHROMIUM_LIBRARIES
+        .iter()
+        .any(|prefix| file_name.starts_with(prefix))
+    {
+        let version = find_version(
+            reader,
+            file,
+            &[
+                Regex::new(r"(?-u)Chrome/(\d+\.\d+\.\d+)\.\d+").unwrap(),
+                Regex::new(r"(?-u)\x00(\d{2,3}\.0\.\d{3,4})\.\d{1,3}\x00").unwrap(),
+            ],
+        )?;
+        return Ok(version.map(WebEngine::Chromium));
+    }
+    if WEBKIT_LIBRARIES
+        .iter()
+        .any(|prefix| file_name.starts_with(prefix))
+    {
+        let version = find_version(
+            reader,
+            file,
+            &[Regex::new(r"(?-u)AppleWebKit/(\d+\.\d+)").unwrap()],
+        )?;
+        return Ok(version.map(WebEngine::WebKit));
+    }
+    return Ok(None);
+}
+
+/// The version the first of `patterns` to match anywhere in the file
+/// captures. Each pattern is preferred over those after it.
+fn find_version(
+    reader: &dyn RootfsReader,
+    file: &Path,
+    patterns: &[Regex],
+) -> Result<Option<Version>, Error> {
+    let mut source = reader.open(file)?;
+    let mut best: Option<(usize, Version)> = None;
+    let mut buf = vec![0; CHUNK_SIZE];
+    let mut kept = 0;
+    loop {
+        let read = source.read(&mut buf[kept..])?;
+        if read == 0 {
+            break;
+        }
+        let chunk = &buf[..kept + read];
+        let untried = best.as_ref().map_or(patterns.len(), |(index, _)| *index);
+        for (index, pattern) in patterns[..untried].iter().enumerate() {
+            let found = pattern
+                .captures(chunk)
+                .and_then(|captures| parse_leading_semver(&String::from_utf8_lossy(&captures[1])));
+            if let Some(version) = found {
+                best = Some((index, version));
+                break;
+            }
+        }
+        if best.as_ref().is_some_and(|(index, _)| *index == 0) {
+            break;
+        }
+        kept = CHUNK_OVERLAP.min(chunk.len());
+        let start = chunk.len() - kept;
+        buf.copy_within(start..start + kept, 0);
+    }
+    return Ok(best.map(|(_, version)| version));
+}
+
 /// Take the leading dotted-numeric run of an `upstream` string and parse it as a
 /// three-component [`Version`]. Debian `upstream` strings are frequently longer
 /// than semver allows (`120.0.6099.270-137.paparoa.1`) or have a `-suffix`
 /// (`53.0.2785.34-92...`), so keep only the first three numeric parts.
-fn parse_leading_semver(upstream: &str) -> Option<Version> {
+pub(crate) fn parse_leading_semver(upstream: &str) -> Option<Version> {
     let lead: String = upstream
         .chars()
         .take_while(|c| c.is_ascii_digit() || *c == '.')
@@ -112,9 +268,45 @@ fn parse_leading_semver(upstream: &str) -> Option<Version> {
 mod tests {
     use super::*;
     use crate::Firmware;
+    use crate::reader::HostReader;
     use std::collections::HashMap;
     use std::path::PathBuf;
 
+    #[test]
+    fn reads_versions_from_binaries() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let node = dir.path().join("node");
+        let mut data = vec![0x7f, b'E', b'L', b'F'];
+        data.extend_from_slice(b"\0v8.12.0\0");
+        // Past the first chunk, to be found across the boundary.
+        data.resize(CHUNK_SIZE - 20, 0);
+        data.extend_from_slice(
+            b"https://nodejs.org/download/release/v8.12.0/node-v8.12.0-headers.tar.gz\0",
+        );
+        std::fs::write(&node, &data).unwrap();
+        let cbe = dir.path().join("libcbe.so");
+        std::fs::write(&cbe, b"\0\x0053.0.2785.34\0AppleWebKit/537.36\0").unwrap();
+
+        assert_eq!(
+            read_node_version(&HostReader, &node).unwrap(),
+            Some(Version::new(8, 12, 0))
+        );
+        assert_eq!(
+            read_web_engine(&HostReader, "libcbe.so", &cbe).unwrap(),
+            Some(WebEngine::Chromium(Version::new(53, 0, 2785)))
+        );
+        assert_eq!(
+            read_web_engine(&HostReader, "libfoo.so", &cbe).unwrap(),
+            None
+        );
+        let engine: WebEngine = serde_json::from_str("\"WebKit 537.41.0\"").unwrap();
+        assert_eq!(engine, WebEngine::WebKit(Version::new(537, 41, 0)));
+        assert_eq!(
+            serde_json::to_string(&WebEngine::Chromium(Version::new(120, 0, 6099))).unwrap(),
+            "\"Chromium 120.0.6099\""
+        );
+    }
+
     #[test]
     fn parses_various_upstream_strings() {
         assert_eq!(
@@ -0,0 +1,332 @@
+//! Consistency checks over a firmware data directory.
+//!
+//! [`Firmware::list`] skips a firmware it cannot load and
+//! [`Firmware::find_library`] treats a broken library file as a missing
+//! library, so bad data shows up as a misleading "missing library" in a report.
+//! These checks find it first.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{BufReader, Error};
+use std::path::{Path, PathBuf};
+
+use bin_lib::LibraryInfo;
+use semver::Version;
+
+use crate::Firmware;
+use crate::packed::PACK_EXTENSION;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DataIssue {
+    /// A firmware's `info.json`, `index.json`, `packages.json`,
+    /// `executables.json`, a library file or a pack that cannot be read or
+    /// parsed.
+    Unreadable { path: PathBuf, error: String },
+    /// An index entry for a library whose own file is missing.
+    MissingFile {
+        firmware: String,
+        name: String,
+        file: String,
+    },
+    /// An index entry for an alias (a symlink on the firmware) whose target's
+    /// file is missing.
+    DanglingAlias {
+        firmware: String,
+        name: String,
+        file: String,
+    },
+    /// A `release` in `info.json` that is not a semantic version.
+    BadRelease {
+        firmware: String,
+        release: String,
+        error: String,
+    },
+    /// More than one firmware, in a directory or a pack, with the same release
+    /// and OTA ID.
+    Duplicate {
+        release: String,
+        ota_id: String,
+        firmwares: Vec<String>,
+    },
+}
+
+/// Check every firmware directory and pack in `data_dir`. Fails only when the
+/// directory itself cannot be read.
+pub fn validate_data_dir(data_dir: &Path) -> Result<Vec<DataIssue>, Error> {
+    let mut entries: Vec<PathBuf> = data_dir
+        .read_dir()?
+        .filter_map(|ent| ent.ok().map(|ent| ent.path()))
+        .collect();
+    entries.sort();
+    let mut issues = Vec::new();
+    let mut releases: BTreeMap<(String, String), Vec<String>> = BTreeMap::new();
+    for path in entries {
+        if path.is_dir() {
+            let firmware = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            if let Some(key) = validate_firmware_dir(&path, &firmware, &mut issues) {
+                releases.entry(key).or_default().push(firmware);
+            }
+        } else if path.extension().is_some_and(|ext| ext == PACK_EXTENSION) {
+            match Firmware::load_pack(&path) {
+                // A pack's firmwares are named by the pack and the directory
+                // each one was packed from.
+                Ok(firmwares) => {
+                    let pack = path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    for fw in firmwares {
+                        let key = (fw.info.release.to_string(), fw.info.ota_id.clone());
+                        let name = format!("{pack}/{}", fw.info.dir_name());
+                        releases.entry(key).or_default().push(name);
+                    }
+                }
+                Err(e) => issues.push(DataIssue::Unreadable {
+                    path,
+                    error: e.to_string(),
+                }),
+            }
+        }
+    }
+    for ((release, ota_id), firmwares) in releases {
+        if firmwares.len() > 1 {
+            issues.push(DataIssue::Duplicate {
+                release,
+                ota_id,
+                firmwares,
+            });
+        }
+    }
+    return Ok(issues);
+}
+
+/// Check one firmware directory. Returns its release and OTA ID when
+/// `info.json` has them.
+fn validate_firmware_dir(
+    dir: &Path,
+    firmware: &str,
+    issues: &mut Vec<DataIssue>,
+) -> Option<(String, String)> {
+    let mut unreadable = |path: PathBuf, error: String| {
+        issues.push(DataIssue::Unreadable { path, error });
+    };
+    let info: Option<serde_json::Value> = read_json(&dir.join("info.json"))
+        .map_err(|e| unreadable(dir.join("info.json"), e))
+        .ok();
+    let index: Option<HashMap<String, String>> = read_json(&dir.join("index.json"))
+        .map_err(|e| unreadable(dir.join("index.json"), e))
+        .ok();
+    for optional in [
+        "packages.json",
+        "executables.json",
+        "luna.json",
+        "jail.json",
+    ] {
+        let path = dir.join(optional);
+        if path.exists() {
+            if let Err(e) = read_json::<serde_json::Value>(&path) {
+                unreadable(path, e);
+            }
+        }
+    }
+
+    if let Some(index) = index {
+        // Several names share a file. Read each file once.
+        let mut files: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for (name, file) in &index {
+            files.entry(file).or_default().push(name);
+        }
+        for (file, mut names) in files {
+            let path = dir.join(file);
+            if !path.exists() {
+                names.sort_unstable();
+                for name in names {
+                    let owner = file.strip_suffix(".json") == Some(name);
+                    let (firmware, name, file) = (
+                        String::from(firmware),
+                        String::from(name),
+                        String::from(file),
+                    );
+                    issues.push(if owner {
+                        DataIssue::MissingFile {
+                            firmware,
+                            name,
+                            file,
+                        }
+                    } else {
+                        DataIssue::DanglingAlias {
+                            firmware,
+                            name,
+                            file,
+                        }
+                    });
+                }
+                continue;
+            }
+            if let Err(e) = read_json::<LibraryInfo>(&path) {
+                issues.push(DataIssue::Unreadable { path, error: e });
+            }
+        }
+    }
+
+    let info = info?;
+    let field = |name: &str| info.get(name).and_then(serde_json::Value::as_str);
+    let (Some(release), Some(ota_id)) = (field("release"), field("ota_id")) else {
+        issues.push(DataIssue::Unreadable {
+            path: dir.join("info.json"),
+            error: String::from("no release or OTA ID"),
+        });
+        return None;
+    };
+    if let Err(e) = Version::parse(release) {
+        issues.push(DataIssue::BadRelease {
+            firmware: String::from(firmware),
+            release: String::from(release),
+            error: e.to_string(),
+        });
+    }
+    return Some((String::from(release), String::from(ota_id)));
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    return serde_json::from_reader(BufReader::new(file)).map_err(|e| e.to_string());
+}
+
+impl Display for DataIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DataIssue::Unreadable { path, error } => {
+                f.write_fmt(format_args!("{}: {error}", path.to_string_lossy()))
+            }
+            DataIssue::MissingFile {
+                firmware,
+                name,
+                file,
+            } => f.write_fmt(format_args!("{firmware}: {name} has no file {file}")),
+            DataIssue::DanglingAlias {
+                firmware,
+                name,
+                file,
+            } => f.write_fmt(format_args!(
+                "{firmware}: alias {name} points at missing {file}"
+            )),
+            DataIssue::BadRelease {
+                firmware,
+                release,
+                error,
+            } => f.write_fmt(format_args!(
+                "{firmware}: release {release} is not a version: {error}"
+            )),
+            DataIssue::Duplicate {
+                release,
+                ota_id,
+                firmwares,
+            } => f.write_fmt(format_args!(
+                "webOS {release} with OTA ID {ota_id} is in {}",
+                firmwares.join(", ")
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use super::{DataIssue, validate_data_dir};
+    use crate::packed::PackBuilder;
+
+    fn write_firmware(data_dir: &Path, name: &str, release: &str, index: &str) -> String {
+        let dir = data_dir.join(name);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("info.json"),
+            format!(r#"{{"version":"{name}","ota_id":"HE_DTV_TEST","release":"{release}"}}"#),
+        )
+        .unwrap();
+        fs::write(dir.join("index.json"), index).unwrap();
+        return String::from(name);
+    }
+
+    #[test]
+    fn reports_each_kind_of_issue() {
+        let data = tempfile::TempDir::new().unwrap();
+        let d = data.path();
+        let broken = write_firmware(
+            d,
+            "a",
+            "4.10",
+            r#"{
+                "libc.so.6": "libc-2.31.so.json",
+                "libgone.so": "libgone.so.json",
+                "libEGL.so.1": "libEGL.so.1.4.json",
+                "libEGL.so.1.4": "libEGL.so.1.4.json"
+            }"#,
+        );
+        fs::write(d.join("a/libc-2.31.so.json"), "{ not json").unwrap();
+        let first = write_firmware(d, "b", "5.0.0", "{}");
+        let second = write_firmware(d, "c", "5.0.0", "{}");
+
+        let issues = validate_data_dir(d).unwrap();
+
+        let firmware = broken.clone();
+        assert!(issues.contains(&DataIssue::MissingFile {
+            firmware: firmware.clone(),
+            name: String::from("libEGL.so.1.4"),
+            file: String::from("libEGL.so.1.4.json"),
+        }));
+        assert!(issues.contains(&DataIssue::DanglingAlias {
+            firmware: firmware.clone(),
+            name: String::from("libEGL.so.1"),
+            file: String::from("libEGL.so.1.4.json"),
+        }));
+        assert!(issues.contains(&DataIssue::MissingFile {
+            firmware: firmware.clone(),
+            name: String::from("libgone.so"),
+            file: String::from("libgone.so.json"),
+        }));
+        assert!(
+            issues
+                .iter()
+                .any(|issue| matches!(issue, DataIssue::Unreadable { path, .. } if path.ends_with("libc-2.31.so.json")))
+        );
+        assert!(issues.iter().any(
+            |issue| matches!(issue, DataIssue::BadRelease { release, .. } if release == "4.10")
+        ));
+        assert!(issues.contains(&DataIssue::Duplicate {
+            release: String::from("5.0.0"),
+            ota_id: String::from("HE_DTV_TEST"),
+            firmwares: vec![first, second],
+        }));
+        assert_eq!(issues.len(), 6, "got {issues:?}");
+    }
+
+    #[test]
+    fn finds_duplicates_across_packs_and_directories() {
+        let data = tempfile::TempDir::new().unwrap();
+        let src = tempfile::TempDir::new().unwrap();
+        let d = data.path();
+        let packed = write_firmware(src.path(), "04.10.00", "5.0.0", "{}");
+        let mut pack = PackBuilder::new();
+        pack.add_dir(src.path().join(&packed)).unwrap();
+        pack.write(d.join("webos.fwdb")).unwrap();
+        let dir = write_firmware(d, "04.10.01", "5.0.0", "{}");
+
+        let issues = validate_data_dir(d).unwrap();
+
+        assert_eq!(
+            issues,
+            vec![DataIssue::Duplicate {
+                release: String::from("5.0.0"),
+                ota_id: String::from("HE_DTV_TEST"),
+                firmwares: vec![dir, String::from("webos.fwdb/04.10.00-HE_DTV_TEST")],
+            }]
+        );
+    }
+}
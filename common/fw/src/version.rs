@@ -15,3 +15,30 @@ where
     let str = String::deserialize(deserializer)?;
     Version::parse(&str).map_err(de::Error::custom)
 }
+
+// serde's `serialize_with` passes the field as it is.
+#[allow(clippy::ref_option)]
+pub(crate) fn optional_version_serialize<S>(
+    value: &Option<Version>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match value {
+        Some(value) => version_serialize(value, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+pub(crate) fn optional_version_deserialize<'de, D>(
+    deserializer: D,
+) -> Result<Option<Version>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let Some(str) = Option::<String>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    Version::parse(&str).map(Some).map_err(de::Error::custom)
+}
@@ -1,8 +1,8 @@
-//! The opkg control parsing this extractor depends on.
+//! The opkg control parsing the rootfs scan depends on.
 //!
-//! `extractor.rs` reads every `*.control` file in a firmware image to learn
-//! which package owns which library. No firmware image is small enough to ship
-//! as a fixture, so nothing else here exercises `debian-control` or
+//! `rootfs.rs` reads every `*.control` file in a firmware image to learn the
+//! version of each installed package. No firmware image is small enough to
+//! ship as a fixture, so little else here exercises `debian-control` or
 //! `debversion`. These tests pin the exact calls that code makes, so a bump of
 //! either crate cannot change them without a failure.
 
@@ -45,7 +45,7 @@ fn a_bare_version_has_no_epoch_or_revision() {
     assert_eq!(version.debian_revision, None);
 }
 
-/// The extractor falls back to treating the raw string as the upstream version
+/// The scan falls back to treating the raw string as the upstream version
 /// when it does not parse. Keep a case that actually fails to parse, so that
 /// fallback stays reachable.
 #[test]
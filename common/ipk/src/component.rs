@@ -8,10 +8,12 @@ use std::path::{Path, PathBuf};
 
 use path_slash::{CowExt, PathExt};
 
-use bin_lib::{BinaryInfo, BundledArtifact, LibraryInfo, LibraryPriority};
+use bin_lib::{BinaryInfo, BundledArtifact, ElfArch, LibraryInfo, LibraryPriority};
 
 use crate::path::{ensure_within, file_label};
-use crate::{AppInfo, Component, ServiceInfo, Symlinks};
+use crate::rpath::expand_tokens;
+use crate::script::scan_scripts;
+use crate::{AppInfo, Component, RpathIssue, RpathNote, ServiceInfo, Symlinks};
 
 impl AppInfo {
     fn is_native(&self) -> bool {
@@ -31,7 +33,7 @@ impl ServiceInfo {
 impl Component<AppInfo> {
     pub(crate) fn parse<P: AsRef<Path>>(dir: P, links: &Symlinks) -> Result<Self, Error> {
         let dir = dir.as_ref();
-        let info: AppInfo = serde_json::from_reader(
+        let mut info: AppInfo = serde_json::from_reader(
             File::open(dir.join("appinfo.json"))
                 .map_err(|e| Error::new(e.kind(), format!("Failed to open appinfo.json: {e}")))?,
         )
@@ -41,12 +43,13 @@ impl Component<AppInfo> {
                 format!("Failed to parse appinfo.json: {e}"),
             )
         })?;
+        info.scripts = scan_scripts(dir);
+        info.luna_calls = webdetect_lib::find_luna_calls(dir);
         if !info.is_native() {
             // Web/hosted app: detect the frontend framework and JS syntax level
             // from the shipped HTML/JS while the extracted files still exist.
             // `main` is untrusted; keep it inside the app directory.
             let index_html = ensure_within(dir, &dir.join(Cow::from_slash(&info.main)))?;
-            let mut info = info;
             info.web = Some(webdetect_lib::detect_web_app(dir, &index_html));
             // A web app can still ship native binaries (a payload it starts
             // through the root service). Note them the same way as a JS
@@ -59,6 +62,8 @@ impl Component<AppInfo> {
                 info,
                 exe: None,
                 libs: Vec::new(),
+                rpath_notes: Vec::new(),
+                plugins: Vec::new(),
             });
         }
         let exe_path = ensure_within(dir, &dir.join(Cow::from_slash(&info.main)))?;
@@ -78,29 +83,39 @@ impl Component<AppInfo> {
                 format!("Bad app executable {}: {e}", info.main),
             )
         })?;
-        let libs = Self::list_libs(
+        // webOS starts a native component in its own directory, so that is
+        // where a relative rpath entry points.
+        let mut rpath_notes = Vec::new();
+        let rpath = Self::rpath(
+            &bin_info.rpath,
+            &exe_path,
             dir,
-            &Component::<AppInfo>::rpath(&bin_info.rpath, &exe_path),
-            links,
-        )?;
+            bin_info.arch,
+            &mut rpath_notes,
+        );
+        let libs = Self::list_libs(dir, &rpath, bin_info.arch, links, &mut rpath_notes)?;
+        let plugins = list_plugins(dir, &libs);
         return Ok(Self {
             id: info.id.clone(),
             info: info.clone(),
             exe: Some(bin_info),
             libs,
+            rpath_notes,
+            plugins,
         });
     }
 }
 impl Component<ServiceInfo> {
     pub(crate) fn parse<P: AsRef<Path>>(dir: P, links: &Symlinks) -> Result<Self, Error> {
         let dir = dir.as_ref();
-        let info: ServiceInfo = serde_json::from_reader(File::open(dir.join("services.json"))?)
+        let mut info: ServiceInfo = serde_json::from_reader(File::open(dir.join("services.json"))?)
             .map_err(|e| Error::new(ErrorKind::InvalidData, format!("Bad appinfo.json: {e:?}")))?;
+        info.scripts = scan_scripts(dir);
+        info.luna_calls = webdetect_lib::find_luna_calls(dir);
         if !info.is_native() {
             // JS/Node service: detect the declared Node.js runtime from the
             // bundled package.json while the extracted files still exist, and
             // note any native binaries it ships (its own node/ffmpeg/.so).
-            let mut info = info;
             info.runtime = Some(webdetect_lib::detect_service_runtime(dir));
             let scan = scan_bundled(dir, links);
             info.bundled = scan.artifacts;
@@ -110,6 +125,8 @@ impl Component<ServiceInfo> {
                 info: info.clone(),
                 exe: None,
                 libs: Vec::new(),
+                rpath_notes: Vec::new(),
+                plugins: Vec::new(),
             });
         }
         let executable = info.executable.as_ref().unwrap();
@@ -121,16 +138,25 @@ impl Component<ServiceInfo> {
                     format!("Bad app executable {executable}: {e:?}"),
                 )
             })?;
-        let libs = Self::list_libs(
+        // webOS starts a native component in its own directory, so that is
+        // where a relative rpath entry points.
+        let mut rpath_notes = Vec::new();
+        let rpath = Self::rpath(
+            &bin_info.rpath,
+            &exe_path,
             dir,
-            &Component::<ServiceInfo>::rpath(&bin_info.rpath, &exe_path),
-            links,
-        )?;
+            bin_info.arch,
+            &mut rpath_notes,
+        );
+        let libs = Self::list_libs(dir, &rpath, bin_info.arch, links, &mut rpath_notes)?;
+        let plugins = list_plugins(dir, &libs);
         return Ok(Self {
             id: info.id.clone(),
             info: info.clone(),
             exe: Some(bin_info),
             libs,
+            rpath_notes,
+            plugins,
         });
     }
 }
@@ -211,21 +237,84 @@ fn walk_bundled(root: &Path, dir: &Path, depth: usize, links: &Symlinks, scan: &
 fn verifiable_bundled_exe(path: &Path, rel: String, links: &Symlinks) -> Option<Component<()>> {
     let bin = BinaryInfo::parse(File::open(path).ok()?, file_label(path), true).ok()?;
     let parent = path.parent()?;
-    let mut rpath = Component::<()>::rpath(&bin.rpath, path);
+    let mut rpath_notes = Vec::new();
+    let mut rpath = Component::<()>::rpath(&bin.rpath, path, parent, bin.arch, &mut rpath_notes);
     if let Ok(sibling_lib) = parent.join("lib").canonicalize() {
         if !rpath.contains(&sibling_lib) {
             rpath.push(sibling_lib);
         }
     }
-    let libs = Component::<()>::list_libs(parent, &rpath, links).ok()?;
+    let libs =
+        Component::<()>::list_libs(parent, &rpath, bin.arch, links, &mut rpath_notes).ok()?;
     Some(Component {
         id: rel,
         info: (),
         exe: Some(bin),
         libs,
+        rpath_notes,
+        plugins: Vec::new(),
     })
 }
 
+/// Whether a directory, given relative to the component, is one a framework
+/// loads plugins from: any `plugins` directory (ours, or Qt's
+/// `plugins/platforms/` and siblings) and `gstreamer-1.0/`.
+fn is_plugin_dir(rel: &Path) -> bool {
+    return rel.components().any(|c| {
+        let name = c.as_os_str().to_string_lossy();
+        return name == "plugins" || name.starts_with("gstreamer-");
+    });
+}
+
+/// The shared objects under the component's plugin directories, skipping any
+/// that library discovery has already found on a search path.
+fn list_plugins(dir: &Path, libs: &[LibraryInfo]) -> Vec<LibraryInfo> {
+    let mut plugins = Vec::new();
+    walk_plugins(dir, dir, 0, libs, &mut plugins);
+    plugins.sort_by(|a, b| a.names.cmp(&b.names));
+    return plugins;
+}
+
+fn walk_plugins(
+    root: &Path,
+    dir: &Path,
+    depth: usize,
+    libs: &[LibraryInfo],
+    plugins: &mut Vec<LibraryInfo>,
+) {
+    if depth > BUNDLED_MAX_DEPTH || plugins.len() >= BUNDLED_MAX {
+        return;
+    }
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let Ok(ft) = entry.file_type() else { continue };
+        let path = entry.path();
+        if ft.is_dir() {
+            walk_plugins(root, &path, depth + 1, libs, plugins);
+            continue;
+        }
+        let in_plugin_dir = dir.strip_prefix(root).is_ok_and(is_plugin_dir);
+        let label = file_label(&path);
+        if !ft.is_file() || !in_plugin_dir || !label.contains(".so") {
+            continue;
+        }
+        if libs.iter().any(|lib| lib.has_name(&label)) {
+            continue;
+        }
+        let Ok(file) = File::open(&path) else {
+            continue;
+        };
+        let Ok(mut plugin) = LibraryInfo::parse(file, true, &label) else {
+            continue;
+        };
+        plugin.priority = LibraryPriority::Package;
+        plugin.names.push(label.into_owned());
+        plugins.push(plugin);
+    }
+}
+
 impl<T> Component<T> {
     pub fn find_lib(&self, name: &str) -> Option<&'_ LibraryInfo> {
         return self.libs.iter().find(|lib| lib.has_name(name));
@@ -238,11 +327,18 @@ impl<T> Component<T> {
         return exe.needed.iter().any(|needed| lib.has_name(needed));
     }
 
-    fn rpath<P>(rpath: &[String], bin_path: P) -> Vec<PathBuf>
-    where
-        P: AsRef<Path>,
-    {
-        let origin = bin_path.as_ref().parent().unwrap_or(Path::new("."));
+    /// The directories the loader searches for an rpath, in order. `cwd` is the
+    /// directory the process starts in, which a relative entry without
+    /// `$ORIGIN` is resolved against. Every entry that is dropped is recorded
+    /// in `notes`.
+    fn rpath(
+        rpath: &[String],
+        bin_path: &Path,
+        cwd: &Path,
+        arch: Option<ElfArch>,
+        notes: &mut Vec<RpathNote>,
+    ) -> Vec<PathBuf> {
+        let origin = bin_path.parent().unwrap_or(Path::new("."));
         // Compare canonical forms on both sides. `canonicalize` returns a `\\?\`
         // verbatim path on Windows (and resolves symlinks everywhere), which
         // shares no prefix with a plain `C:\…` origin, so `common_path` below
@@ -256,26 +352,50 @@ impl<T> Component<T> {
             .ancestors()
             .last()
             .map_or(1, |root| root.components().count());
-        return rpath
-            .iter()
-            .filter_map(|p| {
-                PathBuf::from(p.replace("$ORIGIN", origin.to_string_lossy().as_ref()))
-                    .canonicalize()
-                    .ok()
-            })
-            .filter(|p| {
-                let Some(common) = common_path(p, &origin_canon) else {
-                    return false;
-                };
-                return common.components().count() > root_depth;
-            })
-            .collect();
+        let mut dirs = Vec::new();
+        for entry in rpath {
+            let mut note = |issue: RpathIssue| {
+                notes.push(RpathNote {
+                    binary: String::from(file_label(bin_path)),
+                    entry: entry.clone(),
+                    issue,
+                });
+            };
+            let expanded = match expand_tokens(entry, origin.to_string_lossy().as_ref(), arch) {
+                Ok(expanded) => PathBuf::from(expanded),
+                Err(issue) => {
+                    note(issue);
+                    continue;
+                }
+            };
+            // The loader takes an empty entry, or any relative one, from the
+            // working directory.
+            let expanded = if expanded.is_relative() {
+                cwd.join(expanded)
+            } else {
+                expanded
+            };
+            let Ok(dir) = expanded.canonicalize() else {
+                note(RpathIssue::NotFound);
+                continue;
+            };
+            let within = common_path(&dir, &origin_canon)
+                .is_some_and(|common| common.components().count() > root_depth);
+            if !within {
+                note(RpathIssue::OutsidePackage);
+                continue;
+            }
+            dirs.push(dir);
+        }
+        return dirs;
     }
 
     fn list_libs(
         dir: &Path,
         rpath: &Vec<PathBuf>,
+        arch: Option<ElfArch>,
         links: &Symlinks,
+        notes: &mut Vec<RpathNote>,
     ) -> Result<Vec<LibraryInfo>, Error> {
         let mut libs: HashMap<PathBuf, LibraryInfo> = HashMap::new();
         let mut visited_dirs: HashSet<PathBuf> = HashSet::new();
@@ -320,7 +440,7 @@ impl<T> Component<T> {
                 };
                 // A bundled library's own runpath can point at further bundled
                 // directories; queue them for discovery too.
-                for sub_dir in Self::rpath(&lib.rpath, &path) {
+                for sub_dir in Self::rpath(&lib.rpath, &path, dir, arch, notes) {
                     if !visited_dirs.contains(&sub_dir) {
                         queue.push_back((sub_dir, true));
                     }
@@ -347,6 +467,7 @@ mod tests {
     use super::*;
     use bin_lib::ArtifactKind;
     use std::collections::HashMap;
+    use std::io::Cursor;
 
     fn empty_links() -> Symlinks {
         Symlinks::new(&HashMap::new())
@@ -512,6 +633,34 @@ mod tests {
         assert!(svc.info.bundled.is_empty());
     }
 
+    #[test]
+    fn lists_plugins_outside_the_search_path() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let d = dir.path();
+        let so = &include_bytes!("../../bin/src/fixtures/lib_runpath.so")[..];
+        for sub in ["plugins/platforms", "lib/gstreamer-1.0", "share"] {
+            fs::create_dir_all(d.join(sub)).unwrap();
+        }
+        fs::write(d.join("plugins/platforms/libqwayland.so"), so).unwrap();
+        fs::write(d.join("lib/gstreamer-1.0/libgstapp.so"), so).unwrap();
+        // Already found on the rpath, so verified with the libraries.
+        fs::write(d.join("lib/gstreamer-1.0/libgstfound.so"), so).unwrap();
+        // Not a plugin directory.
+        fs::write(d.join("share/libdata.so"), so).unwrap();
+        let mut found = LibraryInfo::parse(Cursor::new(so), true, "libgstfound.so").unwrap();
+        found.names.push(String::from("libgstfound.so"));
+
+        let plugins = list_plugins(d, &[found]);
+
+        let names: Vec<&String> = plugins.iter().map(|p| &p.names[0]).collect();
+        assert_eq!(names, vec!["libgstapp.so", "libqwayland.so"]);
+        assert!(
+            plugins
+                .iter()
+                .all(|p| p.priority == LibraryPriority::Package)
+        );
+    }
+
     #[test]
     fn rpath_resolves_origin_relative_dirs() {
         // An app laid out like Moonlight: the executable sits in `bin/` and its
@@ -523,6 +672,7 @@ mod tests {
         let exe = d.join("bin/moonlight");
         fs::write(&exe, b"x").unwrap();
 
+        let mut notes = Vec::new();
         let paths = Component::<()>::rpath(
             &[
                 // Does not exist -> dropped.
@@ -531,11 +681,66 @@ mod tests {
                 String::from("$ORIGIN"),
             ],
             &exe,
+            d,
+            None,
+            &mut notes,
         );
 
         let backports = d.join("lib/backports").canonicalize().unwrap();
         let bin = d.join("bin").canonicalize().unwrap();
         assert_eq!(paths, vec![backports, bin], "got {paths:?}");
+        assert_eq!(
+            notes,
+            vec![RpathNote {
+                binary: String::from("moonlight"),
+                entry: String::from("$ORIGIN/lib/backports"),
+                issue: RpathIssue::NotFound,
+            }]
+        );
+    }
+
+    #[test]
+    fn rpath_expands_lib_platform_and_relative_entries() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let d = dir.path();
+        fs::create_dir_all(d.join("bin")).unwrap();
+        fs::create_dir_all(d.join("lib/v7l")).unwrap();
+        fs::create_dir_all(d.join("plugins")).unwrap();
+        let exe = d.join("bin/app");
+        fs::write(
+            &exe,
+            &include_bytes!("../../bin/src/fixtures/sample.bin")[..],
+        )
+        .unwrap();
+        let arch = BinaryInfo::parse(File::open(&exe).unwrap(), "app", true)
+            .unwrap()
+            .arch;
+
+        let mut notes = Vec::new();
+        let paths = Component::<()>::rpath(
+            &[
+                String::from("${ORIGIN}/../$LIB/$PLATFORM"),
+                // No `$ORIGIN`: taken from the working directory.
+                String::from("plugins"),
+                String::from("$ORIGIN/$UNKNOWN"),
+            ],
+            &exe,
+            d,
+            arch,
+            &mut notes,
+        );
+
+        assert_eq!(
+            paths,
+            vec![
+                d.join("lib/v7l").canonicalize().unwrap(),
+                d.join("plugins").canonicalize().unwrap(),
+            ]
+        );
+        assert_eq!(
+            notes.iter().map(|n| &n.issue).collect::<Vec<_>>(),
+            vec![&RpathIssue::UnknownToken(String::from("$UNKNOWN"))]
+        );
     }
 
     #[test]
@@ -552,9 +757,17 @@ mod tests {
             .unwrap()
             .to_path_buf();
 
-        let paths = Component::<()>::rpath(&[root.to_string_lossy().into_owned()], &exe);
+        let mut notes = Vec::new();
+        let paths = Component::<()>::rpath(
+            &[root.to_string_lossy().into_owned()],
+            &exe,
+            dir.path(),
+            None,
+            &mut notes,
+        );
 
         assert!(paths.is_empty(), "expected no rpath dirs, got {paths:?}");
+        assert_eq!(notes[0].issue, RpathIssue::OutsidePackage);
     }
 
     #[test]
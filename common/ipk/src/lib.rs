@@ -3,12 +3,16 @@ use std::collections::HashMap;
 use std::path::PathBuf;
 
 use bin_lib::{BinaryInfo, BundledArtifact, LibraryInfo};
-use webdetect_lib::{ServiceRuntimeDetection, WebAppDetection};
+use webdetect_lib::{LunaCall, ServiceRuntimeDetection, WebAppDetection};
 
 mod component;
 mod ipk;
 mod links;
 mod path;
+mod rpath;
+mod script;
+
+pub use script::ShellScript;
 
 #[derive(Debug)]
 pub struct Package {
@@ -31,6 +35,36 @@ pub struct Component<T> {
     pub info: T,
     pub exe: Option<BinaryInfo>,
     pub libs: Vec<LibraryInfo>,
+    /// Rpath entries of the executable or a bundled library that the loader
+    /// would search but the tool could not follow, or that point outside the
+    /// package. Libraries behind them are never discovered, so the report
+    /// notes each one.
+    pub rpath_notes: Vec<RpathNote>,
+    /// Shared objects in a plugin directory (`plugins/`, `lib/gstreamer-1.0/`
+    /// and the like) that no search path covers. A framework loads them with
+    /// `dlopen` by path, so they are verified apart from `libs`.
+    pub plugins: Vec<LibraryInfo>,
+}
+
+/// One rpath entry the library discovery did not follow.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RpathNote {
+    /// The file name of the binary that carries the entry.
+    pub binary: String,
+    /// The entry as written in `DT_RPATH`/`DT_RUNPATH`.
+    pub entry: String,
+    pub issue: RpathIssue,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RpathIssue {
+    /// A `$TOKEN` the loader does not know, or one with no value for this
+    /// binary's architecture. The loader drops such an entry.
+    UnknownToken(String),
+    /// The expanded directory does not exist in the package.
+    NotFound,
+    /// The expanded directory is outside the package.
+    OutsidePackage,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -49,6 +83,10 @@ pub struct AppInfo {
     pub title: String,
     pub app_description: Option<String>,
     pub main: String,
+    /// The ACG (access control group) permission groups the app asks for. The
+    /// app's services run with them too.
+    #[serde(default)]
+    pub required_permissions: Vec<String>,
     /// Web/frontend technology detected for non-native apps (filled at parse
     /// time; not part of appinfo.json).
     #[serde(skip)]
@@ -64,6 +102,14 @@ pub struct AppInfo {
     /// parse time; supplementary (never gates the verdict).
     #[serde(skip)]
     pub bundled_bins: Vec<Component<()>>,
+    /// Shell scripts in the app directory, with the commands they run. Filled
+    /// at parse time; checked against each firmware's executables as advice.
+    #[serde(skip)]
+    pub scripts: Vec<ShellScript>,
+    /// The `luna://` calls in the app's JS, HTML and scripts. Filled at parse
+    /// time; checked against each firmware's Luna services as advice.
+    #[serde(skip)]
+    pub luna_calls: Vec<LunaCall>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -86,6 +132,14 @@ pub struct ServiceInfo {
     /// at parse time; supplementary (never gates the verdict).
     #[serde(skip)]
     pub bundled_bins: Vec<Component<()>>,
+    /// Shell scripts in the service directory, with the commands they run.
+    /// Filled at parse time, like `bundled`.
+    #[serde(skip)]
+    pub scripts: Vec<ShellScript>,
+    /// The `luna://` calls in the service's JS and scripts. Filled at parse
+    /// time, like `scripts`.
+    #[serde(skip)]
+    pub luna_calls: Vec<LunaCall>,
 }
 
 #[derive(Debug)]
@@ -0,0 +1,105 @@
+//! Dynamic string token expansion for rpath entries.
+//!
+//! The loader substitutes `$ORIGIN`, `$LIB` and `$PLATFORM` (or their `${...}`
+//! forms) in every `DT_RPATH`/`DT_RUNPATH` entry before it searches it. An entry
+//! with any other token is dropped, as is one whose token has no value.
+
+use std::fmt::{Display, Formatter};
+
+use bin_lib::ElfArch;
+
+use crate::RpathIssue;
+
+/// Expand every token in `entry`. `origin` is the directory of the binary that
+/// carries the entry, and `arch` its ELF header's class and machine.
+pub(crate) fn expand_tokens(
+    entry: &str,
+    origin: &str,
+    arch: Option<ElfArch>,
+) -> Result<String, RpathIssue> {
+    let mut expanded = String::with_capacity(entry.len());
+    let mut rest = entry;
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let (name, len) = if let Some(braced) = after.strip_prefix('{') {
+            let Some(end) = braced.find('}') else {
+                return Err(RpathIssue::UnknownToken(String::from(after)));
+            };
+            (&braced[..end], end + 2)
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..end], end)
+        };
+        let value = match name {
+            "ORIGIN" => Some(origin),
+            "LIB" => arch.map(ElfArch::lib_dir),
+            "PLATFORM" => arch.and_then(ElfArch::platform),
+            _ => None,
+        };
+        let Some(value) = value else {
+            return Err(RpathIssue::UnknownToken(format!("${name}")));
+        };
+        expanded.push_str(value);
+        rest = &after[len..];
+    }
+    expanded.push_str(rest);
+    return Ok(expanded);
+}
+
+impl Display for RpathIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RpathIssue::UnknownToken(token) => {
+                f.write_fmt(format_args!("the loader cannot expand {token}"))
+            }
+            RpathIssue::NotFound => f.write_str("the directory is not in the package"),
+            RpathIssue::OutsidePackage => f.write_str("it points outside the package"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bin_lib::{ElfArch, ElfClass};
+
+    use super::expand_tokens;
+    use crate::RpathIssue;
+
+    const ARM: ElfArch = ElfArch {
+        class: ElfClass::Elf32,
+        // EM_ARM
+        machine: 40,
+    };
+
+    #[test]
+    fn expands_every_known_token() {
+        assert_eq!(
+            expand_tokens("$ORIGIN/../$LIB/${PLATFORM}", "/app/bin", Some(ARM)),
+            Ok(String::from("/app/bin/../lib/v7l"))
+        );
+        assert_eq!(
+            expand_tokens("${ORIGIN}lib", "/app", Some(ARM)),
+            Ok(String::from("/applib"))
+        );
+        assert_eq!(
+            expand_tokens("lib/plugins", "/app", None),
+            Ok(String::from("lib/plugins"))
+        );
+    }
+
+    #[test]
+    fn rejects_what_the_loader_drops() {
+        assert_eq!(
+            expand_tokens("$ORIGIN/$FOO", "/app", Some(ARM)),
+            Err(RpathIssue::UnknownToken(String::from("$FOO")))
+        );
+        // Without a header, `$LIB` has no value.
+        assert_eq!(
+            expand_tokens("$ORIGIN/$LIB", "/app", None),
+            Err(RpathIssue::UnknownToken(String::from("$LIB")))
+        );
+    }
+}
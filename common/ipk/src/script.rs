@@ -0,0 +1,761 @@
+//! Shell scripts a package ships, and the commands they run.
+//!
+//! Services often start helpers through a script that calls `curl`,
+//! `luna-send` or a busybox applet. Which of those a firmware has varies, so
+//! each script is read for the commands it runs from `PATH`. This is a lexer,
+//! not a shell: a command built at run time (`$CMD args`) is not found, and
+//! anything that only looks like a command is rather left out than reported.
+
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use path_slash::PathExt;
+
+/// Recursion depth cap for the script walk.
+const SCRIPTS_MAX_DEPTH: usize = 12;
+/// Stop after reading this many scripts.
+const SCRIPTS_MAX: usize = 256;
+/// Larger files are not scripts worth reading.
+const SCRIPT_MAX_SIZE: u64 = 1 << 20;
+
+/// The directories on the default `PATH`. A command named by its full path in
+/// one of them is looked up by its name.
+const PATH_DIRS: [&str; 5] = ["/bin", "/sbin", "/usr/bin", "/usr/sbin", "/usr/local/bin"];
+
+/// Interpreters whose scripts are read for commands.
+const SHELLS: [&str; 6] = ["sh", "ash", "bash", "dash", "ksh", "zsh"];
+
+/// Commands the shell runs itself.
+const BUILTINS: [&str; 39] = [
+    ":", ".", "[", "alias", "break", "cd", "command", "continue", "declare", "echo", "eval",
+    "exec", "exit", "export", "false", "getopts", "hash", "kill", "let", "local", "printf", "pwd",
+    "read", "readonly", "return", "set", "shift", "source", "test", "times", "trap", "true",
+    "type", "typeset", "ulimit", "umask", "unalias", "unset", "wait",
+];
+
+/// Commands that run the command after them. Each is a command as well, except
+/// `exec` and `command`, which are builtins.
+const WRAPPERS: [&str; 6] = ["busybox", "command", "env", "exec", "nice", "nohup"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShellScript {
+    /// The path in the component, with forward slashes.
+    pub path: String,
+    /// The name of the interpreter on the `#!` line, if there is one.
+    pub interpreter: Option<String>,
+    /// The commands the script runs from `PATH`, the interpreter included.
+    /// Builtins, the script's own functions and the package's own files are
+    /// left out.
+    pub commands: BTreeSet<String>,
+}
+
+/// Every shell script in a component directory: `.sh` files, and files that
+/// start with `#!`. Sorted by path.
+pub(crate) fn scan_scripts(dir: &Path) -> Vec<ShellScript> {
+    let mut scripts = Vec::new();
+    let mut own_files = HashSet::new();
+    walk_scripts(dir, dir, 0, &mut scripts, &mut own_files);
+    for script in &mut scripts {
+        script.commands.retain(|name| !own_files.contains(name));
+    }
+    scripts.sort_by(|a, b| a.path.cmp(&b.path));
+    return scripts;
+}
+
+fn walk_scripts(
+    root: &Path,
+    dir: &Path,
+    depth: usize,
+    scripts: &mut Vec<ShellScript>,
+    own_files: &mut HashSet<String>,
+) {
+    if depth > SCRIPTS_MAX_DEPTH {
+        return;
+    }
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let Ok(ft) = entry.file_type() else { continue };
+        let path = entry.path();
+        if ft.is_dir() {
+            walk_scripts(root, &path, depth + 1, scripts, own_files);
+            continue;
+        }
+        own_files.insert(entry.file_name().to_string_lossy().into_owned());
+        if !ft.is_file() || scripts.len() >= SCRIPTS_MAX {
+            continue;
+        }
+        let rel = path
+            .strip_prefix(root)
+            .unwrap_or(&path)
+            .to_slash_lossy()
+            .into_owned();
+        if let Some(script) = read_script(&path, rel) {
+            scripts.push(script);
+        }
+    }
+}
+
+fn read_script(path: &Path, rel: String) -> Option<ShellScript> {
+    let mut text = Vec::new();
+    File::open(path)
+        .ok()?
+        .take(SCRIPT_MAX_SIZE + 1)
+        .read_to_end(&mut text)
+        .ok()?;
+    if text.len() as u64 > SCRIPT_MAX_SIZE {
+        return None;
+    }
+    let is_sh = path.extension().is_some_and(|ext| ext == "sh");
+    if !is_sh && !text.starts_with(b"#!") {
+        return None;
+    }
+    return Some(ShellScript::parse(rel, &String::from_utf8_lossy(&text)));
+}
+
+impl ShellScript {
+    /// Read a script's commands. A script without a `#!` line is taken for a
+    /// shell script.
+    pub fn parse(path: String, text: &str) -> ShellScript {
+        let interpreter = text
+            .strip_prefix("#!")
+            .and_then(|line| interpreter(line.lines().next().unwrap_or_default()));
+        let is_shell = interpreter
+            .as_ref()
+            .is_none_or(|name| SHELLS.contains(&name.as_str()));
+        let mut commands = BTreeSet::new();
+        if is_shell {
+            let mut lexer = Lexer::new(text);
+            lexer.list(None);
+            commands = lexer
+                .commands
+                .into_iter()
+                .filter(|name| !lexer.functions.contains(name))
+                .collect();
+        }
+        commands.extend(interpreter.iter().cloned());
+        return ShellScript {
+            path,
+            interpreter,
+            commands,
+        };
+    }
+}
+
+/// The command a `#!` line runs: the interpreter's name, or for `env`, the
+/// name of the program it looks up.
+fn interpreter(line: &str) -> Option<String> {
+    let mut words = line.split_whitespace();
+    let name = basename(words.next()?);
+    if name == "env" {
+        return words.find(|word| !word.starts_with('-')).map(basename);
+    }
+    return Some(name);
+}
+
+fn basename(path: &str) -> String {
+    return String::from(path.rsplit('/').next().unwrap_or(path));
+}
+
+/// What the next word in a command list is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Position {
+    /// A command, or an assignment or keyword before one.
+    Command,
+    /// An argument.
+    Argument,
+    /// The command after a wrapper, once its options are skipped.
+    Wrapped,
+    /// The name after `function`.
+    FunctionName,
+    /// The word after `case`.
+    CaseWord,
+    /// The `in` after a `case` word.
+    CaseIn,
+    /// A `case` pattern, up to its `)`.
+    Pattern,
+}
+
+/// One word, with its quotes removed and its expansions dropped.
+struct Word {
+    text: String,
+    /// Whether part of the word is only known at run time.
+    expanded: bool,
+}
+
+struct Lexer {
+    chars: Vec<char>,
+    pos: usize,
+    /// Here-documents whose bodies start at the next line: the delimiter, and
+    /// whether leading tabs are stripped.
+    heredocs: Vec<(String, bool)>,
+    /// Whether a backquote ends the current word: the lexer is inside a
+    /// backquoted substitution.
+    in_backquote: bool,
+    commands: BTreeSet<String>,
+    functions: BTreeSet<String>,
+}
+
+impl Lexer {
+    fn new(text: &str) -> Lexer {
+        return Lexer {
+            chars: text.chars().collect(),
+            pos: 0,
+            heredocs: Vec::new(),
+            in_backquote: false,
+            commands: BTreeSet::new(),
+            functions: BTreeSet::new(),
+        };
+    }
+
+    fn peek(&self) -> Option<char> {
+        return self.chars.get(self.pos).copied();
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        return self.chars.get(self.pos + offset).copied();
+    }
+
+    fn starts_with(&self, text: &str) -> bool {
+        return text
+            .chars()
+            .enumerate()
+            .all(|(i, c)| self.peek_at(i) == Some(c));
+    }
+
+    /// Read commands up to the end, or up to `close`, which ends a command
+    /// substitution.
+    fn list(&mut self, close: Option<char>) {
+        let mut position = Position::Command;
+        let mut case_depth = 0usize;
+        let mut paren_depth = 0usize;
+        let mut in_test = false;
+        while let Some(c) = self.peek() {
+            match c {
+                ' ' | '\t' | '\r' => self.pos += 1,
+                '\\' if self.peek_at(1) == Some('\n') => self.pos += 2,
+                '\n' => {
+                    self.pos += 1;
+                    self.skip_heredocs();
+                    if !matches!(
+                        position,
+                        Position::Pattern | Position::CaseWord | Position::CaseIn
+                    ) {
+                        position = Position::Command;
+                    }
+                }
+                '#' => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.pos += 1;
+                    }
+                }
+                ')' if position == Position::Pattern => {
+                    self.pos += 1;
+                    position = Position::Command;
+                }
+                '(' | '|' if position == Position::Pattern => self.pos += 1,
+                ')' if paren_depth == 0 && close == Some(')') => {
+                    self.pos += 1;
+                    return;
+                }
+                '`' if close == Some('`') => {
+                    self.pos += 1;
+                    return;
+                }
+                ')' => {
+                    self.pos += 1;
+                    paren_depth = paren_depth.saturating_sub(1);
+                    position = Position::Argument;
+                }
+                '(' if self.peek_at(1) == Some('(') => {
+                    self.skip_arithmetic();
+                    position = Position::Argument;
+                }
+                '(' => {
+                    self.pos += 1;
+                    paren_depth += 1;
+                    position = Position::Command;
+                }
+                ';' => {
+                    self.pos += 1;
+                    if self.peek() == Some(';') || self.peek() == Some('&') {
+                        self.pos += 1;
+                        if self.peek() == Some('&') {
+                            self.pos += 1;
+                        }
+                        if case_depth > 0 {
+                            position = Position::Pattern;
+                            continue;
+                        }
+                    }
+                    position = Position::Command;
+                }
+                '&' if self.peek_at(1) == Some('>') => {
+                    self.pos += 1;
+                    self.redirection();
+                }
+                '&' | '|' => {
+                    self.pos += 1;
+                    if matches!(self.peek(), Some('&' | '|')) {
+                        self.pos += 1;
+                    }
+                    if !in_test {
+                        position = Position::Command;
+                    }
+                }
+                '<' | '>' => self.redirection(),
+                _ => {
+                    let start = self.pos;
+                    let word = self.word();
+                    if self.pos == start {
+                        // Nothing a word can start with, such as a stray
+                        // backquote. Skip it rather than stop here for good.
+                        self.pos += 1;
+                        continue;
+                    }
+                    if word.text.chars().all(|c| c.is_ascii_digit())
+                        && matches!(self.peek(), Some('<' | '>'))
+                    {
+                        // A file descriptor, as in `2>&1`.
+                        continue;
+                    }
+                    position = match position {
+                        Position::Command | Position::Wrapped => {
+                            self.command_word(&word, position, &mut case_depth, &mut in_test)
+                        }
+                        Position::FunctionName => {
+                            self.functions
+                                .insert(String::from(word.text.trim_end_matches("()")));
+                            self.skip_parens();
+                            Position::Command
+                        }
+                        Position::CaseWord => Position::CaseIn,
+                        Position::CaseIn if word.text == "in" => {
+                            case_depth += 1;
+                            Position::Pattern
+                        }
+                        Position::Pattern if word.text == "esac" => {
+                            case_depth = case_depth.saturating_sub(1);
+                            Position::Argument
+                        }
+                        Position::Argument if in_test && word.text == "]]" => {
+                            in_test = false;
+                            Position::Argument
+                        }
+                        other => other,
+                    };
+                }
+            }
+        }
+    }
+
+    /// Take a word in command position, and say what the next one is.
+    fn command_word(
+        &mut self,
+        word: &Word,
+        position: Position,
+        case_depth: &mut usize,
+        in_test: &mut bool,
+    ) -> Position {
+        let text = word.text.as_str();
+        if is_assignment(text) {
+            return position;
+        }
+        if position == Position::Wrapped
+            && (text.starts_with('-') || text.chars().all(|c| c.is_ascii_digit()))
+        {
+            return position;
+        }
+        if word.expanded || text.is_empty() || text.starts_with('-') {
+            return Position::Argument;
+        }
+        match text {
+            "if" | "then" | "else" | "elif" | "while" | "until" | "do" | "!" | "{" | "time" => {
+                return Position::Command;
+            }
+            "fi" | "done" | "}" | "for" | "select" | "in" => return Position::Argument,
+            "esac" => {
+                *case_depth = case_depth.saturating_sub(1);
+                return Position::Argument;
+            }
+            "case" => return Position::CaseWord,
+            "function" => return Position::FunctionName,
+            "[[" => {
+                *in_test = true;
+                return Position::Argument;
+            }
+            _ => {}
+        }
+        if self.skip_parens() {
+            self.functions.insert(String::from(text));
+            return Position::Command;
+        }
+        if let Some(name) = command_name(text) {
+            let wraps = WRAPPERS.contains(&name.as_str());
+            if !BUILTINS.contains(&name.as_str()) {
+                self.commands.insert(name);
+            }
+            if wraps {
+                return Position::Wrapped;
+            }
+        }
+        return Position::Argument;
+    }
+
+    /// Skip the `()` of a function definition. Returns whether there was one.
+    fn skip_parens(&mut self) -> bool {
+        let start = self.pos;
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.pos += 1;
+        }
+        if self.peek() == Some('(') {
+            self.pos += 1;
+            while matches!(self.peek(), Some(' ' | '\t')) {
+                self.pos += 1;
+            }
+            if self.peek() == Some(')') {
+                self.pos += 1;
+                return true;
+            }
+        }
+        self.pos = start;
+        return false;
+    }
+
+    /// Read a redirection operator and its target. A here-document's body is
+    /// skipped at the next line.
+    fn redirection(&mut self) {
+        let heredoc = self.starts_with("<<") && !self.starts_with("<<<");
+        while matches!(self.peek(), Some('<' | '>' | '&' | '|')) {
+            self.pos += 1;
+        }
+        let strip_tabs = heredoc && self.peek() == Some('-');
+        if strip_tabs {
+            self.pos += 1;
+        }
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.pos += 1;
+        }
+        if matches!(self.peek(), Some('-')) && !heredoc {
+            // Closing a descriptor, as in `>&-`.
+            self.pos += 1;
+            return;
+        }
+        let target = self.word();
+        if heredoc {
+            self.heredocs.push((target.text, strip_tabs));
+        }
+    }
+
+    fn skip_heredocs(&mut self) {
+        for (delimiter, strip_tabs) in std::mem::take(&mut self.heredocs) {
+            while self.pos < self.chars.len() {
+                let start = self.pos;
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.pos += 1;
+                }
+                let line: String = self.chars[start..self.pos].iter().collect();
+                self.pos += 1;
+                let line = if strip_tabs {
+                    line.trim_start_matches('\t')
+                } else {
+                    line.as_str()
+                };
+                if line.trim_end_matches('\r') == delimiter {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Skip `((...))` or `$((...))`, from the first `(`.
+    fn skip_arithmetic(&mut self) {
+        let mut depth = 0usize;
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                '(' => depth += 1,
+                ')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Read one word. Commands in a substitution inside it are read as well.
+    fn word(&mut self) -> Word {
+        let mut word = Word {
+            text: String::new(),
+            expanded: false,
+        };
+        while let Some(c) = self.peek() {
+            match c {
+                ' ' | '\t' | '\r' | '\n' | ';' | '&' | '|' | '(' | ')' | '<' | '>' => break,
+                '`' if self.in_backquote => break,
+                '\\' => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some('\n') => self.pos += 1,
+                        Some(c) => {
+                            word.text.push(c);
+                            self.pos += 1;
+                        }
+                        None => {}
+                    }
+                }
+                '\'' => {
+                    self.pos += 1;
+                    while let Some(c) = self.peek() {
+                        self.pos += 1;
+                        if c == '\'' {
+                            break;
+                        }
+                        word.text.push(c);
+                    }
+                }
+                '"' => {
+                    self.pos += 1;
+                    while let Some(c) = self.peek() {
+                        match c {
+                            '"' => {
+                                self.pos += 1;
+                                break;
+                            }
+                            '\\' => {
+                                self.pos += 1;
+                                if let Some(c) = self.peek() {
+                                    word.text.push(c);
+                                    self.pos += 1;
+                                }
+                            }
+                            '$' | '`' => {
+                                self.expansion();
+                                word.expanded = true;
+                            }
+                            _ => {
+                                word.text.push(c);
+                                self.pos += 1;
+                            }
+                        }
+                    }
+                }
+                '$' | '`' => {
+                    self.expansion();
+                    word.expanded = true;
+                }
+                _ => {
+                    word.text.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+        return word;
+    }
+
+    /// Read an expansion, from its `$` or backquote. A command substitution is
+    /// read as a command list of its own.
+    fn expansion(&mut self) {
+        if self.peek() == Some('`') {
+            self.pos += 1;
+            let outer = std::mem::replace(&mut self.in_backquote, true);
+            self.list(Some('`'));
+            self.in_backquote = outer;
+            return;
+        }
+        self.pos += 1;
+        match self.peek() {
+            Some('(') if self.peek_at(1) == Some('(') => self.skip_arithmetic(),
+            Some('(') => {
+                self.pos += 1;
+                // A backquote in `$(…)` starts a substitution of its own, even
+                // inside backquotes.
+                let outer = std::mem::replace(&mut self.in_backquote, false);
+                self.list(Some(')'));
+                self.in_backquote = outer;
+            }
+            Some('{') => {
+                let mut depth = 0usize;
+                while let Some(c) = self.peek() {
+                    self.pos += 1;
+                    match c {
+                        '{' => depth += 1,
+                        '}' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                return;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Some(c) if c.is_ascii_alphanumeric() || c == '_' => {
+                while self
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+                {
+                    self.pos += 1;
+                }
+            }
+            Some('@' | '*' | '#' | '?' | '$' | '!' | '-') => self.pos += 1,
+            _ => {}
+        }
+    }
+}
+
+/// Whether a word only sets a variable, as in `LD_LIBRARY_PATH=lib`.
+fn is_assignment(word: &str) -> bool {
+    let Some((name, _)) = word.split_once('=') else {
+        return false;
+    };
+    let name = name.trim_end_matches('+');
+    return name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+}
+
+/// The name a command word is looked up by on `PATH`. `None` for a relative
+/// path, a path outside the `PATH` directories, or anything that is not a
+/// name.
+fn command_name(word: &str) -> Option<String> {
+    let name = match word.rsplit_once('/') {
+        Some((dir, name)) if PATH_DIRS.contains(&dir) => name,
+        Some(_) => return None,
+        None => word,
+    };
+    let valid =
+        name.chars().next().is_some_and(|c| {
+            c.is_ascii_alphanumeric() || c == '_' || c == '[' || c == ':' || c == '.'
+        }) && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+' | '[' | ':'));
+    return valid.then(|| String::from(name));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{ShellScript, scan_scripts};
+
+    fn commands(text: &str) -> Vec<String> {
+        return ShellScript::parse(String::from("test.sh"), text)
+            .commands
+            .into_iter()
+            .collect();
+    }
+
+    #[test]
+    fn finds_commands_in_lists_and_pipelines() {
+        let text = r#"#!/bin/sh
+# Start the helper. curl is only mentioned here.
+set -e
+LD_LIBRARY_PATH=lib FOO="a b" /usr/bin/luna-send -n 1 luna://x '{}' > /dev/null 2>&1
+if [ -x "$DIR/helper" ]; then
+    nohup nice -n 10 ./helper &
+elif ! grep -q foo /etc/bar; then
+    sqlite3 db.sqlite "SELECT 1;" | tee log.txt || logger failed
+fi
+VERSION=$(openssl version | cut -d' ' -f2)
+for f in a b c; do echo "$f"; done
+"#;
+        assert_eq!(
+            commands(text),
+            vec![
+                "cut",
+                "grep",
+                "logger",
+                "luna-send",
+                "nice",
+                "nohup",
+                "openssl",
+                "sh",
+                "sqlite3",
+                "tee",
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_functions_cases_and_heredocs() {
+        let text = r#"
+start() {
+    busybox wget -O - "$URL"
+}
+function stop {
+    killall helper
+}
+case "$1" in
+    start|restart) start ;;
+    stop)
+        stop
+        ;;
+    *) usage; exit 1 ;;
+esac
+cat <<-EOF > conf
+	curl is not run here
+	EOF
+/opt/bin/tool --flag
+`which dropbear` \
+    -p 22
+"#;
+        assert_eq!(
+            commands(text),
+            vec!["busybox", "cat", "killall", "usage", "wget", "which"]
+        );
+    }
+
+    /// A backquote inside `$(…)` inside backquotes once stopped the lexer
+    /// without moving on, and it spun there.
+    #[test]
+    fn ends_on_backquotes_in_nested_substitutions() {
+        assert_eq!(commands("echo `ls $(`\n"), vec!["ls"]);
+        assert_eq!(commands("echo `ls $(wd`)`\n"), vec!["ls"]);
+    }
+
+    #[test]
+    fn reads_only_shell_scripts_for_commands() {
+        let script = ShellScript::parse(
+            String::from("bin/tool.py"),
+            "#!/usr/bin/env python3\nimport os\n",
+        );
+        assert_eq!(script.interpreter.as_deref(), Some("python3"));
+        assert_eq!(
+            script.commands.into_iter().collect::<Vec<_>>(),
+            vec!["python3"]
+        );
+    }
+
+    #[test]
+    fn leaves_out_the_packages_own_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("bin")).unwrap();
+        fs::write(dir.path().join("bin/helper"), b"\x7fELF").unwrap();
+        fs::write(
+            dir.path().join("start.sh"),
+            "helper --daemon\nlogger started\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("README"), "helper\n").unwrap();
+
+        let scripts = scan_scripts(dir.path());
+
+        assert_eq!(scripts.len(), 1);
+        assert_eq!(scripts[0].path, "start.sh");
+        assert_eq!(scripts[0].interpreter, None);
+        assert_eq!(
+            scripts[0].commands.iter().collect::<Vec<_>>(),
+            vec!["logger"]
+        );
+    }
+}
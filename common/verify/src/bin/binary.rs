@@ -1,6 +1,6 @@
 use std::collections::HashSet;
 
-use bin_lib::{BinaryInfo, LibraryInfo};
+use bin_lib::{BinaryInfo, ElfArch, LibraryInfo};
 
 use crate::bin::BinVerifyResult;
 use crate::{Verify, VerifyResult};
@@ -8,8 +8,10 @@ use crate::{Verify, VerifyResult};
 impl Verify<BinVerifyResult> for BinaryInfo {
     fn verify<F>(&self, find_library: &F) -> BinVerifyResult
     where
-        F: Fn(&str) -> Option<LibraryInfo>,
+        F: Fn(&str, Option<ElfArch>) -> Option<LibraryInfo>,
     {
+        // Every library the binary loads is of its own class and machine.
+        let find_library = |name: &str| find_library(name, self.arch);
         let mut result = BinVerifyResult::new(self.name.clone());
         result.undefined_sym.extend(self.undefined.clone());
         result
@@ -1,10 +1,11 @@
-use std::collections::HashSet;
+use std::collections::{HashSet, VecDeque};
 
-use bin_lib::{BinaryInfo, LibraryInfo, LibraryPriority};
+use bin_lib::{BinaryInfo, ElfArch, LibraryInfo, LibraryPriority};
 use ipk_lib::Component;
 
 use crate::bin::binary::recursive_resolve_symbols;
-use crate::ipk::{ComponentBinVerifyResult, ComponentVerifyResult};
+use crate::ipk::scope::{ScopedLibrary, symbol_conflicts, version_conflicts};
+use crate::ipk::{ComponentBinVerifyResult, ComponentVerifyResult, MissingDlopen};
 use crate::{Verify, VerifyResult, bin::BinVerifyResult};
 
 trait ComponentImpl {
@@ -19,6 +20,18 @@ trait ComponentImpl {
     fn resolve_in_global_scope<F>(&self, result: &mut BinVerifyResult, find_library: &F)
     where
         F: Fn(&str) -> Option<LibraryInfo>;
+
+    fn global_scope<F>(&self, find_library: &F) -> Vec<ScopedLibrary>
+    where
+        F: Fn(&str) -> Option<LibraryInfo>;
+
+    fn verify_loaded_lib<F>(&self, lib: &LibraryInfo, find_library: &F) -> ComponentBinVerifyResult
+    where
+        F: Fn(&str) -> Option<LibraryInfo>;
+
+    fn missing_dlopen<F>(&self, exe: &BinaryInfo, find_library: &F) -> Vec<MissingDlopen>
+    where
+        F: Fn(&str) -> Option<LibraryInfo>;
 }
 
 impl VerifyResult for ComponentVerifyResult {
@@ -70,7 +83,8 @@ impl<T> ComponentImpl for Component<T> {
     where
         F: Fn(&str) -> Option<LibraryInfo>,
     {
-        return bin.verify(&|name| self.resolve_lib(name, find_library));
+        // `find_library` already looks for the executable's class and machine.
+        return bin.verify(&|name, _| self.resolve_lib(name, find_library));
     }
 
     /// Strike off undefined symbols that are satisfied by the executable's
@@ -84,7 +98,8 @@ impl<T> ComponentImpl for Component<T> {
     /// example a `libEGL.so.1` shim whose `gl*` imports are provided by the
     /// sibling `libGLESv2.so.2`. Verifying each library only against its own
     /// `DT_NEEDED` chain misses this and produces false "undefined symbol"
-    /// reports, so resolve whatever is left against the global scope.
+    /// reports, so resolve whatever is left against the global scope, starting
+    /// with the executable's own exports as the loader does.
     fn resolve_in_global_scope<F>(&self, result: &mut BinVerifyResult, find_library: &F)
     where
         F: Fn(&str) -> Option<LibraryInfo>,
@@ -92,6 +107,14 @@ impl<T> ComponentImpl for Component<T> {
         let Some(exe) = &self.exe else {
             return;
         };
+        // The executable comes first in the scope. A plugin or bundled library
+        // may import what an `-rdynamic` executable exports.
+        result
+            .undefined_sym
+            .retain(|symbol| !exe.has_symbol(symbol));
+        result
+            .undefined_sym_lazy
+            .retain(|symbol| !exe.has_symbol(symbol));
         let resolver = |name: &str| self.resolve_lib(name, find_library);
         let mut visited: HashSet<String> = HashSet::new();
         for needed in &exe.needed {
@@ -113,12 +136,109 @@ impl<T> ComponentImpl for Component<T> {
             );
         }
     }
+
+    /// The libraries in the executable's global scope, in the order the loader
+    /// adds them: a breadth-first walk of `DT_NEEDED` from the executable. A
+    /// library that two names resolve to is loaded once, at its first place.
+    fn global_scope<F>(&self, find_library: &F) -> Vec<ScopedLibrary>
+    where
+        F: Fn(&str) -> Option<LibraryInfo>,
+    {
+        let Some(exe) = &self.exe else {
+            return Vec::new();
+        };
+        let mut scope: Vec<ScopedLibrary> = Vec::new();
+        let mut visited: HashSet<String> = HashSet::new();
+        let mut queue: VecDeque<(String, Vec<String>)> = exe
+            .needed
+            .iter()
+            .map(|needed| (needed.clone(), vec![exe.name.clone()]))
+            .collect();
+        while let Some((needed, mut path)) = queue.pop_front() {
+            if !visited.insert(needed.clone()) {
+                continue;
+            }
+            let Some(lib) = self.resolve_lib(&needed, find_library) else {
+                continue;
+            };
+            if scope.iter().any(|loaded| loaded.lib.name == lib.name) {
+                continue;
+            }
+            path.push(needed);
+            queue.extend(lib.needed.iter().map(|dep| (dep.clone(), path.clone())));
+            scope.push(ScopedLibrary { lib, path });
+        }
+        return scope;
+    }
+
+    /// Verify a bundled library or plugin as the process would load it: its
+    /// own `DT_NEEDED` chain first, then whatever the executable's global
+    /// scope provides.
+    fn verify_loaded_lib<F>(&self, lib: &LibraryInfo, find_library: &F) -> ComponentBinVerifyResult
+    where
+        F: Fn(&str) -> Option<LibraryInfo>,
+    {
+        let mut verify_result = self.verify_bin(
+            &BinaryInfo {
+                name: lib.name.clone(),
+                rpath: Vec::new(),
+                needed: lib.needed.clone(),
+                undefined: lib.undefined.clone(),
+                undefined_lazy: lib.undefined_lazy.clone(),
+                symbols: Vec::new(),
+                arch: None,
+                arm: lib.arm,
+                dlopen: Vec::new(),
+            },
+            find_library,
+        );
+        // A bundled library's imports may be provided by a sibling
+        // library co-loaded by the executable, not just by its own
+        // DT_NEEDED chain. Resolve the leftovers against that scope.
+        self.resolve_in_global_scope(&mut verify_result, find_library);
+        return verify_result.into();
+    }
+
+    /// The names the executable, a bundled library or a plugin may pass to
+    /// `dlopen` that resolve neither to a bundled library or plugin nor to the
+    /// firmware.
+    fn missing_dlopen<F>(&self, exe: &BinaryInfo, find_library: &F) -> Vec<MissingDlopen>
+    where
+        F: Fn(&str) -> Option<LibraryInfo>,
+    {
+        let referrers = std::iter::once((&exe.name, &exe.dlopen)).chain(
+            self.libs
+                .iter()
+                .chain(&self.plugins)
+                .map(|lib| (&lib.name, &lib.dlopen)),
+        );
+        let mut missing: Vec<MissingDlopen> = Vec::new();
+        for (referrer, names) in referrers {
+            for name in names {
+                if name == referrer
+                    || self.plugins.iter().any(|plugin| plugin.has_name(name))
+                    || self.resolve_lib(name, find_library).is_some()
+                {
+                    continue;
+                }
+                match missing.iter_mut().find(|m| &m.name == name) {
+                    Some(entry) => entry.referenced_by.push(referrer.clone()),
+                    None => missing.push(MissingDlopen {
+                        name: name.clone(),
+                        referenced_by: vec![referrer.clone()],
+                    }),
+                }
+            }
+        }
+        missing.sort_by(|a, b| a.name.cmp(&b.name));
+        return missing;
+    }
 }
 
 impl<T> Verify<ComponentVerifyResult> for Component<T> {
     fn verify<F>(&self, find_library: &F) -> ComponentVerifyResult
     where
-        F: Fn(&str) -> Option<LibraryInfo>,
+        F: Fn(&str, Option<ElfArch>) -> Option<LibraryInfo>,
     {
         let Some(exe) = &self.exe else {
             return ComponentVerifyResult {
@@ -129,9 +249,17 @@ impl<T> Verify<ComponentVerifyResult> for Component<T> {
                 libs: Vec::new(),
                 detection: None,
                 bundled: Vec::new(),
+                symbol_conflicts: Vec::new(),
+                version_conflicts: Vec::new(),
+                plugins: Vec::new(),
+                missing_dlopen: Vec::new(),
             };
         };
+        // The whole process is of the executable's class and machine, its
+        // libraries and plugins included.
+        let find_library = &|name: &str| find_library(name, exe.arch);
         let bin = self.verify_bin(exe, find_library);
+        let scope = self.global_scope(find_library);
         let mut libs: Vec<(bool, ComponentBinVerifyResult)> = self
             .libs
             .iter()
@@ -146,23 +274,14 @@ impl<T> Verify<ComponentVerifyResult> for Component<T> {
                         },
                     );
                 }
-                let mut verify_result = self.verify_bin(
-                    &BinaryInfo {
-                        name: lib.name.clone(),
-                        rpath: Vec::new(),
-                        needed: lib.needed.clone(),
-                        undefined: lib.undefined.clone(),
-                        undefined_lazy: lib.undefined_lazy.clone(),
-                    },
-                    find_library,
-                );
-                // A bundled library's imports may be provided by a sibling
-                // library co-loaded by the executable, not just by its own
-                // DT_NEEDED chain. Resolve the leftovers against that scope.
-                self.resolve_in_global_scope(&mut verify_result, find_library);
-                (required, verify_result.into())
+                (required, self.verify_loaded_lib(lib, find_library))
             })
             .collect();
+        let plugins = self
+            .plugins
+            .iter()
+            .map(|plugin| self.verify_loaded_lib(plugin, find_library))
+            .collect();
         libs.sort_by(|(required_a, lib_a), (required_b, lib_b)| {
             let required_cmp = required_a.cmp(required_b);
             if !required_cmp.is_eq() {
@@ -177,6 +296,10 @@ impl<T> Verify<ComponentVerifyResult> for Component<T> {
             // Filled in by Package::verify_for_firmware for non-native components.
             detection: None,
             bundled: Vec::new(),
+            symbol_conflicts: symbol_conflicts(exe, &scope),
+            version_conflicts: version_conflicts(&scope),
+            plugins,
+            missing_dlopen: self.missing_dlopen(exe, find_library),
         };
     }
 }
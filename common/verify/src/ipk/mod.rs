@@ -1,12 +1,15 @@
-use bin_lib::{BundledArtifact, LibraryInfo};
-use fw_lib::WebEngine;
-use ipk_lib::{AppInfo, Component, Package, ServiceInfo};
+use std::collections::{BTreeMap, BTreeSet};
+
+use bin_lib::{ArmAttributes, BundledArtifact, ElfArch, LibraryInfo};
+use fw_lib::{Firmware, LunaRegistry, WebEngine};
+use ipk_lib::{AppInfo, Component, Package, ServiceInfo, ShellScript};
 use semver::Version;
-use webdetect_lib::{EsLevel, ServiceRuntimeDetection, WebAppDetection};
+use webdetect_lib::{EsLevel, LunaCall, ServiceRuntimeDetection, WebAppDetection};
 
 use crate::{Verify, VerifyResult, bin::BinVerifyResult};
 
 pub mod component;
+mod scope;
 
 #[derive(Debug)]
 pub struct PackageVerifyResult {
@@ -26,6 +29,126 @@ pub struct ComponentVerifyResult {
     /// the firmware's libraries like a native component. Supplementary — these
     /// results never gate the package verdict. Empty for everything else.
     pub bundled: Vec<ComponentVerifyResult>,
+    /// Symbols that more than one object in the executable's global scope
+    /// defines. Advisory — the process loads either way, but the first
+    /// definition wins for every object, firmware libraries included.
+    pub symbol_conflicts: Vec<SymbolConflict>,
+    /// Libraries the process loads in more than one version. Advisory, like
+    /// `symbol_conflicts`.
+    pub version_conflicts: Vec<VersionConflict>,
+    /// Each of the component's plugins, verified like a bundled library. A
+    /// framework loads them with `dlopen` and can carry on without one, so
+    /// they never gate the verdict.
+    pub plugins: Vec<ComponentBinVerifyResult>,
+    /// Shared objects the component may `dlopen` that exist neither in the
+    /// package nor on the firmware. Advisory, like `plugins`.
+    pub missing_dlopen: Vec<MissingDlopen>,
+}
+
+/// A name found in the read-only data of the executable or a bundled library
+/// that looks like one passed to `dlopen`, but resolves nowhere.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingDlopen {
+    pub name: String,
+    /// The objects that carry the name, in the order they were checked.
+    pub referenced_by: Vec<String>,
+}
+
+/// A command a package's shell script runs that is not on the firmware's
+/// `PATH`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingCommand {
+    pub name: String,
+    /// The scripts that run it, by path.
+    pub scripts: Vec<String>,
+}
+
+/// A Luna call a package makes that a firmware has no service or method for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingLunaCall {
+    pub call: LunaCall,
+    /// Whether the whole service is missing, rather than only the method.
+    pub service_missing: bool,
+}
+
+/// A group in an app's `requiredPermissions` that a firmware does not define.
+/// The app is denied it at run time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownPermission {
+    pub name: String,
+    /// The newest older firmware among those checked that defines it, when one
+    /// does. The name is legacy then: dropped or renamed since.
+    pub last_defined: Option<Version>,
+}
+
+/// A Luna call outside every permission group the app asks for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UngrantedLunaCall {
+    pub call: LunaCall,
+    /// The groups that grant it.
+    pub groups: Vec<String>,
+}
+
+/// A binary built for a CPU the firmware's libraries do not need, which may
+/// stop with `SIGILL` even when every symbol resolves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CpuMismatch {
+    pub binary: String,
+    /// What it needs beyond the firmware, one sentence each. See
+    /// [`ArmAttributes::exceeds`].
+    pub reasons: Vec<String>,
+}
+
+/// A symbol defined by more than one object in a process's global scope.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolConflict {
+    /// The symbol name, without a version.
+    pub symbol: String,
+    /// The first object in load order to define it. Every lookup binds here.
+    pub winner: ScopeObject,
+    /// The later objects whose definition is never used.
+    pub losers: Vec<ScopeObject>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopeObject {
+    pub name: String,
+    /// Whether the object is a firmware library rather than one the package
+    /// bundles.
+    pub firmware: bool,
+}
+
+/// One library loaded under more than one soname in the same process, such as
+/// a bundled `libssl.so.1.0.0` next to the firmware's `libssl.so.1.1`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionConflict {
+    /// The soname without its version, e.g. `libssl`.
+    pub family: String,
+    /// Each copy, in load order.
+    pub copies: Vec<LoadedCopy>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadedCopy {
+    pub object: ScopeObject,
+    /// The `DT_NEEDED` chain that first loads it, from the executable down to
+    /// the name this copy was loaded as.
+    pub path: Vec<String>,
+}
+
+impl SymbolConflict {
+    /// Whether a bundled definition takes the place of a firmware one. The
+    /// firmware's own code then calls into the package, which is what crashes
+    /// system libraries.
+    pub fn interposes_firmware(&self) -> bool {
+        return !self.winner.firmware && self.losers.iter().any(|loser| loser.firmware);
+    }
+
+    /// Whether the package has a part in it at all. Duplicates among firmware
+    /// libraries are the firmware's business.
+    pub fn involves_package(&self) -> bool {
+        return !self.winner.firmware || self.losers.iter().any(|loser| !loser.firmware);
+    }
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -120,7 +243,7 @@ impl DetectionResult {
 impl Verify<PackageVerifyResult> for Package {
     fn verify<F>(&self, find_library: &F) -> PackageVerifyResult
     where
-        F: Fn(&str) -> Option<LibraryInfo>,
+        F: Fn(&str, Option<ElfArch>) -> Option<LibraryInfo>,
     {
         return PackageVerifyResult {
             app: self.app.verify(find_library),
@@ -150,7 +273,7 @@ pub trait VerifyForFirmware {
         engine: Option<&WebEngine>,
     ) -> PackageVerifyResult
     where
-        F: Fn(&str) -> Option<LibraryInfo>;
+        F: Fn(&str, Option<ElfArch>) -> Option<LibraryInfo>;
 }
 
 impl VerifyForFirmware for Package {
@@ -162,7 +285,7 @@ impl VerifyForFirmware for Package {
         engine: Option<&WebEngine>,
     ) -> PackageVerifyResult
     where
-        F: Fn(&str) -> Option<LibraryInfo>,
+        F: Fn(&str, Option<ElfArch>) -> Option<LibraryInfo>,
     {
         let mut result = self.verify(find_library);
         result.app.detection = web_detection(&self.app, engine);
@@ -269,6 +392,145 @@ fn service_verdict(es_level: Option<EsLevel>, node: Option<&Version>, verb: &str
     }
 }
 
+/// The commands `scripts` run that are not among a firmware's `executables`,
+/// sorted by name.
+pub fn missing_commands(
+    scripts: &[ShellScript],
+    executables: &BTreeSet<String>,
+) -> Vec<MissingCommand> {
+    let mut missing: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for script in scripts {
+        for name in script.commands.difference(executables) {
+            missing
+                .entry(name.as_str())
+                .or_default()
+                .push(script.path.clone());
+        }
+    }
+    return missing
+        .into_iter()
+        .map(|(name, scripts)| MissingCommand {
+            name: String::from(name),
+            scripts,
+        })
+        .collect();
+}
+
+/// The `calls` a firmware's Luna `registry` cannot answer, in the order
+/// given. A method is only judged when the registry lists the service's
+/// methods. Calls to `own`, the names the package itself registers on the
+/// bus, are left out: the package brings those services along.
+pub fn missing_luna_calls(
+    calls: &[LunaCall],
+    own: &[&str],
+    registry: &LunaRegistry,
+) -> Vec<MissingLunaCall> {
+    return calls
+        .iter()
+        .filter(|call| !own.contains(&call.service.as_str()))
+        .filter_map(|call| {
+            let service_missing = !registry.has_service(&call.service);
+            let method_missing = call
+                .method
+                .as_ref()
+                .is_some_and(|method| registry.has_method(&call.service, method) == Some(false));
+            return (service_missing || method_missing).then(|| MissingLunaCall {
+                call: call.clone(),
+                service_missing,
+            });
+        })
+        .collect();
+}
+
+/// The `required` permission groups `firmware` does not define. Each is
+/// checked against the older of `firmwares` to tell a legacy name from one
+/// none of them defines. Firmwares outside `firmwares` are not looked at.
+/// Empty for a firmware without recorded groups.
+pub fn unknown_permissions(
+    required: &[String],
+    firmware: &Firmware,
+    firmwares: &[&Firmware],
+) -> Vec<UnknownPermission> {
+    let Some(groups) = firmware.luna().map(|luna| &luna.groups) else {
+        return Vec::new();
+    };
+    if groups.is_empty() {
+        return Vec::new();
+    }
+    return required
+        .iter()
+        .filter(|name| !groups.contains_key(*name))
+        .map(|name| UnknownPermission {
+            name: name.clone(),
+            last_defined: firmwares
+                .iter()
+                .filter(|other| other.info.release < firmware.info.release)
+                .filter(|other| {
+                    other
+                        .luna()
+                        .is_some_and(|luna| luna.groups.contains_key(name))
+                })
+                .map(|other| other.info.release.clone())
+                .max(),
+        })
+        .collect();
+}
+
+/// The `calls` that no group in `required` grants on a firmware with this Luna
+/// `registry`. Only calls some group grants are judged. An app that asks for
+/// no groups is let through as a legacy app, so it gets none.
+pub fn ungranted_luna_calls(
+    calls: &[LunaCall],
+    required: &[String],
+    registry: &LunaRegistry,
+) -> Vec<UngrantedLunaCall> {
+    if required.is_empty() {
+        return Vec::new();
+    }
+    return calls
+        .iter()
+        .filter_map(|call| {
+            let groups = registry.groups_granting(&call.service, call.method.as_deref()?);
+            if groups.is_empty()
+                || groups
+                    .iter()
+                    .any(|group| required.iter().any(|r| r == group))
+            {
+                return None;
+            }
+            return Some(UngrantedLunaCall {
+                call: call.clone(),
+                groups: groups.into_iter().map(String::from).collect(),
+            });
+        })
+        .collect();
+}
+
+/// The binaries of `component` built for more than the firmware's CPU
+/// `baseline`: the executable, then its libraries and plugins. Binaries
+/// without ARM attributes are not judged.
+pub fn cpu_mismatches<T>(component: &Component<T>, baseline: &ArmAttributes) -> Vec<CpuMismatch> {
+    let exe = component
+        .exe
+        .iter()
+        .map(|exe| (exe.name.as_str(), exe.arm.as_ref()));
+    let libs = component
+        .libs
+        .iter()
+        .chain(&component.plugins)
+        .map(|lib| (lib.name.as_str(), lib.arm.as_ref()));
+    return exe
+        .chain(libs)
+        .filter_map(|(name, arm)| {
+            let reasons = arm?.exceeds(baseline);
+            return (!reasons.is_empty()).then(|| CpuMismatch {
+                binary: String::from(name),
+                reasons,
+            });
+        })
+        .collect();
+}
+
 /// The highest ES level a firmware's web engine supports.
 pub fn engine_max_es(engine: &WebEngine) -> EsLevel {
     match engine {
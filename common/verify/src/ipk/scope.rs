@@ -0,0 +1,126 @@
+//! What the executable's global scope says about the process as a whole.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::iter;
+
+use bin_lib::{BinaryInfo, LibraryInfo, LibraryPriority};
+
+use crate::ipk::{LoadedCopy, ScopeObject, SymbolConflict, VersionConflict};
+
+/// A library in the global scope, with the `DT_NEEDED` chain that loaded it.
+pub(crate) struct ScopedLibrary {
+    pub(crate) lib: LibraryInfo,
+    pub(crate) path: Vec<String>,
+}
+
+/// Every symbol more than one object in the global scope defines: `exe`, then
+/// `scope`, which must be in load order. The first definer of a symbol is the
+/// one the loader binds.
+///
+/// Symbols are compared without their version. The loader picks the first
+/// object that has the name, and a reference from an unversioned object binds
+/// to whatever version that is.
+pub(crate) fn symbol_conflicts(exe: &BinaryInfo, scope: &[ScopedLibrary]) -> Vec<SymbolConflict> {
+    let objects: Vec<(ScopeObject, &[String])> = iter::once((
+        ScopeObject {
+            name: exe.name.clone(),
+            firmware: false,
+        },
+        exe.symbols.as_slice(),
+    ))
+    .chain(
+        scope
+            .iter()
+            .map(|scoped| (scope_object(&scoped.lib), scoped.lib.symbols.as_slice())),
+    )
+    .collect();
+    let mut definers: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for (index, (_, symbols)) in objects.iter().enumerate() {
+        let names: BTreeSet<&str> = symbols.iter().map(|s| unversioned(s)).collect();
+        for name in names {
+            definers.entry(name).or_default().push(index);
+        }
+    }
+    return definers
+        .into_iter()
+        .filter(|(_, definers)| definers.len() > 1)
+        .map(|(symbol, definers)| SymbolConflict {
+            symbol: String::from(symbol),
+            winner: objects[definers[0]].0.clone(),
+            losers: definers[1..]
+                .iter()
+                .map(|index| objects[*index].0.clone())
+                .collect(),
+        })
+        .collect();
+}
+
+/// Every library `scope` holds under more than one soname. Each copy has its
+/// own symbols and state, and whichever loads first answers every lookup, so
+/// objects built against the other copy end up calling an incompatible one.
+pub(crate) fn version_conflicts(scope: &[ScopedLibrary]) -> Vec<VersionConflict> {
+    let mut families: BTreeMap<&str, Vec<&ScopedLibrary>> = BTreeMap::new();
+    for scoped in scope {
+        families
+            .entry(library_family(&scoped.lib.name))
+            .or_default()
+            .push(scoped);
+    }
+    return families
+        .into_iter()
+        .filter(|(_, copies)| copies.len() > 1)
+        .map(|(family, copies)| VersionConflict {
+            family: String::from(family),
+            copies: copies
+                .into_iter()
+                .map(|scoped| LoadedCopy {
+                    object: scope_object(&scoped.lib),
+                    path: scoped.path.clone(),
+                })
+                .collect(),
+        })
+        .collect();
+}
+
+/// The library a soname names a version of: `libssl` for `libssl.so.1.1`, and
+/// `libgstreamer` for `libgstreamer-1.0.so.0`.
+fn library_family(soname: &str) -> &str {
+    let stem = soname.split_once(".so").map_or(soname, |(stem, _)| stem);
+    return match stem.rsplit_once('-') {
+        Some((family, version))
+            if !family.is_empty()
+                && version.starts_with(|c: char| c.is_ascii_digit())
+                && version.chars().all(|c| c.is_ascii_digit() || c == '.') =>
+        {
+            family
+        }
+        _ => stem,
+    };
+}
+
+fn unversioned(symbol: &str) -> &str {
+    return symbol.split_once('@').map_or(symbol, |(name, _)| name);
+}
+
+fn scope_object(lib: &LibraryInfo) -> ScopeObject {
+    return ScopeObject {
+        name: lib.name.clone(),
+        firmware: lib.priority == LibraryPriority::System,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::library_family;
+
+    #[test]
+    fn strips_the_version_from_a_soname() {
+        assert_eq!(library_family("libssl.so.1.0.0"), "libssl");
+        assert_eq!(library_family("libssl.so.1.1"), "libssl");
+        assert_eq!(library_family("libstdc++.so.6"), "libstdc++");
+        assert_eq!(library_family("libgstreamer-1.0.so.0"), "libgstreamer");
+        assert_eq!(library_family("libpulsecommon-15.0.so"), "libpulsecommon");
+        assert_eq!(library_family("ld-linux-armhf.so.3"), "ld-linux-armhf");
+        assert_eq!(library_family("libavcodec.so"), "libavcodec");
+    }
+}
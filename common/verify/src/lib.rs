@@ -1,4 +1,4 @@
-use bin_lib::LibraryInfo;
+use bin_lib::{ElfArch, LibraryInfo};
 
 #[cfg(feature = "bin")]
 pub mod bin;
@@ -6,9 +6,11 @@ pub mod bin;
 pub mod ipk;
 
 pub trait Verify<R> {
+    /// Verify against the libraries `find_library` finds by name, for a binary
+    /// of the given class and machine.
     fn verify<F>(&self, find_library: &F) -> R
     where
-        F: Fn(&str) -> Option<LibraryInfo>;
+        F: Fn(&str, Option<ElfArch>) -> Option<LibraryInfo>;
 }
 
 pub trait VerifyResult {
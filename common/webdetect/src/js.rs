@@ -20,7 +20,7 @@ pub(crate) const MAX_FILE_BYTES: u64 = 8 * 1024 * 1024;
 /// Stop after scanning this many JS files.
 const MAX_JS_FILES: usize = 400;
 /// Recursion depth cap for the directory walk.
-const MAX_DEPTH: usize = 12;
+pub(crate) const MAX_DEPTH: usize = 12;
 
 /// The result of analyzing a set of JS sources.
 // Every field describes an ECMAScript trait, so the shared prefix is the point.
@@ -3,17 +3,20 @@
 //! Given the unpacked directory of a non-native component, this crate reports
 //! which frontend framework a web app ships and what JavaScript syntax level
 //! its bundle requires ([`detect_web_app`]), and what Node.js runtime a service
-//! declares ([`detect_service_runtime`]). It is pure text/JSON analysis with no
+//! declares ([`detect_service_runtime`]). It also lists the Luna bus calls a
+//! component makes ([`find_luna_calls`]). It is pure text/JSON analysis with no
 //! ELF, ipk or firmware knowledge so it can be unit-tested in isolation.
 
 use semver::Version;
 
 mod eslevel;
 mod js;
+mod luna;
 mod service;
 mod web;
 
 pub use eslevel::{EsFeature, EsLevel};
+pub use luna::find_luna_calls;
 pub use service::detect_service_runtime;
 pub use web::detect_web_app;
 
@@ -26,6 +29,18 @@ pub struct ApiUse {
     pub level: EsLevel,
 }
 
+/// A `luna://` call found in a component's JS, HTML or scripts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LunaCall {
+    pub service: String,
+    /// The method path after the service, as in `getVolume` or
+    /// `master/getVolume`. `None` when the URI has none, or it is built at run
+    /// time.
+    pub method: Option<String>,
+    /// The files the call appears in, relative to the component directory.
+    pub files: Vec<String>,
+}
+
 /// What was detected about a web/frontend app.
 #[derive(Debug, Clone)]
 pub struct WebAppDetection {
@@ -0,0 +1,195 @@
+//! Luna bus calls a package makes, found as `luna://service/method` URIs.
+//!
+//! Apps reach system services through such URIs whichever way they call them —
+//! `webOS.service.request`, `PalmServiceBridge` or `luna-send` in a script — so
+//! a plain text scan of the JS, HTML and scripts finds them all. `palm://` is
+//! the older scheme for the same bus.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::LunaCall;
+use crate::js::{MAX_DEPTH, MAX_FILE_BYTES};
+
+/// Stop after scanning this many files.
+const MAX_FILES: usize = 2000;
+
+/// File extensions scanned. Extensionless files are scanned when they start
+/// with a `#!` line.
+const EXTENSIONS: [&str; 6] = ["js", "mjs", "cjs", "html", "htm", "sh"];
+
+/// The files each call appears in, by service and method.
+type Calls = BTreeMap<(String, Option<String>), Vec<String>>;
+
+/// A URI up to the first character that cannot be part of it. A URI built by
+/// concatenation stops where the literal does.
+static LUNA_URI: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b(?:luna|palm)://([A-Za-z0-9_.\-]+)(/[A-Za-z0-9_/\-]*)?").unwrap()
+});
+
+/// Every Luna call in the files under `dir`, once per service and method, with
+/// the files it appears in, relative to `dir`.
+pub fn find_luna_calls(dir: &Path) -> Vec<LunaCall> {
+    let mut calls: Calls = BTreeMap::new();
+    scan_files(dir, dir, 0, &mut 0, &mut calls);
+    return calls
+        .into_iter()
+        .map(|((service, method), files)| LunaCall {
+            service,
+            method,
+            files,
+        })
+        .collect();
+}
+
+/// The service and method of each Luna URI in `text`. The method is `None`
+/// when the URI has none or it is cut short by a concatenation, as in
+/// `'luna://com.webos.audio/' + method`. A service name cut short that way is
+/// left out.
+pub(crate) fn find_luna_uris(text: &str) -> Vec<(String, Option<String>)> {
+    return LUNA_URI
+        .captures_iter(text)
+        .filter_map(|caps| {
+            let service = &caps[1];
+            if service.ends_with('.') || service.ends_with('-') {
+                return None;
+            }
+            let method = caps
+                .get(2)
+                .map(|m| m.as_str())
+                .filter(|m| !m.ends_with('/'))
+                .map(|m| String::from(m.trim_start_matches('/')));
+            return Some((String::from(service), method));
+        })
+        .collect();
+}
+
+/// Scan each file under `dir` that may make calls, one at a time, into
+/// `calls`. `scanned` counts the files scanned so far.
+fn scan_files(root: &Path, dir: &Path, depth: usize, scanned: &mut usize, calls: &mut Calls) {
+    if depth > MAX_DEPTH || *scanned >= MAX_FILES {
+        return;
+    }
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<_> = entries.flatten().collect();
+    entries.sort_by_key(fs::DirEntry::file_name);
+    for entry in entries {
+        if *scanned >= MAX_FILES {
+            return;
+        }
+        let path = entry.path();
+        let Ok(ft) = entry.file_type() else { continue };
+        if ft.is_dir() {
+            scan_files(root, &path, depth + 1, scanned, calls);
+            continue;
+        }
+        if !ft.is_file() || entry.metadata().map_or(u64::MAX, |m| m.len()) > MAX_FILE_BYTES {
+            continue;
+        }
+        let wanted = match path.extension() {
+            Some(ext) => EXTENSIONS
+                .iter()
+                .any(|wanted| ext.eq_ignore_ascii_case(wanted)),
+            None => is_script(&path),
+        };
+        if !wanted {
+            continue;
+        }
+        let Ok(content) = fs::read(&path) else {
+            continue;
+        };
+        *scanned += 1;
+        let rel = path.strip_prefix(root).unwrap_or(&path);
+        let rel = rel
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        for (service, method) in find_luna_uris(&String::from_utf8_lossy(&content)) {
+            let found = calls.entry((service, method)).or_default();
+            if !found.contains(&rel) {
+                found.push(rel.clone());
+            }
+        }
+    }
+}
+
+/// Whether the file starts with a `#!` line. Only its first two bytes are
+/// read.
+fn is_script(path: &Path) -> bool {
+    let mut magic = [0u8; 2];
+    return File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .is_ok_and(|()| &magic == b"#!");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_uris_in_calls() {
+        let js = r#"
+            webOS.service.request("luna://com.webos.audio", { method: "getVolume" });
+            new PalmServiceBridge().call("luna://com.webos.service.tv.systemproperty/getSystemInfo", "{}");
+            request('luna://com.webos.settingsservice/' + method);
+            request("luna://com.webos." + name + "/getStatus");
+            request("palm://com.palm.systemservice/time/getSystemTime");
+        "#;
+        assert_eq!(
+            find_luna_uris(js),
+            vec![
+                (String::from("com.webos.audio"), None),
+                (
+                    String::from("com.webos.service.tv.systemproperty"),
+                    Some(String::from("getSystemInfo"))
+                ),
+                (String::from("com.webos.settingsservice"), None),
+                (
+                    String::from("com.palm.systemservice"),
+                    Some(String::from("time/getSystemTime"))
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn scans_js_html_and_scripts() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("js")).unwrap();
+        fs::write(
+            root.join("js/app.js"),
+            "call('luna://com.webos.audio/getVolume');",
+        )
+        .unwrap();
+        fs::write(
+            root.join("index.html"),
+            "<script>call('luna://com.webos.audio/getVolume')</script>",
+        )
+        .unwrap();
+        fs::write(
+            root.join("start"),
+            "#!/bin/sh\nluna-send -n 1 luna://com.webos.notification/createToast '{}'\n",
+        )
+        .unwrap();
+        fs::write(root.join("notes.txt"), "luna://com.example.ignored/x").unwrap();
+
+        let calls = find_luna_calls(root);
+
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].service, "com.webos.audio");
+        assert_eq!(calls[0].method.as_deref(), Some("getVolume"));
+        assert_eq!(calls[0].files, vec!["index.html", "js/app.js"]);
+        assert_eq!(calls[1].service, "com.webos.notification");
+        assert_eq!(calls[1].files, vec!["start"]);
+    }
+}
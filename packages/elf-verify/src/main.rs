@@ -1,12 +1,14 @@
 use std::fs::File;
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
 use semver::VersionReq;
 
 use bin_lib::BinaryInfo;
 use cli_lib::{ExitCode, file_label};
-use fw_lib::Firmware;
+use fw_lib::rootfs::FirmwareId;
+use fw_lib::{DeviceFilter, Firmware, Platform, group_by_device};
 use verify_lib::Verify;
 
 #[derive(Parser, Debug)]
@@ -19,15 +21,85 @@ struct Args {
     skip_rpath: bool,
     #[arg(short = 'r', long)]
     fw_releases: Option<VersionReq>,
+    #[arg(
+        long = "data-dir",
+        value_name = "DIR",
+        help = "Also read firmware data from DIR (repeatable, first wins)"
+    )]
+    data_dirs: Vec<PathBuf>,
+    #[arg(
+        long,
+        value_name = "DIR",
+        conflicts_with = "data_dirs",
+        help = "Check against this rootfs instead of firmware data (repeatable)"
+    )]
+    rootfs: Vec<PathBuf>,
+    #[arg(
+        long,
+        value_name = "VERSION",
+        requires = "rootfs",
+        help = "The firmware version of a single --rootfs, where it does not say"
+    )]
+    fw_version: Option<String>,
+    #[arg(
+        long,
+        value_name = "OTA_ID",
+        requires = "rootfs",
+        help = "The OTA ID of a single --rootfs, such as HE_DTV_W21P_AFADATAA, where it does not say"
+    )]
+    ota_id: Option<String>,
+    #[arg(
+        long,
+        value_name = "SOC",
+        help = "Only firmwares for this SoC, such as k8lp"
+    )]
+    soc: Option<String>,
+    #[arg(
+        long,
+        value_name = "BOARD",
+        help = "Only firmwares for this board, such as W21P"
+    )]
+    board: Option<String>,
+    #[arg(
+        long,
+        help = "Only firmwares for this platform: tv, signage or projector"
+    )]
+    platform: Option<Platform>,
     #[arg(short, long, action = clap::ArgAction::Count)]
     debug: u8,
 }
 
 fn main() {
     let args = Args::parse();
-    let firmwares: Vec<Firmware> = match Firmware::list(Firmware::data_path()) {
+    let device = DeviceFilter {
+        soc: args.soc.clone(),
+        board: args.board.clone(),
+        platform: args.platform,
+    };
+    if args.rootfs.len() > 1 && (args.fw_version.is_some() || args.ota_id.is_some()) {
+        Args::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--fw-version and --ota-id name a single --rootfs",
+            )
+            .exit();
+    }
+    let loaded = if args.rootfs.is_empty() {
+        Firmware::list_all(&Firmware::data_dirs(&args.data_dirs))
+    } else {
+        let id = FirmwareId {
+            version: args.fw_version.clone(),
+            ota_id: args.ota_id.clone(),
+        };
+        args.rootfs
+            .iter()
+            .map(|rootfs| Firmware::from_rootfs(rootfs, &id))
+            .collect()
+    };
+    let firmwares: Vec<Firmware> = match loaded {
         Ok(firmwares) => firmwares
             .into_iter()
+            .filter(|fw| device.matches(&fw.info))
             .filter(|fw| {
                 if let Some(fw_releases) = &args.fw_releases {
                     return fw_releases.matches(&fw.info.release);
@@ -44,6 +116,13 @@ fn main() {
         eprintln!("No firmware found");
         ExitCode::NoFirmware.exit();
     }
+    // Name the data directory of each firmware once there is more than one.
+    let from_several = firmwares
+        .iter()
+        .any(|fw| fw.data_dir != firmwares[0].data_dir);
+    // Firmwares for different devices are listed apart, under the device.
+    let groups = group_by_device(&firmwares, |fw| &fw.info);
+    let several = groups.len() > 1;
     let mut all_good = true;
     let mut bad_input = false;
     for executable in args.executables {
@@ -63,21 +142,37 @@ fn main() {
         };
         info.rpath.extend(args.lib_paths.clone());
         let mut all_ok = true;
-        for firmware in &firmwares {
-            let result = info.verify(&|name| firmware.find_library(name));
-            println!("Verify result for firmware {}:", firmware.info);
-            for lib in result.missing_lib {
-                println!("Missing library: {lib}");
-                all_ok = false;
-            }
-            for sym in result.undefined_sym {
-                println!("Missing symbol: {sym}");
-                all_ok = false;
+        for (label, group) in &groups {
+            if several {
+                println!(
+                    "Firmwares for {}:",
+                    label.as_deref().unwrap_or("other devices")
+                );
             }
-            // The loader resolves these on the first call, so the binary still
-            // loads. Report them, but do not fail.
-            for sym in result.undefined_sym_lazy {
-                println!("Warning: missing symbol {sym} is bound lazily");
+            for firmware in group {
+                let result = info.verify(&|name, arch| firmware.find_library_for(name, arch));
+                if from_several {
+                    println!(
+                        "Verify result for firmware {} (from {}):",
+                        firmware.info,
+                        firmware.data_dir.to_string_lossy()
+                    );
+                } else {
+                    println!("Verify result for firmware {}:", firmware.info);
+                }
+                for lib in result.missing_lib {
+                    println!("Missing library: {lib}");
+                    all_ok = false;
+                }
+                for sym in result.undefined_sym {
+                    println!("Missing symbol: {sym}");
+                    all_ok = false;
+                }
+                // The loader resolves these on the first call, so the binary
+                // still loads. Report them, but do not fail.
+                for sym in result.undefined_sym_lazy {
+                    println!("Warning: missing symbol {sym} is bound lazily");
+                }
             }
         }
         if all_ok {
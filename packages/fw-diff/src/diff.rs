@@ -0,0 +1,313 @@
+//! What changed in the ABI between two firmwares.
+//!
+//! Libraries are matched by soname. A library whose soname changed but whose
+//! name before `.so` did not, such as `libfoo.so.1` becoming `libfoo.so.2`, is
+//! a soname bump. Its symbols are compared across the bump.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use bin_lib::LibraryInfo;
+use fw_lib::{Firmware, PackageEntry};
+use serde::Serialize;
+
+#[derive(Debug, Default, Serialize, PartialEq, Eq)]
+pub struct FirmwareDiff {
+    pub old: String,
+    pub new: String,
+    pub libraries_added: Vec<String>,
+    pub libraries_removed: Vec<String>,
+    pub soname_bumps: Vec<SonameBump>,
+    pub libraries_changed: Vec<LibraryDiff>,
+    pub packages: Vec<PackageChange>,
+}
+
+/// A library whose soname changed, by the part of its name up to `.so`.
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct SonameBump {
+    pub family: String,
+    pub old: Vec<String>,
+    pub new: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, PartialEq, Eq)]
+pub struct LibraryDiff {
+    /// The soname in the new firmware.
+    pub name: String,
+    /// The soname in the old firmware, when it was bumped.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_name: Option<String>,
+    pub symbols_removed: Vec<String>,
+    pub symbols_added: Vec<String>,
+    /// Symbol version nodes, such as `GLIBC_2.28`, that only the new firmware
+    /// defines.
+    pub version_nodes_added: Vec<String>,
+}
+
+/// An OS package that was added, removed, or changed version. A missing
+/// version means the package is not in that firmware.
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct PackageChange {
+    pub name: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+impl FirmwareDiff {
+    pub fn is_empty(&self) -> bool {
+        return self.libraries_added.is_empty()
+            && self.libraries_removed.is_empty()
+            && self.soname_bumps.is_empty()
+            && self.libraries_changed.is_empty()
+            && self.packages.is_empty();
+    }
+}
+
+/// Compare two firmwares.
+pub fn diff_firmwares(old: &Firmware, new: &Firmware) -> FirmwareDiff {
+    let mut diff = diff_libraries(&old.libraries(), &new.libraries());
+    diff.old = old.info.to_string();
+    diff.new = new.info.to_string();
+    diff.packages = diff_packages(old.packages(), new.packages());
+    return diff;
+}
+
+/// The part of a soname up to and including `.so`: `libfoo.so` for
+/// `libfoo.so.1`.
+fn family(soname: &str) -> &str {
+    return soname
+        .find(".so")
+        .map_or(soname, |idx| &soname[..idx + ".so".len()]);
+}
+
+pub fn diff_libraries(
+    old: &BTreeMap<String, LibraryInfo>,
+    new: &BTreeMap<String, LibraryInfo>,
+) -> FirmwareDiff {
+    let mut diff = FirmwareDiff::default();
+    let mut removed: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    let mut added: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for (name, old_lib) in old {
+        match new.get(name) {
+            Some(new_lib) => {
+                let lib_diff = diff_library(old_lib, new_lib);
+                if !lib_diff.is_empty() {
+                    diff.libraries_changed.push(lib_diff);
+                }
+            }
+            None => removed.entry(family(name)).or_default().push(name),
+        }
+    }
+    for name in new.keys().filter(|name| !old.contains_key(*name)) {
+        added.entry(family(name)).or_default().push(name);
+    }
+
+    for (family, old_names) in removed {
+        let Some(new_names) = added.remove(family) else {
+            diff.libraries_removed
+                .extend(old_names.into_iter().map(String::from));
+            continue;
+        };
+        // Compare symbols across the bump when it is one soname for another.
+        if let ([old_name], [new_name]) = (old_names.as_slice(), new_names.as_slice()) {
+            let mut lib_diff = diff_library(&old[*old_name], &new[*new_name]);
+            lib_diff.old_name = Some(String::from(*old_name));
+            diff.libraries_changed.push(lib_diff);
+        }
+        diff.soname_bumps.push(SonameBump {
+            family: String::from(family),
+            old: old_names.into_iter().map(String::from).collect(),
+            new: new_names.into_iter().map(String::from).collect(),
+        });
+    }
+    diff.libraries_added = added.into_values().flatten().map(String::from).collect();
+    diff.libraries_added.sort();
+    diff.libraries_changed.sort_by(|a, b| a.name.cmp(&b.name));
+    return diff;
+}
+
+fn diff_library(old: &LibraryInfo, new: &LibraryInfo) -> LibraryDiff {
+    let old_symbols: BTreeSet<&str> = old.symbols.iter().map(String::as_str).collect();
+    let new_symbols: BTreeSet<&str> = new.symbols.iter().map(String::as_str).collect();
+    let version_nodes = |symbols: &BTreeSet<&str>| -> BTreeSet<String> {
+        return symbols
+            .iter()
+            .filter_map(|symbol| symbol.split_once('@').map(|(_, node)| String::from(node)))
+            .collect();
+    };
+    let old_nodes = version_nodes(&old_symbols);
+    return LibraryDiff {
+        name: new.name.clone(),
+        old_name: None,
+        symbols_removed: old_symbols
+            .difference(&new_symbols)
+            .map(|symbol| String::from(*symbol))
+            .collect(),
+        symbols_added: new_symbols
+            .difference(&old_symbols)
+            .map(|symbol| String::from(*symbol))
+            .collect(),
+        version_nodes_added: version_nodes(&new_symbols)
+            .into_iter()
+            .filter(|node| !old_nodes.contains(node))
+            .collect(),
+    };
+}
+
+impl LibraryDiff {
+    fn is_empty(&self) -> bool {
+        return self.symbols_removed.is_empty()
+            && self.symbols_added.is_empty()
+            && self.version_nodes_added.is_empty();
+    }
+}
+
+pub fn diff_packages(
+    old: &HashMap<String, PackageEntry>,
+    new: &HashMap<String, PackageEntry>,
+) -> Vec<PackageChange> {
+    let names: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    return names
+        .into_iter()
+        .filter_map(|name| {
+            let old = old.get(name).map(|entry| entry.version.to_string());
+            let new = new.get(name).map(|entry| entry.version.to_string());
+            if old == new {
+                return None;
+            }
+            return Some(PackageChange {
+                name: name.clone(),
+                old,
+                new,
+            });
+        })
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use bin_lib::{LibraryInfo, LibraryPriority};
+    use fw_lib::{PackageEntry, PackageVersion};
+
+    use super::{LibraryDiff, PackageChange, SonameBump, diff_libraries, diff_packages};
+
+    fn libs(items: &[(&str, &[&str])]) -> BTreeMap<String, LibraryInfo> {
+        return items
+            .iter()
+            .map(|(name, symbols)| {
+                let lib = LibraryInfo {
+                    name: String::from(*name),
+                    package: None,
+                    needed: vec![],
+                    symbols: symbols.iter().map(|s| String::from(*s)).collect(),
+                    names: vec![String::from(*name)],
+                    undefined: vec![],
+                    undefined_lazy: vec![],
+                    dlopen: vec![],
+                    arm: None,
+                    rpath: vec![],
+                    priority: LibraryPriority::System,
+                };
+                return (String::from(*name), lib);
+            })
+            .collect();
+    }
+
+    #[test]
+    fn finds_bumps_and_symbol_changes() {
+        let old = libs(&[
+            ("libc.so.6", &["memcpy@GLIBC_2.4", "gets@GLIBC_2.4"]),
+            ("libfoo.so.1", &["foo_open", "foo_close"]),
+            ("libgone.so.2", &["gone"]),
+            ("libsame.so.1", &["same"]),
+        ]);
+        let new = libs(&[
+            (
+                "libc.so.6",
+                &["memcpy@GLIBC_2.4", "reallocarray@GLIBC_2.26"],
+            ),
+            ("libfoo.so.2", &["foo_open", "foo_open2"]),
+            ("libnew.so.0", &["new"]),
+            ("libsame.so.1", &["same"]),
+        ]);
+
+        let diff = diff_libraries(&old, &new);
+
+        assert_eq!(diff.libraries_added, vec!["libnew.so.0"]);
+        assert_eq!(diff.libraries_removed, vec!["libgone.so.2"]);
+        assert_eq!(
+            diff.soname_bumps,
+            vec![SonameBump {
+                family: String::from("libfoo.so"),
+                old: vec![String::from("libfoo.so.1")],
+                new: vec![String::from("libfoo.so.2")],
+            }]
+        );
+        assert_eq!(
+            diff.libraries_changed,
+            vec![
+                LibraryDiff {
+                    name: String::from("libc.so.6"),
+                    old_name: None,
+                    symbols_removed: vec![String::from("gets@GLIBC_2.4")],
+                    symbols_added: vec![String::from("reallocarray@GLIBC_2.26")],
+                    version_nodes_added: vec![String::from("GLIBC_2.26")],
+                },
+                LibraryDiff {
+                    name: String::from("libfoo.so.2"),
+                    old_name: Some(String::from("libfoo.so.1")),
+                    symbols_removed: vec![String::from("foo_close")],
+                    symbols_added: vec![String::from("foo_open2")],
+                    version_nodes_added: vec![],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn lists_package_version_changes() {
+        let packages = |items: &[(&str, &str, Option<&str>)]| -> HashMap<String, PackageEntry> {
+            return items
+                .iter()
+                .map(|(name, upstream, revision)| {
+                    let version = PackageVersion {
+                        epoch: None,
+                        upstream: String::from(*upstream),
+                        debian_revision: revision.map(String::from),
+                    };
+                    return (String::from(*name), PackageEntry { version });
+                })
+                .collect();
+        };
+        let old = packages(&[
+            ("glib-2.0", "2.48.2", Some("r3")),
+            ("qtbase", "5.6.2", None),
+        ]);
+        let new = packages(&[
+            ("glib-2.0", "2.56.4", Some("r1")),
+            ("nodejs", "12.22.12", None),
+        ]);
+
+        assert_eq!(
+            diff_packages(&old, &new),
+            vec![
+                PackageChange {
+                    name: String::from("glib-2.0"),
+                    old: Some(String::from("2.48.2-r3")),
+                    new: Some(String::from("2.56.4-r1")),
+                },
+                PackageChange {
+                    name: String::from("nodejs"),
+                    old: None,
+                    new: Some(String::from("12.22.12")),
+                },
+                PackageChange {
+                    name: String::from("qtbase"),
+                    old: Some(String::from("5.6.2")),
+                    new: None,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use std::fs::File;
+use std::io::{Error, Write};
+use std::path::PathBuf;
+
+use clap::{Parser, ValueEnum};
+
+use cli_lib::ExitCode;
+use fw_lib::Firmware;
+
+use crate::diff::{FirmwareDiff, diff_firmwares};
+
+mod diff;
+
+#[derive(Parser, Debug)]
+struct Args {
+    #[arg(help = "Data directory of the older firmware")]
+    old: PathBuf,
+    #[arg(help = "Data directory of the newer firmware")]
+    new: PathBuf,
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    #[arg(short, long, value_enum, default_value = "markdown")]
+    format: OutputFormat,
+}
+
+#[derive(Debug, Clone, PartialEq, ValueEnum)]
+enum OutputFormat {
+    Markdown,
+    Json,
+}
+
+/// A list longer than this is folded into a collapsible `<details>` block.
+const FOLD_THRESHOLD: usize = 10;
+
+fn main() {
+    let args = Args::parse();
+    let load = |dir: &PathBuf| -> Firmware {
+        return match Firmware::load(dir) {
+            Ok(firmware) => firmware,
+            Err(e) => {
+                eprintln!("Failed to load firmware {}: {e}", dir.to_string_lossy());
+                ExitCode::BadInput.exit();
+            }
+        };
+    };
+    let diff = diff_firmwares(&load(&args.old), &load(&args.new));
+
+    let mut output: Box<dyn Write> = match &args.output {
+        Some(path) => match File::create(path) {
+            Ok(file) => Box::new(file),
+            Err(e) => {
+                eprintln!("Failed to create {}: {e}", path.to_string_lossy());
+                ExitCode::OutputError.exit();
+            }
+        },
+        None => Box::new(std::io::stdout()),
+    };
+    let written = match args.format {
+        OutputFormat::Markdown => print_markdown(&diff, &mut output),
+        OutputFormat::Json => serde_json::to_writer_pretty(&mut output, &diff)
+            .map_err(Error::from)
+            .and_then(|()| output.write_all(b"\n")),
+    };
+    if let Err(e) = written {
+        eprintln!("Failed to write the diff: {e}");
+        ExitCode::OutputError.exit();
+    }
+}
+
+fn print_markdown(diff: &FirmwareDiff, out: &mut Box<dyn Write>) -> Result<(), Error> {
+    out.write_fmt(format_args!("## From {} to {}\n\n", diff.old, diff.new))?;
+    if diff.is_empty() {
+        out.write_fmt(format_args!("No changes\n"))?;
+        return Ok(());
+    }
+    print_list(out, "Libraries removed", &diff.libraries_removed)?;
+    print_list(out, "Libraries added", &diff.libraries_added)?;
+    if !diff.soname_bumps.is_empty() {
+        out.write_fmt(format_args!("### Soname bumps\n\n"))?;
+        for bump in &diff.soname_bumps {
+            out.write_fmt(format_args!(
+                "* {}: {} → {}\n",
+                bump.family,
+                bump.old.join(", "),
+                bump.new.join(", ")
+            ))?;
+        }
+        out.write_fmt(format_args!("\n"))?;
+    }
+    if !diff.libraries_changed.is_empty() {
+        out.write_fmt(format_args!("### Library symbols\n\n"))?;
+        for lib in &diff.libraries_changed {
+            match &lib.old_name {
+                Some(old_name) => {
+                    out.write_fmt(format_args!("#### {} (was {old_name})\n\n", lib.name))?;
+                }
+                None => out.write_fmt(format_args!("#### {}\n\n", lib.name))?,
+            }
+            print_symbols(out, "removed", &lib.symbols_removed)?;
+            print_symbols(out, "added", &lib.symbols_added)?;
+            if !lib.version_nodes_added.is_empty() {
+                out.write_fmt(format_args!(
+                    "* Version nodes added: {}\n",
+                    lib.version_nodes_added.join(", ")
+                ))?;
+            }
+            out.write_fmt(format_args!("\n"))?;
+        }
+    }
+    if !diff.packages.is_empty() {
+        out.write_fmt(format_args!(
+            "### Packages\n\n| Package | Old | New |\n|---|---|---|\n"
+        ))?;
+        for change in &diff.packages {
+            out.write_fmt(format_args!(
+                "| {} | {} | {} |\n",
+                change.name,
+                change.old.as_deref().unwrap_or("-"),
+                change.new.as_deref().unwrap_or("-")
+            ))?;
+        }
+        out.write_fmt(format_args!("\n"))?;
+    }
+    return Ok(());
+}
+
+fn print_list(out: &mut Box<dyn Write>, title: &str, names: &[String]) -> Result<(), Error> {
+    if names.is_empty() {
+        return Ok(());
+    }
+    out.write_fmt(format_args!("### {title}\n\n"))?;
+    for name in names {
+        out.write_fmt(format_args!("* {name}\n"))?;
+    }
+    out.write_fmt(format_args!("\n"))?;
+    return Ok(());
+}
+
+fn print_symbols(out: &mut Box<dyn Write>, what: &str, symbols: &[String]) -> Result<(), Error> {
+    if symbols.is_empty() {
+        return Ok(());
+    }
+    if symbols.len() <= FOLD_THRESHOLD {
+        out.write_fmt(format_args!("* Symbols {what}: {}\n", symbols.join(", ")))?;
+        return Ok(());
+    }
+    out.write_fmt(format_args!(
+        "* <details>\n  <summary>{} symbols {what}</summary>\n\n",
+        symbols.len()
+    ))?;
+    for symbol in symbols {
+        out.write_fmt(format_args!("  * {symbol}\n"))?;
+    }
+    out.write_fmt(format_args!("  </details>\n"))?;
+    return Ok(());
+}
@@ -1,420 +1,402 @@
-use crate::{FirmwareExtractor, output_error};
-use bin_lib::LibraryInfo;
+use crate::output_error;
 use cli_lib::{ExitCode, file_label};
-use debian_control::Control;
-use debversion::{AsVersion, Version as DebVersion};
-use fw_lib::FirmwareInfo;
-use path_slash::PathBufExt;
-use regex::Regex;
-use semver::Version as SemVer;
+use fw_lib::library_key;
+use fw_lib::reader::RootfsReader;
+use fw_lib::rootfs::{LibraryFile, RootfsScan, read_library};
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
-use std::ffi::OsStr;
+use std::fmt::{Display, Formatter};
 use std::fs;
-use std::fs::{DirEntry, File};
-use std::io::{BufRead, BufReader, BufWriter, Error, ErrorKind, Read};
-use std::path::{Path, PathBuf};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Error, ErrorKind};
+use std::path::Path;
 
-#[derive(Serialize, Deserialize, Debug)]
-struct PackageVersion {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    epoch: Option<u32>,
-    upstream: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    debian_revision: Option<String>,
+/// Written next to `index.json`: what each library was extracted from, so the
+/// next `--incremental` run can tell which ones changed.
+const HASHES_FILE: &str = "hashes.json";
+
+/// Bumped whenever the saved library info changes, so that `--incremental`
+/// parses every library again rather than keep files in the old format.
+const EXTRACT_FORMAT: u32 = 1;
+
+/// The content of `hashes.json`.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Sources {
+    /// The [`EXTRACT_FORMAT`] the libraries were saved in. 0 for a
+    /// `hashes.json` that predates it.
+    format: u32,
+    libraries: BTreeMap<String, LibrarySource>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct SystemPackage {
-    version: PackageVersion,
+impl Sources {
+    /// Whether the libraries were saved by this version of the extractor.
+    pub fn is_current(&self) -> bool {
+        return self.format == EXTRACT_FORMAT;
+    }
 }
 
-impl FirmwareExtractor {
-    pub fn extract_pkgs<P: AsRef<Path>>(
-        &self,
-        files_pkg_index: &mut BTreeMap<PathBuf, String>,
-        output: P,
-    ) -> Result<(), (ExitCode, String)> {
-        let mut packages = BTreeMap::<String, SystemPackage>::new();
-        for path in &self.opkg_info_paths {
-            let dir = match path.read_dir() {
-                Ok(dir) => dir,
-                Err(e) => {
-                    eprintln!("Skipping {}: {e}", path.to_string_lossy());
-                    continue;
-                }
-            };
-            for ent in dir {
-                let Ok(ent) = ent else {
-                    continue;
-                };
-                let path = ent.path();
-                let Some(ext) = path.extension() else {
-                    continue;
-                };
-                // The info directory sits five levels below the rootfs root,
-                // as in <root>/usr/lib/opkg/info/<name>.list.
-                let Some(root) = path.ancestors().nth(5) else {
-                    eprintln!("Skipping {}: path is too short", path.to_string_lossy());
-                    continue;
-                };
-                if ext == "list" {
-                    let file = match File::open(&path) {
-                        Ok(file) => file,
-                        Err(e) => {
-                            eprintln!("Skipping {}: {e}", path.to_string_lossy());
-                            continue;
-                        }
-                    };
-                    for line in BufReader::new(file).lines() {
-                        let Ok(line) = line else {
-                            continue;
-                        };
-                        let Some(line) = line.split('\t').next() else {
-                            continue;
-                        };
-                        let Some(stem) = path.file_stem() else {
-                            continue;
-                        };
-                        files_pkg_index.insert(
-                            root.join(PathBuf::from_slash(line.trim_start_matches('/'))),
-                            String::from(stem.to_string_lossy()),
-                        );
-                    }
-                } else if ext == "control" {
-                    let ctrl = match Control::from_file(&path) {
-                        Ok(ctrl) => ctrl,
-                        Err(e) => {
-                            eprintln!("Skipping {}: {e:?}", path.to_string_lossy());
-                            continue;
-                        }
-                    };
-                    let Some(bin) = ctrl.binaries().next() else {
-                        continue;
-                    };
-                    let Some(version_str) = bin.as_deb822().get("Version") else {
-                        eprintln!("Skipping {}: no Version field", path.to_string_lossy());
-                        continue;
-                    };
-                    let version =
-                        version_str
-                            .as_str()
-                            .into_version()
-                            .unwrap_or_else(|_| DebVersion {
-                                epoch: None,
-                                upstream_version: version_str.clone(),
-                                debian_revision: None,
-                            });
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct LibrarySource {
+    sha256: String,
+    /// The package is part of the saved library info, so a library that moved
+    /// to another package is extracted again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    package: Option<String>,
+}
 
-                    let Some(pkg_name) = bin.name() else {
-                        eprintln!("Skipping {}: no Package field", path.to_string_lossy());
-                        continue;
-                    };
-                    packages.insert(
-                        pkg_name.clone(),
-                        SystemPackage {
-                            version: PackageVersion {
-                                epoch: version.epoch,
-                                upstream: version.upstream_version,
-                                debian_revision: version.debian_revision,
-                            },
-                        },
-                    );
-                }
-            }
-        }
-        let file = File::create(output.as_ref().join("packages.json"))
-            .map_err(|e| output_error("open packages.json", &e))?;
-        let writer = BufWriter::new(file);
-        serde_json::to_writer_pretty(writer, &packages)
-            .map_err(|e| output_error("write packages.json", &e))?;
-        return Ok(());
-    }
+/// What an incremental extraction changed in a firmware's libraries.
+#[derive(Debug, Default)]
+pub struct ExtractSummary {
+    pub added: Vec<String>,
+    pub changed: Vec<String>,
+    pub removed: Vec<String>,
+    pub unchanged: usize,
+}
 
-    pub fn extract_libs<P: AsRef<Path>>(
-        &self,
-        files_pkg_index: &BTreeMap<PathBuf, String>,
-        lib_index: &mut BTreeMap<String, String>,
-        output: P,
-    ) -> Result<(), (ExitCode, String)> {
-        for lib_path in &self.lib_paths {
-            let Ok(dir) = lib_path.read_dir() else {
-                continue;
-            };
-            for ent in dir {
-                match ent {
-                    Ok(ent) => {
-                        self.handle_entry(
-                            &ent,
-                            &self.so_regex,
-                            files_pkg_index,
-                            lib_index,
-                            output.as_ref(),
-                            0,
-                        )?;
-                    }
-                    Err(e) => {
-                        eprintln!(
-                            "Failed to read entry in directory: {}: {e:?}",
-                            lib_path.to_string_lossy()
-                        );
-                    }
-                }
+impl Display for ExtractSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "{} libraries added, {} changed, {} removed, {} unchanged",
+            self.added.len(),
+            self.changed.len(),
+            self.removed.len(),
+            self.unchanged
+        ))?;
+        for (label, names) in [
+            ("Added", &self.added),
+            ("Changed", &self.changed),
+            ("Removed", &self.removed),
+        ] {
+            for name in names {
+                f.write_fmt(format_args!("\n  {label}: {name}"))?;
             }
         }
-        return Ok(());
+        Ok(())
     }
+}
 
-    fn handle_entry<P>(
-        &self,
-        ent: &DirEntry,
-        so_regex: &Regex,
-        files_pkg_index: &BTreeMap<PathBuf, String>,
-        lib_index: &mut BTreeMap<String, String>,
-        output: P,
-        debug: u8,
-    ) -> Result<(), (ExitCode, String)>
-    where
-        P: AsRef<Path>,
-    {
-        let ent_path = ent.path();
-        if let (Some(name), Some(metadata)) = (
-            ent_path.file_name().and_then(OsStr::to_str),
-            ent.metadata().ok(),
-        ) {
-            if !so_regex.is_match(name) {
-                return Ok(());
-            }
-            if metadata.is_file() {
-                let mut lib_info = match File::open(&ent_path).and_then(|file| {
-                    LibraryInfo::parse(file, false, name).map_err(|e| {
-                        Error::new(
-                            ErrorKind::InvalidData,
-                            format!("Failed to parse library {name}: {e:?}"),
-                        )
-                    })
-                }) {
-                    Ok(info) => info,
-                    Err(e) => {
-                        eprintln!("Ignoring library {name}: {e:?}");
-                        return Ok(());
-                    }
-                };
-                lib_info.package = files_pkg_index.get(&ent_path).cloned();
-                if debug > 0 {
-                    println!("Saving symbols list for {name}");
-                }
-                let symbols_name = format!("{name}.json");
-                File::create(output.as_ref().join(&symbols_name))
-                    .and_then(|file| {
-                        let writer = BufWriter::new(file);
-                        return serde_json::to_writer_pretty(writer, &lib_info).map_err(|e| {
-                            Error::new(ErrorKind::InvalidData, format!("Failed to write {e:?}"))
-                        });
-                    })
-                    .map_err(|e| output_error(&format!("save {symbols_name}"), &e))?;
-                lib_index.insert(String::from(name), symbols_name);
-            } else if metadata.is_symlink() {
-                match self.final_link_target(&ent_path) {
-                    Ok(Some(target)) => {
-                        lib_index
-                            .insert(String::from(name), format!("{}.json", file_label(&target)));
-                    }
-                    Ok(None) => {}
-                    Err(e) => {
-                        eprintln!(
-                            "Failed to resolve link target for {}: {e}",
-                            ent_path.to_string_lossy()
-                        );
-                    }
-                }
-            }
-        }
-        return Ok(());
+enum Extracted {
+    Written(LibrarySource),
+    Unchanged(LibrarySource),
+    Ignored,
+}
+
+pub fn extract_pkgs<P: AsRef<Path>>(
+    scan: &RootfsScan,
+    output: P,
+) -> Result<(), (ExitCode, String)> {
+    let file = File::create(output.as_ref().join("packages.json"))
+        .map_err(|e| output_error("open packages.json", &e))?;
+    let writer = BufWriter::new(file);
+    serde_json::to_writer_pretty(writer, &scan.packages)
+        .map_err(|e| output_error("write packages.json", &e))?;
+    return Ok(());
+}
+
+/// Save the commands on the firmware's `PATH`, for checking package scripts.
+pub fn extract_executables<P: AsRef<Path>>(
+    scan: &RootfsScan,
+    output: P,
+) -> Result<(), (ExitCode, String)> {
+    let file = File::create(output.as_ref().join("executables.json"))
+        .map_err(|e| output_error("open executables.json", &e))?;
+    let writer = BufWriter::new(file);
+    serde_json::to_writer_pretty(writer, &scan.executables)
+        .map_err(|e| output_error("write executables.json", &e))?;
+    return Ok(());
+}
+
+/// Save the Luna bus services and their methods, for checking the calls
+/// packages make.
+pub fn extract_luna<P: AsRef<Path>>(
+    scan: &RootfsScan,
+    output: P,
+) -> Result<(), (ExitCode, String)> {
+    let file = File::create(output.as_ref().join("luna.json"))
+        .map_err(|e| output_error("open luna.json", &e))?;
+    let writer = BufWriter::new(file);
+    serde_json::to_writer_pretty(writer, &scan.luna)
+        .map_err(|e| output_error("write luna.json", &e))?;
+    return Ok(());
+}
+
+/// Save what the developer-mode jail mounts, and the libraries it leaves out.
+/// Without a jail, a `jail.json` from an earlier extraction is removed.
+pub fn extract_jail<P: AsRef<Path>>(
+    scan: &RootfsScan,
+    output: P,
+) -> Result<(), (ExitCode, String)> {
+    let path = output.as_ref().join("jail.json");
+    let Some(jail) = &scan.jail else {
+        return match fs::remove_file(&path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(output_error("remove jail.json", &e)),
+            _ => Ok(()),
+        };
+    };
+    let file = File::create(path).map_err(|e| output_error("open jail.json", &e))?;
+    let writer = BufWriter::new(file);
+    serde_json::to_writer_pretty(writer, jail).map_err(|e| output_error("write jail.json", &e))?;
+    return Ok(());
+}
+
+/// The sources recorded by the last extraction into `output`. Empty when there
+/// was none, or it predates `hashes.json`.
+pub fn previous_sources<P: AsRef<Path>>(output: P) -> Sources {
+    let Some(value) = File::open(output.as_ref().join(HASHES_FILE))
+        .ok()
+        .and_then(|file| {
+            serde_json::from_reader::<_, serde_json::Value>(BufReader::new(file)).ok()
+        })
+    else {
+        return Sources::default();
+    };
+    if let Ok(sources) = serde_json::from_value(value.clone()) {
+        return sources;
     }
+    // Before the format was recorded, the file held only the libraries.
+    return Sources {
+        format: 0,
+        libraries: serde_json::from_value(value).unwrap_or_default(),
+    };
+}
 
-    fn final_link_target<P: AsRef<Path>>(&self, link: P) -> Result<Option<PathBuf>, Error> {
-        let mut target = link.as_ref().to_path_buf();
-        loop {
-            match fs::symlink_metadata(&target) {
-                Ok(metadata) => {
-                    if !metadata.is_symlink() {
-                        break;
-                    }
-                }
-                Err(e) => {
-                    return Err(Error::new(
-                        e.kind(),
-                        format!("Can't find symlink info for {}", target.to_string_lossy()),
-                    ));
+/// Save every library in `scan` into `output`, parsing them in parallel. A
+/// library whose source matches its entry in `previous` is kept as it is,
+/// unless `previous` was saved in another format.
+/// Each is saved and indexed under its key, so a library of another class
+/// and machine goes in a directory named after it, as in
+/// `elf64-aarch64/libc.so.6.json`.
+pub fn extract_libs<P: AsRef<Path> + Sync>(
+    reader: &dyn RootfsReader,
+    scan: &RootfsScan,
+    previous: &Sources,
+    lib_index: &mut BTreeMap<String, String>,
+    output: P,
+) -> Result<ExtractSummary, (ExitCode, String)> {
+    let output = output.as_ref();
+    let files: Vec<&LibraryFile> = scan.libraries.iter().filter(|lib| !lib.is_link()).collect();
+    let extracted: Vec<Extracted> = files
+        .par_iter()
+        .map(|lib| extract_lib(reader, scan, lib, previous, output))
+        .collect::<Result<_, _>>()?;
+
+    let mut summary = ExtractSummary::default();
+    let mut sources = BTreeMap::new();
+    for (lib, extracted) in files.iter().zip(extracted) {
+        let key = lib.key();
+        let source = match extracted {
+            Extracted::Written(source) => {
+                if previous.libraries.contains_key(&key) {
+                    summary.changed.push(key.clone());
+                } else {
+                    summary.added.push(key.clone());
                 }
+                source
             }
-            match target.read_link() {
-                Ok(l) => {
-                    target = self.join_link_target(l)?;
-                }
-                Err(e) => {
-                    eprintln!(
-                        "Failed to find link target for {}: {:?}",
-                        link.as_ref().to_string_lossy(),
-                        e
-                    );
-                    return Ok(None);
-                }
+            Extracted::Unchanged(source) => {
+                summary.unchanged += 1;
+                source
             }
-        }
-        Ok(Some(target))
+            Extracted::Ignored => continue,
+        };
+        lib_index.insert(key.clone(), format!("{key}.json"));
+        sources.insert(key, source);
+    }
+    for lib in scan.libraries.iter().filter(|lib| lib.is_link()) {
+        let target = library_key(&file_label(&lib.target), lib.arch);
+        lib_index.insert(lib.key(), format!("{target}.json"));
+    }
+    for name in previous
+        .libraries
+        .keys()
+        .filter(|name| !sources.contains_key(*name))
+    {
+        // Gone from the firmware. Its file would otherwise be left behind.
+        let _ = fs::remove_file(output.join(format!("{name}.json")));
+        summary.removed.push(name.clone());
     }
 
-    fn join_link_target<P>(&self, target: P) -> Result<PathBuf, Error>
-    where
-        P: AsRef<Path>,
+    let writer = BufWriter::new(
+        File::create(output.join(HASHES_FILE))
+            .map_err(|e| output_error(&format!("open {HASHES_FILE}"), &e))?,
+    );
+    let sources = Sources {
+        format: EXTRACT_FORMAT,
+        libraries: sources,
+    };
+    serde_json::to_writer_pretty(writer, &sources)
+        .map_err(|e| output_error(&format!("write {HASHES_FILE}"), &e))?;
+    return Ok(summary);
+}
+
+fn extract_lib(
+    reader: &dyn RootfsReader,
+    scan: &RootfsScan,
+    lib: &LibraryFile,
+    previous: &Sources,
+    output: &Path,
+) -> Result<Extracted, (ExitCode, String)> {
+    let key = lib.key();
+    let symbols_name = format!("{key}.json");
+    let sha256 = match reader.read(&lib.target) {
+        Ok(data) => sha256::digest(data),
+        Err(e) => {
+            eprintln!("Ignoring library {}: {e:?}", lib.name);
+            return Ok(Extracted::Ignored);
+        }
+    };
+    let source = LibrarySource {
+        sha256,
+        package: scan.package_files.get(&lib.target).cloned(),
+    };
+    if previous.is_current()
+        && previous.libraries.get(&key) == Some(&source)
+        && output.join(&symbols_name).is_file()
     {
-        let target = target.as_ref();
-        if target.is_absolute() {
-            let joined = self
-                .rootfs_path
-                .join(target.strip_prefix("/").unwrap_or(target));
-            if joined.exists() {
-                return Ok(joined);
-            }
-        } else {
-            for lib_path in &self.lib_paths {
-                let joined = lib_path.join(target);
-                if joined.exists() {
-                    return Ok(joined);
-                }
-            }
+        return Ok(Extracted::Unchanged(source));
+    }
+    let lib_info = match read_library(reader, &lib.target, &scan.package_files) {
+        Ok(info) => info,
+        Err(e) => {
+            eprintln!("Ignoring library {}: {e:?}", lib.name);
+            return Ok(Extracted::Ignored);
         }
-        Err(Error::new(
-            ErrorKind::NotFound,
-            format!("Can't find link target {}", file_label(target)),
-        ))
+    };
+    let path = output.join(&symbols_name);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| output_error(&format!("create {key}"), &e))?;
     }
-
-    pub fn create<P: AsRef<Path>>(input: P) -> Result<Self, Error> {
-        let input = input.as_ref();
-        let fw_info = Self::extract_fw_info(input)?;
-        let rootfs_path = input.join("rootfs.pak.unsquashfs");
-        let lib_paths = Self::extract_lib_paths(input, &rootfs_path)?;
-        let opkg_info_paths = Self::extract_opkg_info_paths(input)?;
-        let so_regex = Regex::new("^.+.so(\\.\\w+)*$").unwrap();
-        Ok(Self {
-            fw_info,
-            rootfs_path,
-            lib_paths,
-            opkg_info_paths,
-            so_regex,
+    File::create(path)
+        .and_then(|file| {
+            let writer = BufWriter::new(file);
+            return serde_json::to_writer_pretty(writer, &lib_info)
+                .map_err(|e| Error::new(ErrorKind::InvalidData, format!("Failed to write {e:?}")));
         })
-    }
+        .map_err(|e| output_error(&format!("save {symbols_name}"), &e))?;
+    return Ok(Extracted::Written(source));
+}
 
-    fn extract_fw_info(input: &Path) -> Result<FirmwareInfo, Error> {
-        let (version, ota_id) = input
-            .file_name()
-            .and_then(|name| name.to_str())
-            .and_then(|s| s.split_once('-'))
-            .ok_or_else(|| {
-                Error::new(
-                    ErrorKind::NotFound,
-                    format!("Bad input path {}", input.display()),
-                )
-            })?;
-        let mut starfish_release = String::new();
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::path::PathBuf;
 
-        File::open(
-            input
-                .join("rootfs.pak.unsquashfs")
-                .join("etc")
-                .join("starfish-release"),
-        )?
-        .read_to_string(&mut starfish_release)?;
-        let release_regex = Regex::new("release (\\d+\\.\\d+\\.\\d+)").unwrap();
-        let release = release_regex
-            .captures(&starfish_release)
-            .and_then(|cap| cap.get(1))
-            .ok_or_else(|| {
-                Error::new(
-                    ErrorKind::NotFound,
-                    format!("Bad starfish-release: {starfish_release}"),
-                )
-            })?;
-        Ok(FirmwareInfo {
-            version: String::from(version),
-            ota_id: String::from(ota_id),
-            release: SemVer::parse(release.as_str()).map_err(|e| {
-                Error::new(
-                    ErrorKind::InvalidData,
-                    format!("Invalid version {}: {e:?}", release.as_str()),
-                )
-            })?,
-        })
-    }
-    fn extract_lib_paths<Input, Root>(input: Input, root: Root) -> Result<Vec<PathBuf>, Error>
-    where
-        Input: AsRef<Path>,
-        Root: AsRef<Path>,
-    {
-        let root = root.as_ref();
-        let input = input.as_ref();
-        let ldconf_path = root.join("etc").join("ld.so.conf");
-        let reader = BufReader::new(File::open(ldconf_path)?);
-        Ok(["lib", "usr/lib"]
-            .iter()
-            .map(|p| root.join(PathBuf::from_slash(*p)))
-            .chain(reader.lines().filter_map(|line| {
-                let trimmed = line
-                    .as_ref()
-                    .map_or("", |r| r.trim().strip_prefix("/").unwrap_or(""));
-                if trimmed.is_empty() {
-                    return None;
-                }
-                if let Some(bsp) = trimmed.strip_prefix("mnt/bsppart/") {
-                    return fs::read_dir(input)
-                        .map(|mut dir| {
-                            return dir.find_map(|entry| {
-                                if let Ok(entry) = entry {
-                                    let file_name = entry.file_name();
-                                    if Regex::new(r"bsppart(-\w+)?.pak.unsquashfs")
-                                        .unwrap()
-                                        .is_match(&file_name.to_string_lossy())
-                                    {
-                                        return Some(
-                                            input.join(file_name).join(PathBuf::from_slash(bsp)),
-                                        );
-                                    }
-                                }
-                                return None;
-                            });
-                        })
-                        .ok()
-                        .flatten();
-                }
-                return Some(root.join(PathBuf::from_slash(trimmed)));
-            }))
-            .collect())
+    use bin_lib::ElfArch;
+    use fw_lib::reader::HostReader;
+    use fw_lib::rootfs::{LibraryFile, RootfsScan};
+
+    use super::{Sources, extract_libs, previous_sources};
+
+    #[test]
+    fn extracts_only_what_changed() {
+        let rootfs = tempfile::TempDir::new().unwrap();
+        let output = tempfile::TempDir::new().unwrap();
+        let lib = rootfs.path().join("libfoo.so.1");
+        fs::copy(
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("../../common/bin/src/fixtures/lib_runpath.so"),
+            &lib,
+        )
+        .unwrap();
+        let mut scan = RootfsScan {
+            libraries: vec![LibraryFile {
+                name: String::from("libfoo.so.1"),
+                path: lib.clone(),
+                target: lib.clone(),
+                arch: None,
+            }],
+            ..RootfsScan::default()
+        };
+        let extract = |scan: &RootfsScan| {
+            let previous = previous_sources(output.path());
+            return extract_libs(
+                &HostReader,
+                scan,
+                &previous,
+                &mut BTreeMap::new(),
+                output.path(),
+            )
+            .unwrap();
+        };
+
+        assert_eq!(extract(&scan).added, vec!["libfoo.so.1"]);
+        assert_eq!(extract(&scan).unchanged, 1);
+
+        scan.package_files.insert(lib, String::from("libfoo1"));
+        assert_eq!(extract(&scan).changed, vec!["libfoo.so.1"]);
+
+        // Saved by an extractor that predates the format: parsed again.
+        let hashes = output.path().join("hashes.json");
+        let sources: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&hashes).unwrap()).unwrap();
+        fs::write(&hashes, sources["libraries"].to_string()).unwrap();
+        assert_eq!(extract(&scan).changed, vec!["libfoo.so.1"]);
+        assert_eq!(extract(&scan).unchanged, 1);
+
+        scan.libraries.clear();
+        assert_eq!(extract(&scan).removed, vec!["libfoo.so.1"]);
+        assert!(!output.path().join("libfoo.so.1.json").exists());
     }
 
-    fn extract_opkg_info_paths<Input>(input: Input) -> Result<Vec<PathBuf>, Error>
-    where
-        Input: AsRef<Path>,
-    {
-        Ok(input
-            .as_ref()
-            .read_dir()?
-            .flat_map(|ent| {
-                let Ok(ent) = ent else {
-                    return vec![];
-                };
-                let path = ent.path();
-                return [
-                    "usr/lib/opkg/info",
-                    "bsp/var/lib/opkg/info", /*, "var/lib/opkg/info"*/
-                ]
-                .iter()
-                .map(|x| path.join(PathBuf::from_slash(x)))
-                .filter(|p| p.is_dir())
-                .collect();
-            })
-            .collect())
+    #[test]
+    fn keys_libraries_by_class_and_machine() {
+        let rootfs = tempfile::TempDir::new().unwrap();
+        let output = tempfile::TempDir::new().unwrap();
+        let lib = rootfs.path().join("libfoo.so.1");
+        fs::copy(
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("../../common/bin/src/fixtures/lib_runpath.so"),
+            &lib,
+        )
+        .unwrap();
+        let arch = ElfArch::read(fs::File::open(&lib).unwrap()).ok();
+        let scan = RootfsScan {
+            libraries: vec![
+                LibraryFile {
+                    name: String::from("libfoo.so.1"),
+                    path: lib.clone(),
+                    target: lib.clone(),
+                    arch,
+                },
+                LibraryFile {
+                    name: String::from("libfoo.so"),
+                    path: rootfs.path().join("libfoo.so"),
+                    target: lib.clone(),
+                    arch,
+                },
+            ],
+            ..RootfsScan::default()
+        };
+        let mut index = BTreeMap::new();
+        extract_libs(
+            &HostReader,
+            &scan,
+            &Sources::default(),
+            &mut index,
+            output.path(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            index,
+            BTreeMap::from([
+                (
+                    String::from("elf64-x86_64/libfoo.so"),
+                    String::from("elf64-x86_64/libfoo.so.1.json")
+                ),
+                (
+                    String::from("elf64-x86_64/libfoo.so.1"),
+                    String::from("elf64-x86_64/libfoo.so.1.json")
+                ),
+            ])
+        );
+        assert!(
+            output
+                .path()
+                .join("elf64-x86_64/libfoo.so.1.json")
+                .is_file()
+        );
     }
 }
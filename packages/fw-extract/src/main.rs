@@ -1,16 +1,23 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
 use std::fmt::Display;
 use std::fs;
 use std::fs::File;
 use std::io::BufWriter;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
 use clap::Parser;
 use cli_lib::ExitCode;
-use fw_lib::FirmwareInfo;
-use regex::Regex;
+use fw_lib::packed::PackBuilder;
+use fw_lib::reader::{HostReader, RootfsReader};
+use fw_lib::rootfs::{FirmwareId, Rootfs};
+use fw_lib::validate::validate_data_dir;
+use rayon::prelude::*;
+
+use crate::squashfs::ImageReader;
 
 mod extractor;
+mod squashfs;
 
 #[derive(Parser, Debug)]
 struct Args {
@@ -20,21 +27,30 @@ struct Args {
     output: PathBuf,
     #[arg(short = 'w', long)]
     rewrite: bool,
+    /// With `--rewrite`, parse again only the libraries whose file or package
+    /// changed since the last extraction, and list what changed.
+    #[arg(short = 'I', long, requires = "rewrite")]
+    incremental: bool,
+    /// How many inputs and libraries to read at once. Defaults to the number
+    /// of CPUs.
+    #[arg(short, long)]
+    jobs: Option<usize>,
+    /// After extracting, pack every firmware directory in the output
+    /// directory into this file. With no inputs, only pack.
+    #[arg(short, long)]
+    pack: Option<PathBuf>,
+    /// After extracting, check the output directory for broken data: index
+    /// entries without a file, unparsable JSON, bad releases and duplicate
+    /// firmwares. Runs before `--pack`, which is skipped if anything is found.
+    #[arg(long)]
+    validate: bool,
     #[arg(short, long, action = clap::ArgAction::Count)]
     verbose: u8,
 }
 
-struct FirmwareExtractor {
-    fw_info: FirmwareInfo,
-    rootfs_path: PathBuf,
-    lib_paths: Vec<PathBuf>,
-    opkg_info_paths: Vec<PathBuf>,
-    so_regex: Regex,
-}
-
 fn main() {
     let args = Args::parse();
-    if let Err((code, message)) = run(args) {
+    if let Err((code, message)) = run(&args) {
         eprintln!("{message}");
         code.exit();
     }
@@ -45,50 +61,157 @@ fn output_error<E: Display>(what: &str, e: &E) -> (ExitCode, String) {
     return (ExitCode::OutputError, format!("Failed to {what}: {e}"));
 }
 
-fn run(args: Args) -> Result<(), (ExitCode, String)> {
-    let mut bad_input = false;
-    for input in args.inputs {
-        let extractor = match FirmwareExtractor::create(&input) {
-            Ok(extractor) => extractor,
-            Err(e) => {
-                eprintln!("Failed to read input {}: {e}", input.to_string_lossy());
-                bad_input = true;
-                continue;
-            }
-        };
+fn run(args: &Args) -> Result<(), (ExitCode, String)> {
+    if let Some(jobs) = args.jobs {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build_global()
+            .map_err(|e| (ExitCode::BadInput, format!("Bad --jobs: {e}")))?;
+    }
+    let claimed = Mutex::new(HashSet::new());
+    let read: Vec<bool> = args
+        .inputs
+        .par_iter()
+        .map(|input| extract_input(args, input, &claimed))
+        .collect::<Result<_, _>>()?;
+    let mut bad_input = read.contains(&false);
+    if args.validate && !validate(&args.output)? {
+        bad_input = true;
+    } else if let Some(pack) = &args.pack {
+        write_pack(&args.output, pack)?;
+    }
+    if bad_input {
+        ExitCode::BadInput.exit();
+    }
+    return Ok(());
+}
+
+/// Print every issue in the data directory. Returns whether there were none.
+fn validate(data_dir: &Path) -> Result<bool, (ExitCode, String)> {
+    let issues = validate_data_dir(data_dir).map_err(|e| {
+        (
+            ExitCode::BadInput,
+            format!(
+                "Failed to read data directory {}: {e}",
+                data_dir.to_string_lossy()
+            ),
+        )
+    })?;
+    for issue in &issues {
+        eprintln!("{issue}");
+    }
+    if issues.is_empty() {
+        println!("No issues in {}", data_dir.to_string_lossy());
+    } else {
+        eprintln!("{} issues in {}", issues.len(), data_dir.to_string_lossy());
+    }
+    return Ok(issues.is_empty());
+}
 
-        let output = args.output.join(format!(
-            "{}-{}",
-            extractor.fw_info.version, extractor.fw_info.ota_id
-        ));
-        if !output.exists() {
-            fs::create_dir_all(&output)
-                .map_err(|e| output_error("create the output directory", &e))?;
-        } else if !args.rewrite {
-            println!("Skipping existing {}", extractor.fw_info);
-            continue;
+/// Extract one input into its firmware directory. Returns whether the input
+/// could be read. `claimed` holds the firmware directories other inputs are
+/// extracting into: two inputs of the same firmware would write over each
+/// other's files, so the second one is rejected.
+fn extract_input(
+    args: &Args,
+    input: &Path,
+    claimed: &Mutex<HashSet<String>>,
+) -> Result<bool, (ExitCode, String)> {
+    let (reader, path): (Arc<dyn RootfsReader>, PathBuf) = match ImageReader::mount(input) {
+        Ok(Some(images)) => {
+            let path = images.path.clone();
+            (Arc::new(images), path)
+        }
+        Ok(None) => (Arc::new(HostReader), input.to_path_buf()),
+        Err(e) => {
+            eprintln!("Failed to read images in {}: {e}", input.to_string_lossy());
+            return Ok(false);
+        }
+    };
+    let rootfs = match Rootfs::open_with(reader, &path, &FirmwareId::default()) {
+        Ok(rootfs) => rootfs,
+        Err(e) => {
+            eprintln!("Failed to read input {}: {e}", input.to_string_lossy());
+            return Ok(false);
         }
-        println!("Extracting information from {}", extractor.fw_info);
+    };
 
-        let mut lib_index: BTreeMap<String, String> = BTreeMap::new();
-        let mut files_pkg_index: BTreeMap<PathBuf, String> = BTreeMap::new();
-        extractor.extract_pkgs(&mut files_pkg_index, &output)?;
-        extractor.extract_libs(&files_pkg_index, &mut lib_index, &output)?;
-        let writer = BufWriter::new(
-            File::create(output.join("index.json"))
-                .map_err(|e| output_error("open index.json", &e))?,
+    let dir_name = rootfs.info.dir_name();
+    if !claimed.lock().unwrap().insert(dir_name.clone()) {
+        eprintln!(
+            "Skipping input {}: another input is also {dir_name}",
+            input.to_string_lossy()
         );
-        serde_json::to_writer_pretty(writer, &lib_index)
-            .map_err(|e| output_error("write index.json", &e))?;
-        let writer = BufWriter::new(
-            File::create(output.join("info.json"))
-                .map_err(|e| output_error("open info.json", &e))?,
-        );
-        serde_json::to_writer_pretty(writer, &extractor.fw_info)
-            .map_err(|e| output_error("write info.json", &e))?;
+        return Ok(false);
     }
-    if bad_input {
-        ExitCode::BadInput.exit();
+    let output = args.output.join(&dir_name);
+    if !output.exists() {
+        fs::create_dir_all(&output).map_err(|e| output_error("create the output directory", &e))?;
+    } else if !args.rewrite {
+        println!("Skipping existing {}", rootfs.info);
+        return Ok(true);
+    }
+    println!("Extracting information from {}", rootfs.info);
+
+    let scan = rootfs.scan();
+    for skipped in &scan.skipped {
+        eprintln!("Skipping {skipped}");
+    }
+    for note in &scan.cache_notes {
+        eprintln!("ld.so.cache: {note}");
+    }
+    let previous = if args.incremental {
+        extractor::previous_sources(&output)
+    } else {
+        extractor::Sources::default()
+    };
+    let mut lib_index: BTreeMap<String, String> = BTreeMap::new();
+    extractor::extract_pkgs(&scan, &output)?;
+    extractor::extract_executables(&scan, &output)?;
+    extractor::extract_luna(&scan, &output)?;
+    extractor::extract_jail(&scan, &output)?;
+    let summary =
+        extractor::extract_libs(rootfs.reader(), &scan, &previous, &mut lib_index, &output)?;
+    let writer = BufWriter::new(
+        File::create(output.join("index.json")).map_err(|e| output_error("open index.json", &e))?,
+    );
+    serde_json::to_writer_pretty(writer, &lib_index)
+        .map_err(|e| output_error("write index.json", &e))?;
+    let writer = BufWriter::new(
+        File::create(output.join("info.json")).map_err(|e| output_error("open info.json", &e))?,
+    );
+    serde_json::to_writer_pretty(writer, &rootfs.info)
+        .map_err(|e| output_error("write info.json", &e))?;
+    if args.incremental {
+        println!("{}: {summary}", rootfs.info.dir_name());
+    }
+    return Ok(true);
+}
+
+/// Pack every firmware directory in `output`, in name order.
+fn write_pack(output: &Path, pack: &Path) -> Result<(), (ExitCode, String)> {
+    let mut dirs: Vec<PathBuf> = fs::read_dir(output)
+        .map_err(|e| output_error("read the output directory", &e))?
+        .filter_map(|ent| ent.ok().map(|ent| ent.path()))
+        .filter(|path| path.join("info.json").is_file())
+        .collect();
+    dirs.sort();
+    let mut builder = PackBuilder::new();
+    for dir in &dirs {
+        builder.add_dir(dir).map_err(|e| {
+            (
+                ExitCode::BadInput,
+                format!("Failed to pack {}: {e}", dir.to_string_lossy()),
+            )
+        })?;
     }
+    builder
+        .write(pack)
+        .map_err(|e| output_error("write the firmware pack", &e))?;
+    println!(
+        "Packed {} firmwares into {}",
+        dirs.len(),
+        pack.to_string_lossy()
+    );
     return Ok(());
 }
@@ -0,0 +1,519 @@
+//! Read firmware images without `unsquashfs`.
+//!
+//! Unpacking a rootfs takes root-like permissions and gigabytes of disk, so
+//! the images are read in place instead. [`ImageReader`] shows each one where
+//! `unsquashfs` would have unpacked it, and reads its files out of the image
+//! as they are asked for.
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fmt::{Debug, Formatter};
+use std::fs::File;
+use std::io::{BufReader, Error, ErrorKind, Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+
+use backhand::compression::{CompressionAction, Compressor, DefaultCompressor};
+use backhand::kind::Kind;
+use backhand::{BackhandError, FilesystemCompressor, FilesystemReader, InnerNode, SuperBlock};
+use fw_lib::reader::{Entry, EntryKind, HostReader, ReadSeek, RootfsReader};
+use xz2::read::XzDecoder;
+
+/// File extensions of the images read in place.
+const IMAGE_EXTENSIONS: [&str; 2] = ["pak", "squashfs"];
+
+/// How far into a `.pak` to look for the squashfs superblock. Some carry a
+/// header before it.
+const MAGIC_SEARCH_LIMIT: u64 = 1 << 20;
+
+/// backhand's own xz support links a liblzma that conflicts with the one
+/// `debpkg` links, so xz blocks are decoded here with `xz2`.
+struct XzCompressor;
+
+static XZ_COMPRESSOR: XzCompressor = XzCompressor;
+
+impl CompressionAction for XzCompressor {
+    type Error = BackhandError;
+    type Compressor = Compressor;
+    type FilesystemCompressor = FilesystemCompressor;
+    type SuperBlock = SuperBlock;
+
+    fn decompress(
+        &self,
+        bytes: &[u8],
+        out: &mut Vec<u8>,
+        compressor: Compressor,
+    ) -> Result<(), BackhandError> {
+        if compressor != Compressor::Xz {
+            return DefaultCompressor.decompress(bytes, out, compressor);
+        }
+        XzDecoder::new(bytes).read_to_end(out)?;
+        return Ok(());
+    }
+
+    fn compress(
+        &self,
+        bytes: &[u8],
+        fc: FilesystemCompressor,
+        block_size: u32,
+    ) -> Result<Vec<u8>, BackhandError> {
+        return DefaultCompressor.compress(bytes, fc, block_size);
+    }
+}
+
+/// How many symlinks a path may go through, as `MAXSYMLINKS` on Linux.
+const MAX_LINK_HOPS: usize = 40;
+
+/// A firmware read out of its images. Each image shows as a directory where
+/// `unsquashfs` would have unpacked it. Any other path is read from the host.
+pub(crate) struct ImageReader {
+    /// The input to open: the directory of images, or where the single image
+    /// shows.
+    pub path: PathBuf,
+    images: Vec<Image>,
+}
+
+/// One image, and where it shows.
+struct Image {
+    mount: PathBuf,
+    filesystem: FilesystemReader<'static>,
+    /// The index of each node in the image, by its path from the root.
+    nodes: HashMap<PathBuf, usize>,
+    /// The names in each directory, by its path from the root.
+    children: HashMap<PathBuf, Vec<OsString>>,
+}
+
+impl ImageReader {
+    /// Read the images in `input`: a directory of images, as
+    /// `<version>-<ota_id>/rootfs.pak`, or a single rootfs image. Returns
+    /// `None` when there is nothing to read in place: the images are already
+    /// unpacked, or there are none.
+    pub(crate) fn mount(input: &Path) -> Result<Option<ImageReader>, Error> {
+        if input.is_file() {
+            if !is_image(input) {
+                return Ok(None);
+            }
+            let stem = input.file_stem().unwrap_or(input.as_os_str());
+            let path = input.with_file_name(stem);
+            let Some(image) = Image::open(input, path.clone())? else {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("{} is not a squashfs image", input.display()),
+                ));
+            };
+            return Ok(Some(ImageReader {
+                path,
+                images: vec![image],
+            }));
+        }
+
+        let mut entries: Vec<PathBuf> = input
+            .read_dir()?
+            .filter_map(|ent| ent.ok().map(|ent| ent.path()))
+            .collect();
+        entries.sort();
+        let unpacked = entries.iter().any(|path| {
+            return path.is_dir() && path.extension().is_some_and(|ext| ext == "unsquashfs");
+        });
+        if unpacked {
+            return Ok(None);
+        }
+        let mut images = Vec::new();
+        for file in entries
+            .iter()
+            .filter(|path| path.is_file() && is_image(path))
+        {
+            let mut name = file.file_name().unwrap_or_default().to_os_string();
+            name.push(".unsquashfs");
+            // A `.pak` may hold something other than a filesystem, such as a
+            // kernel. Skip it.
+            images.extend(Image::open(file, input.join(name))?);
+        }
+        if images.is_empty() {
+            return Ok(None);
+        }
+        return Ok(Some(ImageReader {
+            path: input.to_path_buf(),
+            images,
+        }));
+    }
+
+    /// The image `path` is in, and the path from its root.
+    fn locate<'a>(&self, path: &'a Path) -> Option<(&Image, &'a Path)> {
+        return self.images.iter().find_map(|image| {
+            let rel = path.strip_prefix(&image.mount).ok()?;
+            return Some((image, rel));
+        });
+    }
+}
+
+impl Debug for ImageReader {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return f
+            .debug_struct("ImageReader")
+            .field("path", &self.path)
+            .field(
+                "images",
+                &self
+                    .images
+                    .iter()
+                    .map(|image| &image.mount)
+                    .collect::<Vec<_>>(),
+            )
+            .finish();
+    }
+}
+
+impl RootfsReader for ImageReader {
+    fn metadata(&self, path: &Path) -> Result<Entry, Error> {
+        let Some((image, rel)) = self.locate(path) else {
+            return HostReader.metadata(path);
+        };
+        return Ok(image.entry(image.resolve(rel, true)?));
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> Result<Entry, Error> {
+        let Some((image, rel)) = self.locate(path) else {
+            return HostReader.symlink_metadata(path);
+        };
+        return Ok(image.entry(image.resolve(rel, false)?));
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>, Error> {
+        let Some((image, rel)) = self.locate(path) else {
+            // The images show next to the files they are read from.
+            let mut entries = HostReader.read_dir(path)?;
+            for image in &self.images {
+                if image.mount.parent() == Some(path) && !entries.contains(&image.mount) {
+                    entries.push(image.mount.clone());
+                }
+            }
+            return Ok(entries);
+        };
+        let index = image.resolve(rel, true)?;
+        let node = &image.filesystem.root.nodes[index];
+        if !matches!(node.inner, InnerNode::Dir(_)) {
+            return Err(Error::new(
+                ErrorKind::NotADirectory,
+                format!("{} is not a directory", path.display()),
+            ));
+        }
+        let dir = node.fullpath.strip_prefix("/").unwrap_or(&node.fullpath);
+        return Ok(image
+            .children
+            .get(dir)
+            .into_iter()
+            .flatten()
+            .map(|name| path.join(name))
+            .collect());
+    }
+
+    fn read_link(&self, path: &Path) -> Result<PathBuf, Error> {
+        let Some((image, rel)) = self.locate(path) else {
+            return HostReader.read_link(path);
+        };
+        let index = image.resolve(rel, false)?;
+        let InnerNode::Symlink(link) = &image.filesystem.root.nodes[index].inner else {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} is not a symlink", path.display()),
+            ));
+        };
+        return Ok(link.link.clone());
+    }
+
+    fn open(&self, path: &Path) -> Result<Box<dyn ReadSeek + '_>, Error> {
+        let Some((image, rel)) = self.locate(path) else {
+            return HostReader.open(path);
+        };
+        let index = image.resolve(rel, true)?;
+        let InnerNode::File(file) = &image.filesystem.root.nodes[index].inner else {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} is not a file", path.display()),
+            ));
+        };
+        return Ok(Box::new(image.filesystem.file(file).reader()));
+    }
+}
+
+impl Image {
+    /// Read the directory of the image in `file`, to show at `mount`.
+    /// Returns `None` when the file is not a squashfs image.
+    fn open(file: &Path, mount: PathBuf) -> Result<Option<Image>, Error> {
+        let mut reader = BufReader::new(File::open(file)?);
+        let Some(offset) = find_superblock(&mut reader)? else {
+            return Ok(None);
+        };
+        let filesystem = FilesystemReader::from_reader_with_offset_and_kind(
+            reader,
+            offset,
+            Kind::new_v4(&XZ_COMPRESSOR),
+        )
+        .map_err(|e| {
+            return Error::new(
+                ErrorKind::InvalidData,
+                format!("Failed to read {}: {e}", file.display()),
+            );
+        })?;
+        let mut nodes = HashMap::new();
+        let mut children: HashMap<PathBuf, Vec<OsString>> = HashMap::new();
+        for (index, node) in filesystem.files().enumerate() {
+            let rel = node.fullpath.strip_prefix("/").unwrap_or(&node.fullpath);
+            if let (Some(parent), Some(name)) = (rel.parent(), rel.file_name()) {
+                children
+                    .entry(parent.to_path_buf())
+                    .or_default()
+                    .push(name.to_os_string());
+            }
+            nodes.insert(rel.to_path_buf(), index);
+        }
+        return Ok(Some(Image {
+            mount,
+            filesystem,
+            nodes,
+            children,
+        }));
+    }
+
+    /// The node at `rel`, from the root of the image. Symlinks on the way are
+    /// followed, inside the image, and so is one at the end if `follow`.
+    fn resolve(&self, rel: &Path, follow: bool) -> Result<usize, Error> {
+        // The components still to walk, the next one last.
+        let mut pending = components(rel);
+        let mut current = PathBuf::new();
+        let mut hops = 0;
+        while let Some(name) = pending.pop() {
+            if name == ".." {
+                current.pop();
+                continue;
+            }
+            current.push(&name);
+            let index = self.index(&current)?;
+            let InnerNode::Symlink(link) = &self.filesystem.root.nodes[index].inner else {
+                continue;
+            };
+            if pending.is_empty() && !follow {
+                return Ok(index);
+            }
+            hops += 1;
+            if hops > MAX_LINK_HOPS {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Too many levels of symlinks in {}", rel.display()),
+                ));
+            }
+            current.pop();
+            if link.link.has_root() {
+                current = PathBuf::new();
+            }
+            pending.extend(components(&link.link));
+        }
+        return self.index(&current);
+    }
+
+    fn index(&self, rel: &Path) -> Result<usize, Error> {
+        return self.nodes.get(rel).copied().ok_or_else(|| {
+            return Error::new(
+                ErrorKind::NotFound,
+                format!("No {} in the image", self.mount.join(rel).display()),
+            );
+        });
+    }
+
+    fn entry(&self, index: usize) -> Entry {
+        let node = &self.filesystem.root.nodes[index];
+        let kind = match node.inner {
+            InnerNode::File(_) => EntryKind::File,
+            InnerNode::Dir(_) => EntryKind::Dir,
+            InnerNode::Symlink(_) => EntryKind::Symlink,
+            _ => EntryKind::Other,
+        };
+        return Entry {
+            kind,
+            mode: u32::from(node.header.permissions & 0o7777),
+        };
+    }
+}
+
+/// The names and `..` in `path`, last first.
+fn components(path: &Path) -> Vec<OsString> {
+    return path
+        .components()
+        .rev()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_os_string()),
+            Component::ParentDir => Some(OsString::from("..")),
+            _ => None,
+        })
+        .collect();
+}
+
+fn is_image(path: &Path) -> bool {
+    return path
+        .extension()
+        .is_some_and(|ext| IMAGE_EXTENSIONS.iter().any(|known| ext == *known));
+}
+
+/// The offset of the squashfs superblock, found by its magic.
+fn find_superblock<R: Read + Seek>(reader: &mut R) -> Result<Option<u64>, Error> {
+    let mut head = Vec::new();
+    reader
+        .by_ref()
+        .take(MAGIC_SEARCH_LIMIT)
+        .read_to_end(&mut head)?;
+    reader.seek(SeekFrom::Start(0))?;
+    return Ok(head
+        .windows(4)
+        .position(|window| window == b"hsqs")
+        .map(|pos| pos as u64));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::fs::File;
+    use std::io::Cursor;
+    use std::io::Read;
+    use std::sync::Arc;
+
+    use backhand::compression::{CompressionAction, Compressor};
+    use backhand::{FilesystemCompressor, FilesystemWriter, NodeHeader};
+    use fw_lib::reader::RootfsReader;
+    use fw_lib::rootfs::{FirmwareId, Rootfs};
+    use xz2::read::XzEncoder;
+
+    use super::{ImageReader, XZ_COMPRESSOR};
+
+    #[test]
+    fn decodes_xz_blocks() {
+        let mut block = Vec::new();
+        XzEncoder::new(&b"libfoo.so.1"[..], 6)
+            .read_to_end(&mut block)
+            .unwrap();
+        let mut out = Vec::new();
+        XZ_COMPRESSOR
+            .decompress(&block, &mut out, Compressor::Xz)
+            .unwrap();
+        assert_eq!(out, b"libfoo.so.1");
+    }
+
+    #[test]
+    fn reads_a_rootfs_in_its_image() {
+        let input = tempfile::TempDir::new().unwrap();
+        let fw_dir = input.path().join("03.40.82.01-HE_DTV_W21P_AFADATAA");
+        fs::create_dir(&fw_dir).unwrap();
+        let header = NodeHeader {
+            permissions: 0o644,
+            ..NodeHeader::default()
+        };
+        let mut image = FilesystemWriter::default();
+        image.set_compressor(FilesystemCompressor::new(Compressor::Gzip, None).unwrap());
+        image
+            .push_dir_all("etc", header)
+            .and_then(|()| image.push_dir_all("usr/lib/opkg", header))
+            .and_then(|()| image.push_dir_all("usr/bin", header))
+            .and_then(|()| image.push_dir_all("usr/share/luna-service2/services.d", header))
+            .unwrap();
+        image
+            .push_file(
+                Cursor::new(b"webOS TV release 6.4.0".to_vec()),
+                "etc/starfish-release",
+                header,
+            )
+            .unwrap();
+        image
+            .push_file(
+                Cursor::new(b"\x7fELF".to_vec()),
+                "usr/lib/libfoo.so.1",
+                header,
+            )
+            .unwrap();
+        image
+            .push_symlink("libfoo.so.1", "usr/lib/libfoo.so", header)
+            .unwrap();
+        image
+            .push_symlink("/usr/lib/libfoo.so", "usr/lib/libfoo-abs.so", header)
+            .unwrap();
+        image
+            .push_file(
+                Cursor::new(b"Package: libfoo1\nVersion: 1.0\n".to_vec()),
+                "usr/lib/opkg/status",
+                header,
+            )
+            .unwrap();
+        image
+            .push_file(
+                Cursor::new(vec![0; 4096]),
+                "usr/bin/curl",
+                NodeHeader {
+                    permissions: 0o755,
+                    ..header
+                },
+            )
+            .unwrap();
+        image
+            .push_file(Cursor::new(vec![0; 16]), "usr/bin/notes.txt", header)
+            .unwrap();
+        image
+            .push_file(
+                Cursor::new(b"Name=com.webos.audio\n".to_vec()),
+                "usr/share/luna-service2/services.d/audio.service",
+                header,
+            )
+            .unwrap();
+        image
+            .write(File::create(fw_dir.join("rootfs.pak")).unwrap())
+            .unwrap();
+        fs::write(fw_dir.join("kernel.pak"), b"not a filesystem").unwrap();
+
+        let reader = Arc::new(ImageReader::mount(&fw_dir).unwrap().expect("images"));
+        let rootfs_dir = fw_dir.join("rootfs.pak.unsquashfs");
+        let mut listed = reader.read_dir(&fw_dir).unwrap();
+        listed.sort();
+        assert_eq!(
+            listed,
+            vec![
+                fw_dir.join("kernel.pak"),
+                fw_dir.join("rootfs.pak"),
+                rootfs_dir.clone(),
+            ]
+        );
+        assert!(
+            reader
+                .metadata(&rootfs_dir.join("usr/lib/libfoo-abs.so"))
+                .unwrap()
+                .is_file()
+        );
+        assert!(!rootfs_dir.exists(), "nothing is unpacked");
+
+        let rootfs =
+            Rootfs::open_with(reader.clone(), &reader.path, &FirmwareId::default()).unwrap();
+        let scan = rootfs.scan();
+
+        assert_eq!(rootfs.info.dir_name(), "03.40.82.01-HE_DTV_W21P_AFADATAA");
+        assert!(scan.packages.contains_key("libfoo1"));
+        let libfoo = rootfs_dir.join("usr/lib/libfoo.so.1");
+        let mut libraries: Vec<_> = scan
+            .libraries
+            .iter()
+            .map(|lib| (lib.name.as_str(), &lib.target))
+            .collect();
+        libraries.sort();
+        assert_eq!(
+            libraries,
+            vec![
+                ("libfoo-abs.so", &libfoo),
+                ("libfoo.so", &libfoo),
+                ("libfoo.so.1", &libfoo),
+            ]
+        );
+        assert_eq!(
+            scan.executables
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>(),
+            vec!["curl"]
+        );
+        assert!(scan.luna.has_service("com.webos.audio"));
+    }
+}
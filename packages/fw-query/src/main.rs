@@ -0,0 +1,231 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use prettytable::{Cell, Row, Table};
+use semver::VersionReq;
+
+use cli_lib::ExitCode;
+use fw_lib::{DeviceFilter, Firmware, FirmwareInfo, Middleware, Platform, group_by_device};
+
+use crate::query::{needed_closure, symbol_matches};
+
+mod query;
+
+#[derive(Parser, Debug)]
+struct Args {
+    #[arg(
+        short,
+        long,
+        value_name = "NAME",
+        required_unless_present_any = ["symbol", "capabilities"],
+        conflicts_with_all = ["symbol", "capabilities"],
+        help = "Find a library by soname or alias"
+    )]
+    library: Option<String>,
+    #[arg(
+        short,
+        long,
+        value_name = "SYMBOL",
+        help = "Find the libraries that define SYMBOL, with or without @VERSION"
+    )]
+    symbol: Option<String>,
+    #[arg(
+        short,
+        long,
+        conflicts_with = "symbol",
+        help = "List the versions of key middleware, such as Qt and SDL2"
+    )]
+    capabilities: bool,
+    #[arg(short, long, help = "Also list every library the found ones need")]
+    needed: bool,
+    #[arg(short = 'r', long)]
+    fw_releases: Option<VersionReq>,
+    #[arg(
+        long = "data-dir",
+        value_name = "DIR",
+        help = "Also read firmware data from DIR (repeatable, first wins)"
+    )]
+    data_dirs: Vec<PathBuf>,
+    #[arg(
+        long,
+        value_name = "SOC",
+        help = "Only firmwares for this SoC, such as k8lp"
+    )]
+    soc: Option<String>,
+    #[arg(
+        long,
+        value_name = "BOARD",
+        help = "Only firmwares for this board, such as W21P"
+    )]
+    board: Option<String>,
+    #[arg(
+        long,
+        help = "Only firmwares for this platform: tv, signage or projector"
+    )]
+    platform: Option<Platform>,
+}
+
+fn main() {
+    let args = Args::parse();
+    let device = DeviceFilter {
+        soc: args.soc.clone(),
+        board: args.board.clone(),
+        platform: args.platform,
+    };
+    let firmwares: Vec<Firmware> = match Firmware::list_all(&Firmware::data_dirs(&args.data_dirs)) {
+        Ok(firmwares) => firmwares
+            .into_iter()
+            .filter(|fw| device.matches(&fw.info))
+            .filter(|fw| {
+                if let Some(fw_releases) = &args.fw_releases {
+                    return fw_releases.matches(&fw.info.release);
+                }
+                return true;
+            })
+            .collect(),
+        Err(e) => {
+            eprintln!("Failed to read firmware data: {e}");
+            ExitCode::NoFirmware.exit();
+        }
+    };
+    if firmwares.is_empty() {
+        eprintln!("No firmware found");
+        ExitCode::NoFirmware.exit();
+    }
+
+    // Rows of the same device go together, and are labelled with it once the
+    // firmwares are for more than one.
+    let groups = group_by_device(&firmwares, |fw| &fw.info);
+    let by_device = groups.len() > 1;
+    let firmwares: Vec<&Firmware> = groups.into_iter().flat_map(|(_, group)| group).collect();
+
+    if args.capabilities {
+        print_capabilities(&firmwares, by_device);
+        return;
+    }
+    let mut table = Table::new();
+    let mut titles = vec![];
+    if by_device {
+        titles.push("Device");
+    }
+    titles.push("Firmware");
+    if args.symbol.is_some() {
+        titles.push("Symbol");
+    }
+    titles.extend(["Library", "Package"]);
+    if args.needed {
+        titles.push("Needed");
+    }
+    let columns = titles.len();
+    table.set_titles(Row::new(titles.into_iter().map(Cell::new).collect()));
+    for firmware in &firmwares {
+        let label = firmware_cells(&firmware.info, by_device);
+        // (symbol, library) pairs found on this firmware.
+        let found = if let Some(symbol) = &args.symbol {
+            firmware
+                .libraries()
+                .into_values()
+                .flat_map(|lib| {
+                    let symbols: Vec<String> = lib
+                        .symbols
+                        .iter()
+                        .filter(|defined| symbol_matches(defined, symbol))
+                        .cloned()
+                        .collect();
+                    return symbols
+                        .into_iter()
+                        .map(move |defined| (Some(defined), lib.clone()));
+                })
+                .collect()
+        } else {
+            let name = args.library.as_deref().unwrap_or_default();
+            firmware
+                .find_library(name)
+                .into_iter()
+                .map(|lib| (None, lib))
+                .collect::<Vec<_>>()
+        };
+        if found.is_empty() {
+            let mut cells = label.clone();
+            cells.resize_with(columns, || Cell::new("-"));
+            table.add_row(Row::new(cells));
+            continue;
+        }
+        for (symbol, lib) in found {
+            let mut cells = label.clone();
+            if let Some(symbol) = symbol {
+                cells.push(Cell::new(&symbol));
+            }
+            match &args.library {
+                Some(name) if *name != lib.name => {
+                    cells.push(Cell::new(&format!("{name} → {}", lib.name)));
+                }
+                _ => cells.push(Cell::new(&lib.name)),
+            }
+            cells.push(Cell::new(lib.package.as_deref().unwrap_or("-")));
+            if args.needed {
+                let closure = needed_closure(&lib, |name| firmware.find_library(name));
+                let needed: Vec<String> = closure
+                    .found
+                    .into_iter()
+                    .chain(
+                        closure
+                            .missing
+                            .iter()
+                            .map(|name| format!("{name} (missing)")),
+                    )
+                    .collect();
+                cells.push(Cell::new(&needed.join("\n")));
+            }
+            table.add_row(Row::new(cells));
+        }
+    }
+    if let Err(e) = table.print(&mut std::io::stdout()) {
+        eprintln!("Failed to write the result: {e}");
+        ExitCode::OutputError.exit();
+    }
+}
+
+/// The cells naming a firmware at the start of a row: its device first, with
+/// `by_device`.
+fn firmware_cells(info: &FirmwareInfo, by_device: bool) -> Vec<Cell> {
+    let mut cells = Vec::new();
+    if by_device {
+        cells.push(Cell::new(info.device_label().as_deref().unwrap_or("-")));
+    }
+    cells.push(Cell::new(&format!(
+        "webOS {} ({})",
+        info.release,
+        info.dir_name()
+    )));
+    return cells;
+}
+
+/// One row per firmware, one column per middleware.
+fn print_capabilities(firmwares: &[&Firmware], by_device: bool) {
+    let mut table = Table::new();
+    let mut titles = Vec::new();
+    if by_device {
+        titles.push(String::from("Device"));
+    }
+    titles.push(String::from("Firmware"));
+    titles.extend(Middleware::ALL.iter().map(ToString::to_string));
+    table.set_titles(Row::new(
+        titles.iter().map(|title| Cell::new(title)).collect(),
+    ));
+    for firmware in firmwares {
+        let capabilities = firmware.capabilities();
+        let mut cells = firmware_cells(&firmware.info, by_device);
+        cells.extend(Middleware::ALL.iter().map(|middleware| {
+            let version = capabilities
+                .get(middleware)
+                .map_or_else(|| String::from("-"), ToString::to_string);
+            return Cell::new(&version);
+        }));
+        table.add_row(Row::new(cells));
+    }
+    if let Err(e) = table.print(&mut std::io::stdout()) {
+        eprintln!("Failed to write the result: {e}");
+        ExitCode::OutputError.exit();
+    }
+}
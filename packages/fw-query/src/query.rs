@@ -0,0 +1,103 @@
+use std::collections::{BTreeSet, VecDeque};
+
+use bin_lib::LibraryInfo;
+
+/// Whether a defined symbol answers a query. A query without a version, such
+/// as `memcpy`, matches every version of it; `memcpy@GLIBC_2.4` matches only
+/// that one.
+pub fn symbol_matches(defined: &str, query: &str) -> bool {
+    if query.contains('@') {
+        return defined == query;
+    }
+    return defined.split_once('@').map_or(defined, |(name, _)| name) == query;
+}
+
+/// Every library a library loads, directly or through another one.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct NeededClosure {
+    pub found: Vec<String>,
+    /// Names the firmware has no library for.
+    pub missing: Vec<String>,
+}
+
+pub fn needed_closure<F>(lib: &LibraryInfo, find_library: F) -> NeededClosure
+where
+    F: Fn(&str) -> Option<LibraryInfo>,
+{
+    let mut found = BTreeSet::new();
+    let mut missing = BTreeSet::new();
+    let mut queue: VecDeque<String> = lib.needed.iter().cloned().collect();
+    while let Some(name) = queue.pop_front() {
+        if found.contains(&name) || missing.contains(&name) {
+            continue;
+        }
+        match find_library(&name) {
+            Some(needed) => {
+                queue.extend(needed.needed);
+                found.insert(name);
+            }
+            None => {
+                missing.insert(name);
+            }
+        }
+    }
+    return NeededClosure {
+        found: found.into_iter().collect(),
+        missing: missing.into_iter().collect(),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use bin_lib::{LibraryInfo, LibraryPriority};
+
+    use super::{NeededClosure, needed_closure, symbol_matches};
+
+    #[test]
+    fn matches_symbol_with_or_without_version() {
+        assert!(symbol_matches("memcpy@GLIBC_2.4", "memcpy"));
+        assert!(symbol_matches("memcpy@GLIBC_2.4", "memcpy@GLIBC_2.4"));
+        assert!(symbol_matches(
+            "wl_proxy_marshal_flags",
+            "wl_proxy_marshal_flags"
+        ));
+        assert!(!symbol_matches("memcpy@GLIBC_2.4", "memcpy@GLIBC_2.14"));
+        assert!(!symbol_matches("wmemcpy@GLIBC_2.4", "memcpy"));
+    }
+
+    #[test]
+    fn follows_needed_through_the_firmware() {
+        let lib = |name: &str, needed: &[&str]| LibraryInfo {
+            name: String::from(name),
+            package: None,
+            needed: needed.iter().map(|s| String::from(*s)).collect(),
+            symbols: vec![],
+            names: vec![String::from(name)],
+            undefined: vec![],
+            undefined_lazy: vec![],
+            dlopen: vec![],
+            arm: None,
+            rpath: vec![],
+            priority: LibraryPriority::System,
+        };
+        let sdl = lib("libSDL2-2.0.so.0", &["libwayland-client.so.0", "libm.so.6"]);
+        let closure = needed_closure(&sdl, |name| match name {
+            "libwayland-client.so.0" => Some(lib(name, &["libffi.so.6", "libc.so.6"])),
+            "libm.so.6" => Some(lib(name, &["libc.so.6"])),
+            "libc.so.6" => Some(lib(name, &[])),
+            _ => None,
+        });
+
+        assert_eq!(
+            closure,
+            NeededClosure {
+                found: vec![
+                    String::from("libc.so.6"),
+                    String::from("libm.so.6"),
+                    String::from("libwayland-client.so.0"),
+                ],
+                missing: vec![String::from("libffi.so.6")],
+            }
+        );
+    }
+}
@@ -1,23 +1,33 @@
+use std::cell::RefCell;
+use std::collections::BTreeSet;
 use std::fs::File;
 use std::io::{Error, Write};
 use std::iter;
 use std::path::PathBuf;
 
-use clap::{Parser, ValueEnum};
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, ValueEnum};
 use is_terminal::IsTerminal;
 use prettytable::{Cell, Row, Table};
 use semver::VersionReq;
 
+use bin_lib::LibraryInfo;
 use cli_lib::{ExitCode, file_label};
-use fw_lib::Firmware;
-use ipk_lib::Package;
+use fw_lib::rootfs::FirmwareId;
+use fw_lib::{
+    BundledCopy, DeviceFilter, Firmware, Middleware, Platform, group_by_device, library_key,
+};
+use ipk_lib::{Component, Package, RpathNote, ShellScript};
 use verify_lib::VerifyResult;
 use verify_lib::bin::BinVerifyResult;
 use verify_lib::ipk::{
-    CompatVerdict, ComponentBinVerifyResult, ComponentVerifyResult, DetectionResult,
-    PackageVerifyResult, VerifyForFirmware,
+    CompatVerdict, ComponentBinVerifyResult, ComponentVerifyResult, CpuMismatch, DetectionResult,
+    MissingCommand, MissingDlopen, MissingLunaCall, PackageVerifyResult, ScopeObject,
+    SymbolConflict, UngrantedLunaCall, UnknownPermission, VerifyForFirmware, VersionConflict,
+    cpu_mismatches, missing_commands, missing_luna_calls, ungranted_luna_calls,
+    unknown_permissions,
 };
-use webdetect_lib::{ServiceRuntimeDetection, WebAppDetection};
+use webdetect_lib::{LunaCall, ServiceRuntimeDetection, WebAppDetection};
 
 use crate::output::ReportOutput;
 
@@ -37,6 +47,55 @@ struct Args {
     no_summary: bool,
     #[arg(short = 'r', long)]
     fw_releases: Option<VersionReq>,
+    #[arg(
+        long = "data-dir",
+        value_name = "DIR",
+        help = "Also read firmware data from DIR (repeatable, first wins)"
+    )]
+    data_dirs: Vec<PathBuf>,
+    #[arg(
+        long,
+        value_name = "DIR",
+        conflicts_with = "data_dirs",
+        help = "Check against this rootfs instead of firmware data (repeatable)"
+    )]
+    rootfs: Vec<PathBuf>,
+    #[arg(
+        long,
+        value_name = "VERSION",
+        requires = "rootfs",
+        help = "The firmware version of a single --rootfs, where it does not say"
+    )]
+    fw_version: Option<String>,
+    #[arg(
+        long,
+        value_name = "OTA_ID",
+        requires = "rootfs",
+        help = "The OTA ID of a single --rootfs, such as HE_DTV_W21P_AFADATAA, where it does not say"
+    )]
+    ota_id: Option<String>,
+    #[arg(
+        long,
+        value_name = "SOC",
+        help = "Only firmwares for this SoC, such as k8lp"
+    )]
+    soc: Option<String>,
+    #[arg(
+        long,
+        value_name = "BOARD",
+        help = "Only firmwares for this board, such as W21P"
+    )]
+    board: Option<String>,
+    #[arg(
+        long,
+        help = "Only firmwares for this platform: tv, signage or projector"
+    )]
+    platform: Option<Platform>,
+    #[arg(
+        long,
+        help = "Resolve libraries only from what the developer-mode app jail mounts"
+    )]
+    jailed: bool,
     #[arg(short, long, action = clap::ArgAction::Count)]
     verbose: u8,
 }
@@ -73,9 +132,35 @@ fn main() {
     } else {
         OutputFormat::Plain
     };
-    let firmwares: Vec<Firmware> = match Firmware::list(Firmware::data_path()) {
+    let device = DeviceFilter {
+        soc: args.soc.clone(),
+        board: args.board.clone(),
+        platform: args.platform,
+    };
+    if args.rootfs.len() > 1 && (args.fw_version.is_some() || args.ota_id.is_some()) {
+        Args::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--fw-version and --ota-id name a single --rootfs",
+            )
+            .exit();
+    }
+    let loaded = if args.rootfs.is_empty() {
+        Firmware::list_all(&Firmware::data_dirs(&args.data_dirs))
+    } else {
+        let id = FirmwareId {
+            version: args.fw_version.clone(),
+            ota_id: args.ota_id.clone(),
+        };
+        args.rootfs
+            .iter()
+            .map(|rootfs| Firmware::from_rootfs(rootfs, &id))
+            .collect()
+    };
+    let firmwares: Vec<Firmware> = match loaded {
         Ok(firmwares) => firmwares
             .into_iter()
+            .filter(|fw| device.matches(&fw.info))
             .filter(|fw| {
                 if let Some(fw_releases) = &args.fw_releases {
                     return fw_releases.matches(&fw.info.release);
@@ -92,6 +177,11 @@ fn main() {
         eprintln!("No firmware found");
         ExitCode::NoFirmware.exit();
     }
+    if args.jailed {
+        for fw in firmwares.iter().filter(|fw| fw.jail().is_none()) {
+            eprintln!("No jail recorded for {}, resolving from all of it", fw.info);
+        }
+    }
     let mut all_good = true;
     let mut bad_input = false;
     for package in &args.packages {
@@ -105,21 +195,44 @@ fn main() {
             }
         };
         eprintln!("Verifying package {}...", package.id);
+        let mut outside_jail: Vec<(&Firmware, BTreeSet<String>)> = Vec::new();
         let results: Vec<(&Firmware, PackageVerifyResult)> = firmwares
             .iter()
             .map(|fw| {
+                let jail = fw.jail().filter(|_| args.jailed);
+                let hidden = RefCell::new(BTreeSet::new());
                 let verify = package.verify_for_firmware(
-                    &|name| fw.find_library(name),
+                    &|name, arch| {
+                        // Data extracted before libraries were keyed by
+                        // class and machine has the bare name.
+                        let key = library_key(name, arch.or(fw.info.arch));
+                        if jail.is_some_and(|jail| {
+                            return jail.hidden.contains(&key) || jail.hidden.contains(name);
+                        }) {
+                            hidden.borrow_mut().insert(String::from(name));
+                            return None;
+                        }
+                        return fw.find_library_for(name, arch);
+                    },
                     fw.node_version().as_ref(),
                     fw.web_engine().as_ref(),
                 );
+                outside_jail.push((fw, hidden.into_inner()));
                 return (fw, verify);
             })
             .collect();
         if all_good && !results.iter().all(|(_, r)| r.is_good()) {
             all_good = false;
         }
-        if let Err(e) = print_package_report(&package, &results, &args, &mut output, &format) {
+        let report = print_package_report(
+            &package,
+            &results,
+            &outside_jail,
+            &args,
+            &mut output,
+            &format,
+        );
+        if let Err(e) = report {
             eprintln!("Failed to write the report: {e}");
             ExitCode::OutputError.exit();
         }
@@ -135,23 +248,68 @@ fn main() {
 }
 
 /// Write the report for one package: the app, then each of its services.
+/// Firmwares for different devices get sections of their own, with the device
+/// in their headings.
 fn print_package_report(
     package: &Package,
     results: &[(&Firmware, PackageVerifyResult)],
+    outside_jail: &[(&Firmware, BTreeSet<String>)],
     args: &Args,
     out: &mut Box<dyn ReportOutput>,
     out_fmt: &OutputFormat,
 ) -> Result<(), Error> {
-    let to_file = args.output.is_some();
     out.h2(&format!("Package {}", package.id))?;
     print_packager_warning(package.hand_rolled, out, out_fmt)?;
     print_install_hooks(&package.install_hooks, out, out_fmt)?;
+    print_firmware_sources(results, out)?;
+    print_outside_jail(outside_jail, out)?;
+    let groups = group_by_device(results, |(fw, _)| &fw.info);
+    let several = groups.len() > 1;
+    for (label, group) in groups {
+        let device = match label {
+            _ if !several => String::new(),
+            Some(label) => format!(" on {label}"),
+            None => String::from(" on other devices"),
+        };
+        print_components(package, &group, &device, args, out, out_fmt)?;
+    }
+    return Ok(());
+}
+
+/// Write the sections of the app and each service for some of the firmwares.
+/// `device` follows each heading.
+fn print_components(
+    package: &Package,
+    results: &[&(&Firmware, PackageVerifyResult)],
+    device: &str,
+    args: &Args,
+    out: &mut Box<dyn ReportOutput>,
+    out_fmt: &OutputFormat,
+) -> Result<(), Error> {
+    let to_file = args.output.is_some();
     let (_, result) = results.first().unwrap();
     if to_file {
-        eprintln!(" - App {}", result.app.id);
+        eprintln!(" - App {}{device}", result.app.id);
     }
-    out.h3(&format!("App {}", result.app.id))?;
+    out.h3(&format!("App {}{device}", result.app.id))?;
+    print_rpath_notes(&package.app.rpath_notes, out, out_fmt)?;
     let app: Vec<_> = results.iter().map(|(fw, res)| (*fw, &res.app)).collect();
+    print_bundled_middleware(&package.app.libs, &app, out)?;
+    print_cpu_mismatches(&package.app, &package.app.info.bundled_bins, &app, out)?;
+    print_missing_commands(&package.app.info.scripts, &app, out)?;
+    // The package's own services answer its calls to them.
+    let own: Vec<&str> = iter::once(package.app.info.id.as_str())
+        .chain(
+            package
+                .services
+                .iter()
+                .map(|service| service.info.id.as_str()),
+        )
+        .collect();
+    print_missing_luna_calls(&package.app.info.luna_calls, &own, &app, out)?;
+    let permissions = &package.app.info.required_permissions;
+    print_unknown_permissions(permissions, &app, out)?;
+    print_ungranted_luna_calls(&package.app.info.luna_calls, permissions, &app, out)?;
     if !args.no_summary {
         print_component_summary(&app, out, out_fmt)?;
     }
@@ -160,13 +318,29 @@ fn print_package_report(
     }
     for idx in 0..result.services.len() {
         if to_file {
-            eprintln!(" - Service {}", result.services.get(idx).unwrap().id);
+            eprintln!(" - Service {}{device}", result.services[idx].id);
         }
-        out.h3(&format!("Service {}", result.services.get(idx).unwrap().id))?;
+        out.h3(&format!("Service {}{device}", result.services[idx].id))?;
+        print_rpath_notes(&package.services[idx].rpath_notes, out, out_fmt)?;
         let service: Vec<_> = results
             .iter()
             .map(|(fw, res)| (*fw, res.services.get(idx).unwrap()))
             .collect();
+        print_bundled_middleware(&package.services[idx].libs, &service, out)?;
+        print_cpu_mismatches(
+            &package.services[idx],
+            &package.services[idx].info.bundled_bins,
+            &service,
+            out,
+        )?;
+        print_missing_commands(&package.services[idx].info.scripts, &service, out)?;
+        print_missing_luna_calls(&package.services[idx].info.luna_calls, &own, &service, out)?;
+        print_ungranted_luna_calls(
+            &package.services[idx].info.luna_calls,
+            &package.app.info.required_permissions,
+            &service,
+            out,
+        )?;
         if !args.no_summary {
             print_component_summary(&service, out, out_fmt)?;
         }
@@ -177,6 +351,351 @@ fn print_package_report(
     return Ok(());
 }
 
+/// Compare each bundled copy of a well-known middleware library, such as SDL2
+/// or `GLib`, with the firmware's. Advisory — never changes the verdict.
+fn print_bundled_middleware(
+    libs: &[LibraryInfo],
+    results: &[(&Firmware, &ComponentVerifyResult)],
+    out: &mut Box<dyn ReportOutput>,
+) -> Result<(), Error> {
+    for lib in libs {
+        let Some(middleware) = Middleware::of_library(lib) else {
+            continue;
+        };
+        let version = middleware
+            .bundled_version(lib)
+            .map_or_else(String::new, |version| format!(" {version}"));
+        out.write_fmt(format_args!(
+            "Bundles {middleware}{version} as {}.\n",
+            lib.name
+        ))?;
+        let mut redundant = Vec::new();
+        let mut same_soname = Vec::new();
+        for (fw, _) in results {
+            match BundledCopy::compare(fw, lib) {
+                Some(BundledCopy::Older { firmware }) => out.write_fmt(format_args!(
+                    "* Older than the firmware's {middleware} {firmware} on webOS {}, which it shadows\n",
+                    fw.info.release
+                ))?,
+                Some(BundledCopy::Newer { firmware }) => out.write_fmt(format_args!(
+                    "* Newer than the firmware's {middleware} {firmware} on webOS {}, which it replaces for this app\n",
+                    fw.info.release
+                ))?,
+                Some(BundledCopy::Redundant) => redundant.push(fw.info.release.to_string()),
+                Some(BundledCopy::SameSoname) => same_soname.push(fw.info.release.to_string()),
+                _ => {}
+            }
+        }
+        if !redundant.is_empty() {
+            out.write_fmt(format_args!(
+                "* Redundant on webOS {}, which ship the same version\n",
+                redundant.join(", ")
+            ))?;
+        }
+        if !same_soname.is_empty() {
+            out.write_fmt(format_args!(
+                "* The firmware also has {} on webOS {}, but the versions cannot be compared\n",
+                lib.name,
+                same_soname.join(", ")
+            ))?;
+        }
+        out.write_fmt(format_args!("\n"))?;
+    }
+    return Ok(());
+}
+
+/// List the component's ARM binaries, bundled ones included, that are built
+/// for more than a firmware's CPU baseline. Grouped like
+/// [`print_missing_commands`]; firmwares extracted before the baseline was
+/// recorded are left out. Advisory — never changes the verdict.
+fn print_cpu_mismatches<T>(
+    component: &Component<T>,
+    bundled_bins: &[Component<()>],
+    results: &[(&Firmware, &ComponentVerifyResult)],
+    out: &mut Box<dyn ReportOutput>,
+) -> Result<(), Error> {
+    let mut groups: Vec<(Vec<CpuMismatch>, Vec<String>)> = Vec::new();
+    for (fw, _) in results {
+        let Some(baseline) = &fw.info.cpu else {
+            continue;
+        };
+        let mut mismatches = cpu_mismatches(component, baseline);
+        for bin in bundled_bins {
+            mismatches.extend(cpu_mismatches(bin, baseline));
+        }
+        if mismatches.is_empty() {
+            continue;
+        }
+        let release = fw.info.release.to_string();
+        match groups.iter_mut().find(|(seen, _)| *seen == mismatches) {
+            Some((_, releases)) => releases.push(release),
+            None => groups.push((mismatches, vec![release])),
+        }
+    }
+    for (mismatches, releases) in groups {
+        out.h5(&format!(
+            "Binaries the CPU of webOS {} may not run",
+            releases.join(", ")
+        ))?;
+        for entry in mismatches {
+            out.write_fmt(format_args!(
+                "* {} {}\n",
+                entry.binary,
+                entry.reasons.join("; ")
+            ))?;
+        }
+        out.write_fmt(format_args!("\n"))?;
+    }
+    return Ok(());
+}
+
+/// List the commands the component's shell scripts run that a firmware does
+/// not have. Firmwares with the same list share one block; those extracted
+/// before executables were recorded are left out. Advisory — never changes the
+/// verdict.
+fn print_missing_commands(
+    scripts: &[ShellScript],
+    results: &[(&Firmware, &ComponentVerifyResult)],
+    out: &mut Box<dyn ReportOutput>,
+) -> Result<(), Error> {
+    if scripts.is_empty() {
+        return Ok(());
+    }
+    let mut groups: Vec<(Vec<MissingCommand>, Vec<String>)> = Vec::new();
+    for (fw, _) in results {
+        let Some(executables) = fw.executables() else {
+            continue;
+        };
+        let missing = missing_commands(scripts, executables);
+        if missing.is_empty() {
+            continue;
+        }
+        let release = fw.info.release.to_string();
+        match groups.iter_mut().find(|(seen, _)| *seen == missing) {
+            Some((_, releases)) => releases.push(release),
+            None => groups.push((missing, vec![release])),
+        }
+    }
+    for (missing, releases) in groups {
+        out.h5(&format!(
+            "Commands run by scripts but missing on webOS {}",
+            releases.join(", ")
+        ))?;
+        for entry in missing {
+            out.write_fmt(format_args!(
+                "* {}, run by {}\n",
+                entry.name,
+                entry.scripts.join(", ")
+            ))?;
+        }
+        out.write_fmt(format_args!("\n"))?;
+    }
+    return Ok(());
+}
+
+/// List the `luna://` calls the component makes that a firmware has no service
+/// or method for. Grouped like [`print_missing_commands`]; firmwares extracted
+/// before Luna services were recorded, or without any, are left out. Advisory
+/// — never changes the verdict.
+fn print_missing_luna_calls(
+    calls: &[LunaCall],
+    own: &[&str],
+    results: &[(&Firmware, &ComponentVerifyResult)],
+    out: &mut Box<dyn ReportOutput>,
+) -> Result<(), Error> {
+    if calls.is_empty() {
+        return Ok(());
+    }
+    let mut groups: Vec<(Vec<MissingLunaCall>, Vec<String>)> = Vec::new();
+    for (fw, _) in results {
+        let Some(luna) = fw.luna().filter(|luna| !luna.services.is_empty()) else {
+            continue;
+        };
+        let missing = missing_luna_calls(calls, own, luna);
+        if missing.is_empty() {
+            continue;
+        }
+        let release = fw.info.release.to_string();
+        match groups.iter_mut().find(|(seen, _)| *seen == missing) {
+            Some((_, releases)) => releases.push(release),
+            None => groups.push((missing, vec![release])),
+        }
+    }
+    for (missing, releases) in groups {
+        out.h5(&format!(
+            "Luna calls with no service or method on webOS {}",
+            releases.join(", ")
+        ))?;
+        for entry in missing {
+            let call = &entry.call;
+            let uri = match &call.method {
+                Some(method) => format!("luna://{}/{method}", call.service),
+                None => format!("luna://{}", call.service),
+            };
+            let what = if entry.service_missing {
+                "no such service"
+            } else {
+                "no such method"
+            };
+            out.write_fmt(format_args!(
+                "* {uri}: {what}, in {}\n",
+                call.files.join(", ")
+            ))?;
+        }
+        out.write_fmt(format_args!("\n"))?;
+    }
+    return Ok(());
+}
+
+/// List the groups in the app's `requiredPermissions` that a firmware does not
+/// define, naming the last older firmware that did for a legacy one. Only the
+/// firmwares checked in this run are looked at, so a group that none of them
+/// defines may still be defined elsewhere. Grouped like
+/// [`print_missing_commands`]. Advisory — never changes the verdict.
+fn print_unknown_permissions(
+    required: &[String],
+    results: &[(&Firmware, &ComponentVerifyResult)],
+    out: &mut Box<dyn ReportOutput>,
+) -> Result<(), Error> {
+    if required.is_empty() {
+        return Ok(());
+    }
+    let firmwares: Vec<&Firmware> = results.iter().map(|(fw, _)| *fw).collect();
+    let mut groups: Vec<(Vec<UnknownPermission>, Vec<String>)> = Vec::new();
+    for fw in &firmwares {
+        let unknown = unknown_permissions(required, fw, &firmwares);
+        if unknown.is_empty() {
+            continue;
+        }
+        let release = fw.info.release.to_string();
+        match groups.iter_mut().find(|(seen, _)| *seen == unknown) {
+            Some((_, releases)) => releases.push(release),
+            None => groups.push((unknown, vec![release])),
+        }
+    }
+    for (unknown, releases) in groups {
+        out.h5(&format!(
+            "Required permissions not defined on webOS {}",
+            releases.join(", ")
+        ))?;
+        for entry in unknown {
+            match entry.last_defined {
+                Some(release) => out.write_fmt(format_args!(
+                    "* {}, a legacy group last defined on webOS {release}\n",
+                    entry.name
+                ))?,
+                None => out.write_fmt(format_args!(
+                    "* {}, not defined on any of the checked firmwares\n",
+                    entry.name
+                ))?,
+            }
+        }
+        out.write_fmt(format_args!("\n"))?;
+    }
+    return Ok(());
+}
+
+/// List the `luna://` calls the component makes that none of the app's
+/// permission groups grant on a firmware. Grouped like
+/// [`print_missing_commands`]. Advisory — never changes the verdict.
+fn print_ungranted_luna_calls(
+    calls: &[LunaCall],
+    required: &[String],
+    results: &[(&Firmware, &ComponentVerifyResult)],
+    out: &mut Box<dyn ReportOutput>,
+) -> Result<(), Error> {
+    if calls.is_empty() || required.is_empty() {
+        return Ok(());
+    }
+    let mut groups: Vec<(Vec<UngrantedLunaCall>, Vec<String>)> = Vec::new();
+    for (fw, _) in results {
+        let Some(luna) = fw.luna() else {
+            continue;
+        };
+        let ungranted = ungranted_luna_calls(calls, required, luna);
+        if ungranted.is_empty() {
+            continue;
+        }
+        let release = fw.info.release.to_string();
+        match groups.iter_mut().find(|(seen, _)| *seen == ungranted) {
+            Some((_, releases)) => releases.push(release),
+            None => groups.push((ungranted, vec![release])),
+        }
+    }
+    for (ungranted, releases) in groups {
+        out.h5(&format!(
+            "Luna calls outside the required permissions on webOS {}",
+            releases.join(", ")
+        ))?;
+        for entry in ungranted {
+            let call = &entry.call;
+            out.write_fmt(format_args!(
+                "* luna://{}/{}, granted by {}, in {}\n",
+                call.service,
+                call.method.as_deref().unwrap_or_default(),
+                entry.groups.join(", "),
+                call.files.join(", ")
+            ))?;
+        }
+        out.write_fmt(format_args!("\n"))?;
+    }
+    return Ok(());
+}
+
+/// Say which data directory each firmware came from, once the firmwares come
+/// from more than one.
+fn print_firmware_sources(
+    results: &[(&Firmware, PackageVerifyResult)],
+    out: &mut Box<dyn ReportOutput>,
+) -> Result<(), Error> {
+    let first = &results.first().unwrap().0.data_dir;
+    if results.iter().all(|(fw, _)| fw.data_dir == *first) {
+        return Ok(());
+    }
+    out.write_fmt(format_args!("Firmware data:\n"))?;
+    for (fw, _) in results {
+        out.write_fmt(format_args!(
+            "* webOS {} ({}) from {}\n",
+            fw.info.release,
+            fw.info.dir_name(),
+            fw.data_dir.to_string_lossy()
+        ))?;
+    }
+    out.write_fmt(format_args!("\n"))?;
+    return Ok(());
+}
+
+/// With `--jailed`, list the libraries the package looked for that a firmware
+/// has only outside the developer-mode jail. They were treated as missing.
+/// Firmwares with the same list share one block.
+fn print_outside_jail(
+    outside_jail: &[(&Firmware, BTreeSet<String>)],
+    out: &mut Box<dyn ReportOutput>,
+) -> Result<(), Error> {
+    let mut groups: Vec<(&BTreeSet<String>, Vec<String>)> = Vec::new();
+    for (fw, hidden) in outside_jail {
+        if hidden.is_empty() {
+            continue;
+        }
+        let release = fw.info.release.to_string();
+        match groups.iter_mut().find(|(seen, _)| *seen == hidden) {
+            Some((_, releases)) => releases.push(release),
+            None => groups.push((hidden, vec![release])),
+        }
+    }
+    for (hidden, releases) in groups {
+        out.h5(&format!(
+            "Libraries only outside the jail on webOS {}",
+            releases.join(", ")
+        ))?;
+        for name in hidden {
+            out.write_fmt(format_args!("* {name}\n"))?;
+        }
+        out.write_fmt(format_args!("\n"))?;
+    }
+    return Ok(());
+}
+
 /// Warn when the package was not built by a webOS packager. Which control
 /// fields gave it away is of no use to the author — say what to do instead.
 fn print_packager_warning(
@@ -227,6 +746,33 @@ fn print_install_hooks(
     return Ok(());
 }
 
+/// Note each rpath entry the library discovery did not follow. Whatever the
+/// loader would find there is missing from the report, so a library reported
+/// as missing may only be missing here. A note only — the firmware checks below
+/// still run.
+fn print_rpath_notes(
+    notes: &[RpathNote],
+    out: &mut Box<dyn ReportOutput>,
+    out_fmt: &OutputFormat,
+) -> Result<(), Error> {
+    if notes.is_empty() {
+        return Ok(());
+    }
+    let mark = if *out_fmt == OutputFormat::Markdown {
+        ":warning:"
+    } else {
+        "Warning:"
+    };
+    for note in notes {
+        out.write_fmt(format_args!(
+            "{mark} Rpath entry `{}` of {} was not searched: {}.\n",
+            note.entry, note.binary, note.issue
+        ))?;
+    }
+    out.write_fmt(format_args!("\n"))?;
+    return Ok(());
+}
+
 fn print_component_summary(
     results: &[(&Firmware, &ComponentVerifyResult)],
     out: &mut Box<dyn ReportOutput>,
@@ -276,6 +822,17 @@ fn print_component_summary(
                 .collect(),
         ));
     }
+    for (idx, plugin) in result.plugins.iter().enumerate() {
+        table.add_row(Row::new(
+            iter::once(Cell::new(&format!("plugin {}", plugin.name())))
+                .chain(
+                    results
+                        .iter()
+                        .map(|(_, result)| out.result_cell(&result.plugins[idx], out_fmt)),
+                )
+                .collect(),
+        ));
+    }
     out.print_table(&table)?;
     return Ok(());
 }
@@ -293,6 +850,7 @@ fn print_component_details(
     out.h4(result.exe.name())?;
     if results.iter().all(|r| r.1.is_good()) && !results.iter().any(|r| has_notes(&r.1.exe)) {
         out.write_fmt(format_args!("All OK\n"))?;
+        print_advisories(results, out, out_fmt)?;
         return Ok(true);
     }
     for (fw, result) in results {
@@ -321,9 +879,202 @@ fn print_component_details(
             }
         }
     }
+    print_advisories(results, out, out_fmt)?;
     return Ok(false);
 }
 
+/// Everything the report notes about a native component that never changes
+/// its verdict.
+fn print_advisories(
+    results: &[(&Firmware, &ComponentVerifyResult)],
+    out: &mut Box<dyn ReportOutput>,
+    out_fmt: &OutputFormat,
+) -> Result<(), Error> {
+    print_plugin_details(results, out, out_fmt)?;
+    print_missing_dlopen(results, out)?;
+    print_version_conflicts(results, out)?;
+    print_symbol_conflicts(results, out, out_fmt)?;
+    return Ok(());
+}
+
+/// The reasons behind every plugin that fails or warns on some firmware.
+fn print_plugin_details(
+    results: &[(&Firmware, &ComponentVerifyResult)],
+    out: &mut Box<dyn ReportOutput>,
+    out_fmt: &OutputFormat,
+) -> Result<(), Error> {
+    let (_, first) = *results.first().unwrap();
+    for (index, plugin) in first.plugins.iter().enumerate() {
+        if !results
+            .iter()
+            .any(|(_, result)| has_notes(&result.plugins[index]))
+        {
+            continue;
+        }
+        out.h4(&format!("plugin {}", plugin.name()))?;
+        for (fw, result) in results {
+            if let Some(bin) = notes(&result.plugins[index]) {
+                out.h5(&format!("On {}", fw.info))?;
+                print_bin_verify_details(bin, out, out_fmt)?;
+                out.write_fmt(format_args!("\n"))?;
+            }
+        }
+    }
+    return Ok(());
+}
+
+/// List the shared objects the component may `dlopen` that no firmware
+/// library or bundled file provides. Firmwares with the same list share one
+/// block.
+fn print_missing_dlopen(
+    results: &[(&Firmware, &ComponentVerifyResult)],
+    out: &mut Box<dyn ReportOutput>,
+) -> Result<(), Error> {
+    let mut groups: Vec<(&[MissingDlopen], Vec<String>)> = Vec::new();
+    for (fw, result) in results {
+        if result.missing_dlopen.is_empty() {
+            continue;
+        }
+        let release = fw.info.release.to_string();
+        match groups
+            .iter_mut()
+            .find(|(seen, _)| *seen == result.missing_dlopen.as_slice())
+        {
+            Some((_, releases)) => releases.push(release),
+            None => groups.push((&result.missing_dlopen, vec![release])),
+        }
+    }
+    for (missing, releases) in groups {
+        out.h5(&format!(
+            "Libraries loaded at run time but found nowhere on webOS {}",
+            releases.join(", ")
+        ))?;
+        for entry in missing {
+            out.write_fmt(format_args!(
+                "* {}, named in {}\n",
+                entry.name,
+                entry.referenced_by.join(", ")
+            ))?;
+        }
+        out.write_fmt(format_args!("\n"))?;
+    }
+    return Ok(());
+}
+
+/// List each library the process loads in more than one version, with the
+/// `DT_NEEDED` chain that pulls in every copy. Firmwares with the same list
+/// share one block. Advisory — never changes the verdict.
+fn print_version_conflicts(
+    results: &[(&Firmware, &ComponentVerifyResult)],
+    out: &mut Box<dyn ReportOutput>,
+) -> Result<(), Error> {
+    let mut groups: Vec<(&[VersionConflict], Vec<String>)> = Vec::new();
+    for (fw, result) in results {
+        if result.version_conflicts.is_empty() {
+            continue;
+        }
+        let release = fw.info.release.to_string();
+        match groups
+            .iter_mut()
+            .find(|(seen, _)| *seen == result.version_conflicts.as_slice())
+        {
+            Some((_, releases)) => releases.push(release),
+            None => groups.push((&result.version_conflicts, vec![release])),
+        }
+    }
+    for (conflicts, releases) in groups {
+        out.h5(&format!(
+            "Libraries loaded in more than one version on webOS {}",
+            releases.join(", ")
+        ))?;
+        for conflict in conflicts {
+            out.write_fmt(format_args!("* {}\n", conflict.family))?;
+            for copy in &conflict.copies {
+                out.write_fmt(format_args!(
+                    "  * {} via {}\n",
+                    scope_label(&copy.object),
+                    copy.path.join(" → ")
+                ))?;
+            }
+        }
+        out.write_fmt(format_args!("\n"))?;
+    }
+    return Ok(());
+}
+
+/// List the symbols a bundled library defines again on top of another object
+/// in the same process. Firmwares with the same list share one block. Only
+/// conflicts the package has a part in are listed; duplicates among firmware
+/// libraries are counted. Advisory — never changes the verdict.
+fn print_symbol_conflicts(
+    results: &[(&Firmware, &ComponentVerifyResult)],
+    out: &mut Box<dyn ReportOutput>,
+    out_fmt: &OutputFormat,
+) -> Result<(), Error> {
+    let mut groups: Vec<(Vec<&SymbolConflict>, usize, Vec<String>)> = Vec::new();
+    for (fw, result) in results {
+        let (package, firmware): (Vec<&SymbolConflict>, Vec<&SymbolConflict>) = result
+            .symbol_conflicts
+            .iter()
+            .partition(|conflict| conflict.involves_package());
+        if package.is_empty() {
+            continue;
+        }
+        let release = fw.info.release.to_string();
+        match groups
+            .iter_mut()
+            .find(|(seen, count, _)| *seen == package && *count == firmware.len())
+        {
+            Some((_, _, releases)) => releases.push(release),
+            None => groups.push((package, firmware.len(), vec![release])),
+        }
+    }
+    for (conflicts, firmware_only, releases) in groups {
+        out.h5(&format!(
+            "Symbols defined more than once on webOS {}",
+            releases.join(", ")
+        ))?;
+        let fold = *out_fmt == OutputFormat::Markdown && conflicts.len() > SYMBOL_FOLD_THRESHOLD;
+        if fold {
+            out.write_fmt(format_args!(
+                "<details>\n<summary>{} symbols defined more than once</summary>\n\n",
+                conflicts.len()
+            ))?;
+        }
+        for conflict in conflicts {
+            let losers: Vec<String> = conflict.losers.iter().map(scope_label).collect();
+            let note = if conflict.interposes_firmware() {
+                " — firmware code calls the bundled copy"
+            } else {
+                ""
+            };
+            out.write_fmt(format_args!(
+                "* Symbol {} from {} hides the one in {}{note}\n",
+                conflict.symbol,
+                scope_label(&conflict.winner),
+                losers.join(", ")
+            ))?;
+        }
+        if fold {
+            out.write_fmt(format_args!("</details>\n"))?;
+        }
+        if firmware_only > 0 {
+            out.write_fmt(format_args!(
+                "* {firmware_only} more defined more than once among firmware libraries\n"
+            ))?;
+        }
+        out.write_fmt(format_args!("\n"))?;
+    }
+    return Ok(());
+}
+
+fn scope_label(object: &ScopeObject) -> String {
+    if object.firmware {
+        return format!("{} (firmware)", object.name);
+    }
+    return format!("{} (bundled)", object.name);
+}
+
 /// A long list of undefined symbols is folded into a collapsible `<details>`
 /// block rather than emitted inline.
 const SYMBOL_FOLD_THRESHOLD: usize = 10;
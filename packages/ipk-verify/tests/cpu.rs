@@ -0,0 +1,99 @@
+//! ARM binaries built for more than the firmware's CPU baseline.
+//!
+//! A binary built with `-march=armv8-a` or for another float ABI links fine and
+//! may still stop with `SIGILL` on an older chip, so it is reported as advice.
+
+use bin_lib::{ArmAttributes, BinaryInfo, LibraryInfo, LibraryPriority};
+use ipk_lib::Component;
+use verify_lib::ipk::{CpuMismatch, cpu_mismatches};
+
+const ARMV7_SOFTFP: ArmAttributes = ArmAttributes {
+    cpu_arch: Some(10),
+    fp_arch: Some(3),
+    simd_arch: Some(1),
+    vfp_args: None,
+};
+
+fn lib(name: &str, arm: Option<ArmAttributes>) -> LibraryInfo {
+    LibraryInfo {
+        name: name.to_string(),
+        package: None,
+        needed: vec![],
+        symbols: vec![],
+        names: vec![name.to_string()],
+        undefined: vec![],
+        undefined_lazy: vec![],
+        dlopen: vec![],
+        arm,
+        rpath: vec![],
+        priority: LibraryPriority::Rpath,
+    }
+}
+
+fn app(arm: Option<ArmAttributes>, libs: Vec<LibraryInfo>) -> Component<()> {
+    Component {
+        id: "test".to_string(),
+        info: (),
+        exe: Some(BinaryInfo {
+            name: "app".to_string(),
+            rpath: vec![],
+            needed: vec![],
+            undefined: vec![],
+            undefined_lazy: vec![],
+            symbols: vec![],
+            arch: None,
+            dlopen: vec![],
+            arm,
+        }),
+        libs,
+        rpath_notes: vec![],
+        plugins: vec![],
+    }
+}
+
+#[test]
+fn flags_newer_architectures_and_other_float_abis() {
+    let armv8 = ArmAttributes {
+        cpu_arch: Some(14),
+        ..ARMV7_SOFTFP
+    };
+    let hard = ArmAttributes {
+        vfp_args: Some(1),
+        ..ARMV7_SOFTFP
+    };
+    let component = app(
+        Some(armv8),
+        vec![
+            lib("libhard.so", Some(hard)),
+            lib("libsame.so", Some(ARMV7_SOFTFP)),
+            lib("libunknown.so", None),
+        ],
+    );
+
+    assert_eq!(
+        cpu_mismatches(&component, &ARMV7_SOFTFP),
+        vec![
+            CpuMismatch {
+                binary: "app".to_string(),
+                reasons: vec!["needs ARMv8-A, but the firmware is built for ARMv7".to_string()],
+            },
+            CpuMismatch {
+                binary: "libhard.so".to_string(),
+                reasons: vec!["is hard-float, but the firmware is soft-float".to_string()],
+            },
+        ]
+    );
+}
+
+#[test]
+fn passes_binaries_the_baseline_runs() {
+    let older = ArmAttributes {
+        cpu_arch: Some(8),
+        fp_arch: Some(2),
+        simd_arch: None,
+        vfp_args: None,
+    };
+    let component = app(Some(older), vec![lib("libsame.so", Some(ARMV7_SOFTFP))]);
+
+    assert!(cpu_mismatches(&component, &ARMV7_SOFTFP).is_empty());
+}
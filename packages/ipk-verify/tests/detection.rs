@@ -4,7 +4,7 @@
 //! detection facts to a per-firmware `CompatVerdict` and the `is_good` exit
 //! signal, without needing a real `.ipk` on disk.
 
-use bin_lib::LibraryInfo;
+use bin_lib::{ElfArch, LibraryInfo};
 use fw_lib::WebEngine;
 use ipk_lib::{AppInfo, Component, Package, ServiceInfo};
 use semver::Version;
@@ -24,6 +24,7 @@ fn web_app(es: EsLevel) -> Component<AppInfo> {
             title: "Example".to_string(),
             app_description: None,
             main: "index.html".to_string(),
+            required_permissions: vec![],
             web: Some(WebAppDetection {
                 framework: Some(FrameworkInfo::new(
                     FrameworkKind::React,
@@ -39,9 +40,13 @@ fn web_app(es: EsLevel) -> Component<AppInfo> {
             }),
             bundled: vec![],
             bundled_bins: vec![],
+            scripts: vec![],
+            luna_calls: vec![],
         },
         exe: None,
         libs: vec![],
+        rpath_notes: vec![],
+        plugins: vec![],
     }
 }
 
@@ -61,9 +66,13 @@ fn node_service(id: &str, es: EsLevel) -> Component<ServiceInfo> {
             }),
             bundled: vec![],
             bundled_bins: vec![],
+            scripts: vec![],
+            luna_calls: vec![],
         },
         exe: None,
         libs: vec![],
+        rpath_notes: vec![],
+        plugins: vec![],
     }
 }
 
@@ -78,7 +87,7 @@ fn package(app: Component<AppInfo>, services: Vec<Component<ServiceInfo>>) -> Pa
     }
 }
 
-fn no_libs(_: &str) -> Option<LibraryInfo> {
+fn no_libs(_: &str, _: Option<ElfArch>) -> Option<LibraryInfo> {
     None
 }
 
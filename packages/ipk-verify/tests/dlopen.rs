@@ -0,0 +1,114 @@
+//! Plugins and libraries a component loads with `dlopen`.
+//!
+//! Neither shows up in `DT_NEEDED`: a plugin is found by its directory, a
+//! runtime-loaded library by its name in the read-only data. Both are checked
+//! against the firmware and the executable's global scope, but a framework
+//! can carry on without either, so neither gates the verdict.
+
+use bin_lib::{BinaryInfo, LibraryInfo, LibraryPriority};
+use ipk_lib::Component;
+use verify_lib::ipk::{ComponentBinVerifyResult, MissingDlopen};
+use verify_lib::{Verify, VerifyResult};
+
+fn lib(name: &str, symbols: &[&str], undefined: &[&str]) -> LibraryInfo {
+    let to_strings = |items: &[&str]| -> Vec<String> {
+        return items.iter().map(std::string::ToString::to_string).collect();
+    };
+    LibraryInfo {
+        name: name.to_string(),
+        package: None,
+        needed: vec![],
+        symbols: to_strings(symbols),
+        names: vec![name.to_string()],
+        undefined: to_strings(undefined),
+        undefined_lazy: vec![],
+        dlopen: vec![],
+        arm: None,
+        rpath: vec![],
+        priority: LibraryPriority::Package,
+    }
+}
+
+fn app(exe_symbols: &[&str], dlopen: &[&str], plugins: Vec<LibraryInfo>) -> Component<()> {
+    Component {
+        id: "test".to_string(),
+        info: (),
+        exe: Some(BinaryInfo {
+            name: "app".to_string(),
+            rpath: vec![],
+            needed: vec!["libc.so.6".to_string()],
+            undefined: vec![],
+            undefined_lazy: vec![],
+            symbols: exe_symbols
+                .iter()
+                .map(std::string::ToString::to_string)
+                .collect(),
+            arch: None,
+            dlopen: dlopen
+                .iter()
+                .map(std::string::ToString::to_string)
+                .collect(),
+            arm: None,
+        }),
+        libs: vec![],
+        rpath_notes: vec![],
+        plugins,
+    }
+}
+
+fn firmware(name: &str) -> Option<LibraryInfo> {
+    return match name {
+        "libc.so.6" => Some(lib("libc.so.6", &["malloc"], &[])),
+        "libEGL.so.1" => Some(lib("libEGL.so.1", &["eglGetDisplay"], &[])),
+        _ => None,
+    };
+}
+
+/// A plugin may import what the executable exports and what the firmware
+/// libraries in the global scope define.
+#[test]
+fn plugin_resolves_against_executable_and_firmware() {
+    let plugin = lib("libqwayland.so", &[], &["host_api", "malloc"]);
+    let result = app(&["host_api"], &[], vec![plugin]).verify(&|name, _| firmware(name));
+
+    assert_eq!(
+        result.plugins,
+        vec![ComponentBinVerifyResult::Ok {
+            name: "libqwayland.so".to_string()
+        }]
+    );
+}
+
+#[test]
+fn broken_plugin_does_not_fail_the_component() {
+    let plugin = lib("libgstapp.so", &[], &["gst_app_sink_pull"]);
+    let result = app(&[], &[], vec![plugin]).verify(&|name, _| firmware(name));
+
+    let ComponentBinVerifyResult::Failed(failure) = &result.plugins[0] else {
+        panic!("expected the plugin to fail, got {:?}", result.plugins);
+    };
+    assert_eq!(failure.undefined_sym, vec!["gst_app_sink_pull"]);
+    assert!(result.is_good());
+}
+
+/// A name that neither the firmware nor the package provides is reported; one
+/// the firmware has, or that names a plugin, is not.
+#[test]
+fn reports_dlopen_names_that_resolve_nowhere() {
+    let plugin = lib("libqwayland.so", &[], &[]);
+    let result = app(
+        &[],
+        &["libEGL.so.1", "libqwayland.so", "libwayland-client.so.0"],
+        vec![plugin],
+    )
+    .verify(&|name, _| firmware(name));
+
+    assert_eq!(
+        result.missing_dlopen,
+        vec![MissingDlopen {
+            name: "libwayland-client.so.0".to_string(),
+            referenced_by: vec!["app".to_string()],
+        }]
+    );
+    assert!(result.is_good());
+}
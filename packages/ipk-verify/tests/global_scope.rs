@@ -32,6 +32,8 @@ fn bundled_lib(name: &str, needed: &[&str], symbols: &[&str], undefined: &[&str]
             .map(std::string::ToString::to_string)
             .collect(),
         undefined_lazy: vec![],
+        dlopen: vec![],
+        arm: None,
         rpath: vec![],
         priority: LibraryPriority::Rpath,
     }
@@ -50,8 +52,14 @@ fn component(exe_needed: &[&str], libs: Vec<LibraryInfo>) -> Component<()> {
                 .collect(),
             undefined: vec![],
             undefined_lazy: vec![],
+            symbols: vec![],
+            arch: None,
+            dlopen: vec![],
+            arm: None,
         }),
         libs,
+        rpath_notes: vec![],
+        plugins: vec![],
     }
 }
 
@@ -78,7 +86,7 @@ fn sibling_library_satisfies_undefined_symbol() {
     let component = component(&["libEGL.so.1", "libGLESv2.so.2"], vec![libegl, libgles]);
 
     // No firmware libraries available.
-    let result = component.verify(&|_name| None);
+    let result = component.verify(&|_name, _| None);
 
     assert!(
         matches!(
@@ -107,7 +115,7 @@ fn truly_missing_symbol_still_fails() {
     let libgles = bundled_lib("libGLESv2.so.2", &[], &["glActiveTexture@GLES_3_2"], &[]);
     let component = component(&["libEGL.so.1", "libGLESv2.so.2"], vec![libegl, libgles]);
 
-    let result = component.verify(&|_name| None);
+    let result = component.verify(&|_name, _| None);
 
     match lib_result(&result, "libEGL.so.1") {
         ComponentBinVerifyResult::Failed(r) => {
@@ -116,3 +124,28 @@ fn truly_missing_symbol_still_fails() {
         other => panic!("libEGL.so.1 should fail on the missing symbol, got {other:?}"),
     }
 }
+
+/// A plugin that imports what an `-rdynamic` executable exports resolves
+/// against the executable, which heads the global scope.
+#[test]
+fn executable_exports_satisfy_library_imports() {
+    let plugin = bundled_lib(
+        "libplugin.so",
+        &[],
+        &["plugin_init"],
+        &["host_register_plugin"],
+    );
+    let mut component = component(&["libplugin.so"], vec![plugin]);
+    component.exe.as_mut().unwrap().symbols = vec!["host_register_plugin".to_string()];
+
+    let result = component.verify(&|_name, _| None);
+
+    assert!(
+        matches!(
+            lib_result(&result, "libplugin.so"),
+            ComponentBinVerifyResult::Ok { .. }
+        ),
+        "the executable exports host_register_plugin; got {:?}",
+        lib_result(&result, "libplugin.so")
+    );
+}
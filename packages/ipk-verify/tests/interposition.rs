@@ -0,0 +1,170 @@
+//! Symbols defined by more than one object in the executable's global scope.
+//!
+//! The loader binds every lookup to the first object in load order that
+//! defines the name: the executable, then its `DT_NEEDED` libraries breadth
+//! first. A bundled `libz.so` loaded ahead of the firmware's copy therefore
+//! answers the firmware's own calls too, which can crash system libraries.
+
+use bin_lib::{BinaryInfo, LibraryInfo, LibraryPriority};
+use ipk_lib::Component;
+use verify_lib::ipk::ScopeObject;
+use verify_lib::{Verify, VerifyResult};
+
+fn lib(name: &str, needed: &[&str], symbols: &[&str], priority: LibraryPriority) -> LibraryInfo {
+    let mut symbols: Vec<String> = symbols
+        .iter()
+        .map(std::string::ToString::to_string)
+        .collect();
+    symbols.sort_unstable();
+    LibraryInfo {
+        name: name.to_string(),
+        package: None,
+        needed: needed
+            .iter()
+            .map(std::string::ToString::to_string)
+            .collect(),
+        symbols,
+        names: vec![name.to_string()],
+        undefined: vec![],
+        undefined_lazy: vec![],
+        dlopen: vec![],
+        arm: None,
+        rpath: vec![],
+        priority,
+    }
+}
+
+fn app(needed: &[&str], libs: Vec<LibraryInfo>) -> Component<()> {
+    Component {
+        id: "test".to_string(),
+        info: (),
+        exe: Some(BinaryInfo {
+            name: "app".to_string(),
+            rpath: vec![],
+            needed: needed
+                .iter()
+                .map(std::string::ToString::to_string)
+                .collect(),
+            undefined: vec![],
+            undefined_lazy: vec![],
+            symbols: vec![],
+            arch: None,
+            dlopen: vec![],
+            arm: None,
+        }),
+        libs,
+        rpath_notes: vec![],
+        plugins: vec![],
+    }
+}
+
+fn object(name: &str, firmware: bool) -> ScopeObject {
+    ScopeObject {
+        name: name.to_string(),
+        firmware,
+    }
+}
+
+/// The bundled `libz.so.1.2.13` comes first in `DT_NEEDED`, so its `deflate`
+/// hides the one in the firmware `libz.so.1` that `libpng16.so.16` pulls in.
+#[test]
+fn bundled_library_interposes_firmware_library() {
+    let bundled = lib(
+        "libz.so.1.2.13",
+        &[],
+        &["deflate@ZLIB_1.2.9", "inflate"],
+        LibraryPriority::Rpath,
+    );
+    let firmware = |name: &str| match name {
+        "libpng16.so.16" => Some(lib(
+            "libpng16.so.16",
+            &["libz.so.1"],
+            &["png_read_info"],
+            LibraryPriority::System,
+        )),
+        "libz.so.1" => Some(lib(
+            "libz.so.1",
+            &[],
+            &["deflate@ZLIB_1.2.0", "deflate@ZLIB_1.2.9"],
+            LibraryPriority::System,
+        )),
+        _ => None,
+    };
+    let result =
+        app(&["libz.so.1.2.13", "libpng16.so.16"], vec![bundled]).verify(&|name, _| firmware(name));
+
+    assert_eq!(
+        result.symbol_conflicts.len(),
+        1,
+        "got {:?}",
+        result.symbol_conflicts
+    );
+    let conflict = &result.symbol_conflicts[0];
+    assert_eq!(conflict.symbol, "deflate");
+    assert_eq!(conflict.winner, object("libz.so.1.2.13", false));
+    assert_eq!(conflict.losers, vec![object("libz.so.1", true)]);
+    assert!(conflict.interposes_firmware());
+    assert!(result.is_good(), "a conflict never fails the component");
+}
+
+/// Load order is breadth first: a library the executable needs directly comes
+/// before one that only a dependency needs, even if that dependency is listed
+/// first.
+#[test]
+fn winner_follows_breadth_first_load_order() {
+    let first = lib("libfirst.so", &["libdeep.so"], &[], LibraryPriority::Rpath);
+    let deep = lib("libdeep.so", &[], &["shared"], LibraryPriority::Rpath);
+    let second = lib("libsecond.so", &[], &["shared"], LibraryPriority::Rpath);
+    let result =
+        app(&["libfirst.so", "libsecond.so"], vec![first, deep, second]).verify(&|_, _| None);
+
+    assert_eq!(
+        result.symbol_conflicts.len(),
+        1,
+        "got {:?}",
+        result.symbol_conflicts
+    );
+    assert_eq!(
+        result.symbol_conflicts[0].winner,
+        object("libsecond.so", false)
+    );
+    assert_eq!(
+        result.symbol_conflicts[0].losers,
+        vec![object("libdeep.so", false)]
+    );
+    assert!(!result.symbol_conflicts[0].interposes_firmware());
+}
+
+/// Duplicates among firmware libraries are recorded, but the package has no
+/// part in them.
+#[test]
+fn firmware_duplicates_do_not_involve_the_package() {
+    let firmware = |name: &str| match name {
+        "libc.so.6" => Some(lib(
+            "libc.so.6",
+            &[],
+            &["fork@GLIBC_2.4"],
+            LibraryPriority::System,
+        )),
+        "libpthread.so.0" => Some(lib(
+            "libpthread.so.0",
+            &["libc.so.6"],
+            &["fork@GLIBC_2.4"],
+            LibraryPriority::System,
+        )),
+        _ => None,
+    };
+    let result = app(&["libpthread.so.0"], vec![]).verify(&|name, _| firmware(name));
+
+    assert_eq!(
+        result.symbol_conflicts.len(),
+        1,
+        "got {:?}",
+        result.symbol_conflicts
+    );
+    assert_eq!(
+        result.symbol_conflicts[0].winner,
+        object("libpthread.so.0", true)
+    );
+    assert!(!result.symbol_conflicts[0].involves_package());
+}
@@ -25,6 +25,8 @@ fn lib(name: &str, symbols: &[&str]) -> LibraryInfo {
         names: vec![name.to_string()],
         undefined: vec![],
         undefined_lazy: vec![],
+        dlopen: vec![],
+        arm: None,
         rpath: vec![],
         priority: LibraryPriority::Rpath,
     }
@@ -42,14 +44,21 @@ fn app(eager: &[&str], lazy: &[&str], libs: Vec<LibraryInfo>) -> Component<()> {
             needed: libs.iter().map(|l| l.name.clone()).collect(),
             undefined: eager.iter().map(std::string::ToString::to_string).collect(),
             undefined_lazy: lazy.iter().map(std::string::ToString::to_string).collect(),
+            symbols: vec![],
+            arch: None,
+            dlopen: vec![],
+            arm: None,
         }),
         libs,
+        rpath_notes: vec![],
+        plugins: vec![],
     }
 }
 
 #[test]
 fn missing_lazy_symbol_only_warns() {
-    let result = app(&[], &["glTexStorage2D"], vec![lib("libGLESv2.so.2", &[])]).verify(&|_| None);
+    let result =
+        app(&[], &["glTexStorage2D"], vec![lib("libGLESv2.so.2", &[])]).verify(&|_, _| None);
 
     match &result.exe {
         ComponentBinVerifyResult::Warned(bin) => {
@@ -66,7 +75,8 @@ fn missing_lazy_symbol_only_warns() {
 
 #[test]
 fn missing_eager_symbol_still_fails() {
-    let result = app(&["someDataSymbol"], &[], vec![lib("libGLESv2.so.2", &[])]).verify(&|_| None);
+    let result =
+        app(&["someDataSymbol"], &[], vec![lib("libGLESv2.so.2", &[])]).verify(&|_, _| None);
 
     match &result.exe {
         ComponentBinVerifyResult::Failed(bin) => {
@@ -80,7 +90,7 @@ fn missing_eager_symbol_still_fails() {
 #[test]
 fn resolved_lazy_symbol_passes() {
     let libgles = lib("libGLESv2.so.2", &["glTexStorage2D@GLES_3_2"]);
-    let result = app(&[], &["glTexStorage2D"], vec![libgles]).verify(&|_| None);
+    let result = app(&[], &["glTexStorage2D"], vec![libgles]).verify(&|_, _| None);
 
     assert!(
         matches!(&result.exe, ComponentBinVerifyResult::Ok { .. }),
@@ -97,7 +107,7 @@ fn eager_and_lazy_together_fail() {
         &["glTexStorage2D"],
         vec![lib("libGLESv2.so.2", &[])],
     )
-    .verify(&|_| None);
+    .verify(&|_, _| None);
 
     match &result.exe {
         ComponentBinVerifyResult::Failed(bin) => {
@@ -0,0 +1,75 @@
+//! Luna calls a package makes, checked against a firmware's Luna services.
+//!
+//! A service or method the firmware lacks only fails the call at run time, so
+//! it is reported as advice.
+
+use std::collections::BTreeSet;
+
+use fw_lib::LunaRegistry;
+use verify_lib::ipk::{MissingLunaCall, missing_luna_calls};
+use webdetect_lib::LunaCall;
+
+fn call(service: &str, method: Option<&str>) -> LunaCall {
+    return LunaCall {
+        service: String::from(service),
+        method: method.map(String::from),
+        files: vec![String::from("js/app.js")],
+    };
+}
+
+fn registry() -> LunaRegistry {
+    let mut registry = LunaRegistry::default();
+    registry.services.insert(
+        String::from("com.webos.audio"),
+        BTreeSet::from([String::from("master/*"), String::from("getStatus")]),
+    );
+    registry
+        .services
+        .insert(String::from("com.webos.settingsservice"), BTreeSet::new());
+    return registry;
+}
+
+#[test]
+fn flags_missing_services_and_methods() {
+    let calls = vec![
+        call("com.webos.audio", Some("master/getVolume")),
+        call("com.webos.audio", Some("setMuted")),
+        call("com.webos.service.tv.broadcast", None),
+        call("com.webos.settingsservice", Some("getSystemSettings")),
+    ];
+
+    assert_eq!(
+        missing_luna_calls(&calls, &[], &registry()),
+        vec![
+            MissingLunaCall {
+                call: call("com.webos.audio", Some("setMuted")),
+                service_missing: false,
+            },
+            MissingLunaCall {
+                call: call("com.webos.service.tv.broadcast", None),
+                service_missing: true,
+            },
+        ]
+    );
+}
+
+#[test]
+fn leaves_out_calls_to_the_packages_own_services() {
+    let calls = vec![
+        call("org.example.app.service", Some("start")),
+        call("org.example.app", None),
+        call("com.webos.service.tv.broadcast", None),
+    ];
+
+    assert_eq!(
+        missing_luna_calls(
+            &calls,
+            &["org.example.app", "org.example.app.service"],
+            &registry()
+        ),
+        vec![MissingLunaCall {
+            call: call("com.webos.service.tv.broadcast", None),
+            service_missing: true,
+        }]
+    );
+}
@@ -0,0 +1,108 @@
+//! An app's `requiredPermissions`, checked against each firmware's ACG groups.
+//!
+//! A group the firmware does not define is denied at run time, and a Luna call
+//! outside the requested groups fails. Both are reported as advice.
+
+use std::fs;
+use std::path::Path;
+
+use fw_lib::Firmware;
+use verify_lib::ipk::{
+    UngrantedLunaCall, UnknownPermission, ungranted_luna_calls, unknown_permissions,
+};
+use webdetect_lib::LunaCall;
+
+fn load_firmware(data_dir: &Path, release: &str, groups: &str) -> Firmware {
+    let dir = data_dir.join(release);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("info.json"),
+        format!(r#"{{"version":"{release}","ota_id":"HE_DTV_TEST","release":"{release}"}}"#),
+    )
+    .unwrap();
+    fs::write(dir.join("index.json"), "{}").unwrap();
+    fs::write(
+        dir.join("luna.json"),
+        format!(r#"{{"services": {{}}, "groups": {groups}}}"#),
+    )
+    .unwrap();
+    return Firmware::load(dir).unwrap();
+}
+
+fn names(items: &[&str]) -> Vec<String> {
+    return items.iter().map(std::string::ToString::to_string).collect();
+}
+
+#[test]
+fn tells_legacy_groups_from_unknown_ones() {
+    let data_dir = tempfile::TempDir::new().unwrap();
+    let old = load_firmware(
+        data_dir.path(),
+        "4.0.0",
+        r#"{"time.query": ["com.webos.service.systemservice/time/*"]}"#,
+    );
+    let new = load_firmware(
+        data_dir.path(),
+        "6.0.0",
+        r#"{"time": ["com.webos.service.systemservice/time/*"]}"#,
+    );
+    let firmwares = [&old, &new];
+    let required = names(&["time", "time.query", "made.up"]);
+
+    assert_eq!(
+        unknown_permissions(&required, &new, &firmwares),
+        vec![
+            UnknownPermission {
+                name: String::from("time.query"),
+                last_defined: Some(old.info.release.clone()),
+            },
+            UnknownPermission {
+                name: String::from("made.up"),
+                last_defined: None,
+            },
+        ]
+    );
+    assert_eq!(
+        unknown_permissions(&required, &old, &firmwares),
+        vec![
+            UnknownPermission {
+                name: String::from("time"),
+                last_defined: None,
+            },
+            UnknownPermission {
+                name: String::from("made.up"),
+                last_defined: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn flags_calls_outside_the_required_groups() {
+    let data_dir = tempfile::TempDir::new().unwrap();
+    let firmware = load_firmware(
+        data_dir.path(),
+        "6.0.0",
+        r#"{"audio.operation": ["com.webos.audio/master/*"], "time": ["com.webos.service.systemservice/time/*"]}"#,
+    );
+    let call = |service: &str, method: &str| LunaCall {
+        service: String::from(service),
+        method: Some(String::from(method)),
+        files: vec![String::from("js/app.js")],
+    };
+    let calls = vec![
+        call("com.webos.audio", "master/getVolume"),
+        call("com.webos.service.systemservice", "time/getSystemTime"),
+        call("com.webos.service.ungrouped", "getStatus"),
+    ];
+    let luna = firmware.luna().unwrap();
+
+    assert_eq!(
+        ungranted_luna_calls(&calls, &names(&["time"]), luna),
+        vec![UngrantedLunaCall {
+            call: call("com.webos.audio", "master/getVolume"),
+            groups: names(&["audio.operation"]),
+        }]
+    );
+    assert!(ungranted_luna_calls(&calls, &[], luna).is_empty());
+}
@@ -0,0 +1,44 @@
+//! Commands a package's shell scripts run, checked against a firmware's
+//! executables.
+//!
+//! A firmware with busybox but not `curl` still starts the service; the script
+//! fails later, so the missing command is reported as advice.
+
+use std::collections::BTreeSet;
+
+use ipk_lib::ShellScript;
+use verify_lib::ipk::{MissingCommand, missing_commands};
+
+fn names(items: &[&str]) -> BTreeSet<String> {
+    return items.iter().map(std::string::ToString::to_string).collect();
+}
+
+#[test]
+fn lists_each_missing_command_with_the_scripts_that_run_it() {
+    let scripts = vec![
+        ShellScript::parse(
+            String::from("bin/start.sh"),
+            "#!/bin/sh\ncurl -s http://example.com | sqlite3 cache.db\n",
+        ),
+        ShellScript::parse(String::from("bin/update.sh"), "curl -O \"$URL\"\nsync\n"),
+    ];
+    let executables = names(&["busybox", "sh", "sqlite3", "sync"]);
+
+    assert_eq!(
+        missing_commands(&scripts, &executables),
+        vec![MissingCommand {
+            name: String::from("curl"),
+            scripts: vec![String::from("bin/start.sh"), String::from("bin/update.sh")],
+        }]
+    );
+}
+
+#[test]
+fn nothing_is_missing_when_the_firmware_has_every_command() {
+    let scripts = vec![ShellScript::parse(
+        String::from("run.sh"),
+        "exec luna-send -n 1 luna://com.webos.service.x/y '{}'\n",
+    )];
+
+    assert!(missing_commands(&scripts, &names(&["luna-send"])).is_empty());
+}
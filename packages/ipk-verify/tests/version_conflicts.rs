@@ -0,0 +1,112 @@
+//! One library loaded in more than one version by the same process.
+//!
+//! A bundled `libssl.so.1.0.0` and the firmware's `libssl.so.1.1`, pulled in
+//! by a firmware `libcurl.so.4`, have different sonames, so the loader maps
+//! both. Whichever comes first answers every `SSL_*` lookup, including the ones
+//! from code built against the other copy.
+
+use bin_lib::{BinaryInfo, LibraryInfo, LibraryPriority};
+use ipk_lib::Component;
+use verify_lib::Verify;
+
+fn lib(name: &str, needed: &[&str], priority: LibraryPriority) -> LibraryInfo {
+    LibraryInfo {
+        name: name.to_string(),
+        package: None,
+        needed: needed
+            .iter()
+            .map(std::string::ToString::to_string)
+            .collect(),
+        symbols: vec![],
+        names: vec![name.to_string()],
+        undefined: vec![],
+        undefined_lazy: vec![],
+        dlopen: vec![],
+        arm: None,
+        rpath: vec![],
+        priority,
+    }
+}
+
+fn app(needed: &[&str], libs: Vec<LibraryInfo>) -> Component<()> {
+    Component {
+        id: "test".to_string(),
+        info: (),
+        exe: Some(BinaryInfo {
+            name: "app".to_string(),
+            rpath: vec![],
+            needed: needed
+                .iter()
+                .map(std::string::ToString::to_string)
+                .collect(),
+            undefined: vec![],
+            undefined_lazy: vec![],
+            symbols: vec![],
+            arch: None,
+            dlopen: vec![],
+            arm: None,
+        }),
+        libs,
+        rpath_notes: vec![],
+        plugins: vec![],
+    }
+}
+
+fn firmware(name: &str) -> Option<LibraryInfo> {
+    match name {
+        "libcurl.so.4" => Some(lib(
+            "libcurl.so.4",
+            &["libssl.so.1.1"],
+            LibraryPriority::System,
+        )),
+        "libssl.so.1.1" => Some(lib("libssl.so.1.1", &[], LibraryPriority::System)),
+        _ => None,
+    }
+}
+
+#[test]
+fn bundled_and_firmware_copies_are_reported_with_their_paths() {
+    let bundled = lib("libssl.so.1.0.0", &[], LibraryPriority::Rpath);
+    let result =
+        app(&["libssl.so.1.0.0", "libcurl.so.4"], vec![bundled]).verify(&|name, _| firmware(name));
+
+    assert_eq!(result.version_conflicts.len(), 1);
+    let conflict = &result.version_conflicts[0];
+    assert_eq!(conflict.family, "libssl");
+    let copies: Vec<(&str, bool, Vec<&str>)> = conflict
+        .copies
+        .iter()
+        .map(|copy| {
+            (
+                copy.object.name.as_str(),
+                copy.object.firmware,
+                copy.path.iter().map(String::as_str).collect(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        copies,
+        vec![
+            ("libssl.so.1.0.0", false, vec!["app", "libssl.so.1.0.0"]),
+            (
+                "libssl.so.1.1",
+                true,
+                vec!["app", "libcurl.so.4", "libssl.so.1.1"]
+            ),
+        ]
+    );
+}
+
+/// Two names for the same file are one copy, not two versions.
+#[test]
+fn one_library_under_two_names_is_not_a_conflict() {
+    let mut bundled = lib("libssl.so.1.1", &[], LibraryPriority::Rpath);
+    bundled.names.push("libssl.so".to_string());
+    let result = app(&["libssl.so", "libssl.so.1.1"], vec![bundled]).verify(&|_, _| None);
+
+    assert!(
+        result.version_conflicts.is_empty(),
+        "got {:?}",
+        result.version_conflicts
+    );
+}